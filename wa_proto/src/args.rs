@@ -0,0 +1,103 @@
+//! Buffers for the args written by Outcoming::args in wasm.
+
+use crate::{ProtocolError, ARGS_BUF_FULL_ERROR, SLICE_LEN_ERROR};
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::vec::Vec;
+
+/**
+Destination of the args: `Vec<u32>` with alloc
+or `ArgsBuf` over a caller-provided `&mut [u32]` without alloc.
+*/
+pub trait ArgsWriter {
+    /**
+    Append an arg, fails if the buffer is full.
+    */
+    fn push(&mut self, arg: u32) -> Result<(), ProtocolError>;
+
+    /**
+    Replace a written arg, for the counts known after the args they count, like the frames.
+    */
+    fn set(&mut self, index: usize, arg: u32) -> Result<(), ProtocolError>;
+
+    /**
+    Count of the written args.
+    */
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl ArgsWriter for Vec<u32> {
+    fn push(&mut self, arg: u32) -> Result<(), ProtocolError> {
+        Vec::push(self, arg);
+        Ok(())
+    }
+
+    fn set(&mut self, index: usize, arg: u32) -> Result<(), ProtocolError> {
+        let slot = self
+            .get_mut(index)
+            .ok_or_else(|| ProtocolError::from(SLICE_LEN_ERROR))?;
+        *slot = arg;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+}
+
+/**
+Args written into a fixed buffer, for guests without an allocator.
+*/
+pub struct ArgsBuf<'a> {
+    buf: &'a mut [u32],
+    len: usize,
+}
+
+impl<'a> ArgsBuf<'a> {
+    pub fn new(buf: &'a mut [u32]) -> Self {
+        ArgsBuf { buf, len: 0 }
+    }
+
+    /**
+    Written args.
+    */
+    pub fn as_slice(&self) -> &[u32] {
+        &self.buf[..self.len]
+    }
+
+    /**
+    Written args, mutable for the init step in the same module.
+    */
+    pub fn as_mut_slice(&mut self) -> &mut [u32] {
+        &mut self.buf[..self.len]
+    }
+}
+
+impl ArgsWriter for ArgsBuf<'_> {
+    fn push(&mut self, arg: u32) -> Result<(), ProtocolError> {
+        let slot = self
+            .buf
+            .get_mut(self.len)
+            .ok_or_else(|| ProtocolError::from(ARGS_BUF_FULL_ERROR))?;
+        *slot = arg;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn set(&mut self, index: usize, arg: u32) -> Result<(), ProtocolError> {
+        let slot = self
+            .as_mut_slice()
+            .get_mut(index)
+            .ok_or_else(|| ProtocolError::from(SLICE_LEN_ERROR))?;
+        *slot = arg;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
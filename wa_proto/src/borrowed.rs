@@ -0,0 +1,427 @@
+//! Borrowed reading: `&'a str` and `&'a [u8]` point into the memory of the guest
+//! instead of being copied into the args. Their args are the len and the pointer of the bytes,
+//! so the guest keeps the value alive until the host has read it.
+
+#[cfg(feature = "alloc")]
+use crate::Bytes;
+#[cfg(feature = "host")]
+use crate::{array_try_from_fn, ARGS_NEXT_ERROR, MAP_INSERT_ERROR, TRAILING_ARGS_ERROR};
+#[cfg(feature = "guest")]
+use crate::{usize_arg, ArgsWriter};
+#[cfg(any(feature = "guest", feature = "host"))]
+use crate::{ProtocolError, SLICE_LEN_ERROR};
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{boxed::Box, collections::BTreeMap, string::String, vec::Vec};
+#[cfg(any(feature = "std", feature = "hashmap"))]
+use core::hash::{BuildHasher, Hash};
+#[cfg(feature = "host")]
+use core::slice::Iter;
+#[cfg(all(not(feature = "std"), feature = "hashmap"))]
+use hashbrown::HashMap;
+#[cfg(feature = "std")]
+use std::collections::{BTreeMap, HashMap};
+
+/**
+OutcomingRef - исходящее сообщение, которое хост читает без копирования:
+ссылки `&'a str` и `&'a [u8]` указывают в память песочницы `heap`.
+'b - время жизни аргументов, значение может ссылаться на них,
+только если оба времени жизни совпадают, остальные типы реализуют трейт для любого 'b.
+Типы без ссылок читаются через Outcoming, derive(Outcoming) реализует оба трейта.
+*/
+pub trait OutcomingRef<'a, 'b>: Sized {
+    /**
+    Количество аргументов, которые добавит args в wasm.
+    */
+    fn size_hint(&self) -> usize;
+
+    /**
+    Заполнение массива чисел, для ссылок - длина и указатель в памяти wasm.
+    Вызывается в wasm.
+    */
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError>;
+
+    /**
+    Чтение со ссылками на память wasm и на аргументы, без копирования байт.
+    Вызывается на хосте.
+    */
+    #[cfg(feature = "host")]
+    fn read_ref(heap: &'a [u8], args: &mut Iter<'b, u32>) -> Result<Self, ProtocolError>;
+}
+
+/**
+OutcomingRef of a type without references, the same args as its Outcoming.
+`@impl [generics] type` is one generic type, the attributes go on its impl.
+*/
+macro_rules! outcoming_ref_by_value {
+    (@impl [$($generics:tt)*] $(#[$attr:meta])* $typ:ty) => {
+        $(#[$attr])*
+        impl<'a, 'b, $($generics)*> crate::OutcomingRef<'a, 'b> for $typ {
+            fn size_hint(&self) -> usize {
+                crate::Outcoming::size_hint(self)
+            }
+
+            crate::__guest! {
+                fn args<W: crate::ArgsWriter>(&self, args: &mut W) -> Result<(), crate::ProtocolError> {
+                    crate::Outcoming::args(self, args)
+                }
+            }
+
+            crate::__host! {
+                fn read_ref(heap: &'a [u8], args: &mut core::slice::Iter<'b, u32>) -> Result<Self, crate::ProtocolError> {
+                    <Self as crate::Outcoming>::read(heap, args)
+                }
+            }
+        }
+    };
+    ($($(#[$attr:meta])* $typ:ty),* $(,)?) => {
+        $(
+            outcoming_ref_by_value!(@impl [] $(#[$attr])* $typ);
+        )*
+    };
+}
+
+outcoming_ref_by_value!(
+    bool,
+    u8,
+    i32,
+    u32,
+    usize,
+    isize,
+    f32,
+    u64,
+    i64,
+    f64,
+    u128,
+    i128,
+    core::time::Duration
+);
+
+#[cfg(feature = "std")]
+outcoming_ref_by_value!(std::time::SystemTime);
+
+#[cfg(feature = "alloc")]
+outcoming_ref_by_value!(String, Bytes);
+
+#[cfg(all(feature = "serde", feature = "alloc"))]
+outcoming_ref_by_value!(@impl [T: serde::Serialize + serde::de::DeserializeOwned, C: crate::Codec] crate::Serde<T, C>);
+
+#[cfg(feature = "heapless")]
+outcoming_ref_by_value!(@impl [const N: usize] heapless::String<N>);
+
+#[cfg(feature = "heapless")]
+outcoming_ref_by_value!(@impl [T: crate::Outcoming, const N: usize] heapless::Vec<T, N>);
+
+#[cfg(feature = "chrono")]
+outcoming_ref_by_value!(
+    chrono::Duration,
+    chrono::DateTime<chrono::Utc>,
+    chrono::DateTime<chrono::FixedOffset>,
+    #[allow(deprecated)]
+    chrono::Date<chrono::Utc>,
+    chrono::NaiveDate,
+    chrono::NaiveTime,
+    chrono::NaiveDateTime,
+    chrono::Weekday,
+    chrono::Month,
+);
+
+#[cfg(feature = "time")]
+outcoming_ref_by_value!(
+    time::Duration,
+    time::OffsetDateTime,
+    time::Date,
+    time::Time,
+    time::PrimitiveDateTime,
+    time::UtcOffset,
+    time::Weekday,
+    time::Month,
+);
+
+/**
+Args of borrowed bytes: the len and the pointer, wasm32 pointers fit in an arg.
+*/
+#[cfg(feature = "guest")]
+fn slice_args<W: ArgsWriter>(bytes: &[u8], args: &mut W) -> Result<(), ProtocolError> {
+    let ptr =
+        u32::try_from(bytes.as_ptr() as usize).map_err(|_| ProtocolError::from(SLICE_LEN_ERROR))?;
+    args.push(usize_arg(bytes.len())?)?;
+    args.push(ptr)
+}
+
+/**
+Bytes of the heap at the len and the pointer from the args.
+*/
+#[cfg(feature = "host")]
+fn heap_slice<'a>(heap: &'a [u8], args: &mut Iter<u32>) -> Result<&'a [u8], ProtocolError> {
+    let mut next = || {
+        args.next()
+            .map(|word| *word as usize)
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))
+    };
+    let len = next()?;
+    let ptr = next()?;
+    ptr.checked_add(len)
+        .and_then(|end| heap.get(ptr..end))
+        .ok_or_else(|| ProtocolError::from(SLICE_LEN_ERROR))
+}
+
+impl<'a, 'b> OutcomingRef<'a, 'b> for &'a [u8] {
+    fn size_hint(&self) -> usize {
+        2
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        slice_args(self, args)
+    }
+
+    #[cfg(feature = "host")]
+    fn read_ref(heap: &'a [u8], args: &mut Iter<'b, u32>) -> Result<Self, ProtocolError> {
+        heap_slice(heap, args)
+    }
+}
+
+impl<'a, 'b> OutcomingRef<'a, 'b> for &'a str {
+    fn size_hint(&self) -> usize {
+        2
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        slice_args(self.as_bytes(), args)
+    }
+
+    #[cfg(feature = "host")]
+    fn read_ref(heap: &'a [u8], args: &mut Iter<'b, u32>) -> Result<Self, ProtocolError> {
+        Ok(core::str::from_utf8(heap_slice(heap, args)?)?)
+    }
+}
+
+impl<'a, 'b, T: OutcomingRef<'a, 'b>> OutcomingRef<'a, 'b> for Option<T> {
+    fn size_hint(&self) -> usize {
+        1 + self.as_ref().map_or(0, T::size_hint)
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        match self {
+            None => args.push(0),
+            Some(item) => {
+                args.push(1)?;
+                item.args(args)
+            }
+        }
+    }
+
+    #[cfg(feature = "host")]
+    fn read_ref(heap: &'a [u8], args: &mut Iter<'b, u32>) -> Result<Self, ProtocolError> {
+        match *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?
+        {
+            0 => Ok(None),
+            _ => Ok(Some(T::read_ref(heap, args)?)),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, 'b, T: OutcomingRef<'a, 'b>> OutcomingRef<'a, 'b> for Vec<T> {
+    fn size_hint(&self) -> usize {
+        1 + self.iter().map(T::size_hint).sum::<usize>()
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        args.push(usize_arg(self.len())?)?;
+        self.iter().try_for_each(|item| item.args(args))
+    }
+
+    #[cfg(feature = "host")]
+    fn read_ref(heap: &'a [u8], args: &mut Iter<'b, u32>) -> Result<Self, ProtocolError> {
+        let len = next_len(args)?;
+        let mut items = Vec::with_capacity(len.min(args.len()));
+        for _ in 0..len {
+            items.push(T::read_ref(heap, args)?);
+        }
+        Ok(items)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, 'b, T: OutcomingRef<'a, 'b>> OutcomingRef<'a, 'b> for Box<T> {
+    fn size_hint(&self) -> usize {
+        T::size_hint(self)
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        T::args(self, args)
+    }
+
+    #[cfg(feature = "host")]
+    fn read_ref(heap: &'a [u8], args: &mut Iter<'b, u32>) -> Result<Self, ProtocolError> {
+        Ok(Box::new(T::read_ref(heap, args)?))
+    }
+}
+
+impl<'a, 'b, T: OutcomingRef<'a, 'b>, const N: usize> OutcomingRef<'a, 'b> for [T; N] {
+    fn size_hint(&self) -> usize {
+        self.iter().map(T::size_hint).sum()
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        self.iter().try_for_each(|item| item.args(args))
+    }
+
+    #[cfg(feature = "host")]
+    fn read_ref(heap: &'a [u8], args: &mut Iter<'b, u32>) -> Result<Self, ProtocolError> {
+        array_try_from_fn(|| T::read_ref(heap, args))
+    }
+}
+
+#[cfg(any(feature = "std", feature = "hashmap"))]
+impl<'a, 'b, K, V, S> OutcomingRef<'a, 'b> for HashMap<K, V, S>
+where
+    K: OutcomingRef<'a, 'b> + Eq + Hash,
+    V: OutcomingRef<'a, 'b>,
+    S: BuildHasher + Default,
+{
+    fn size_hint(&self) -> usize {
+        1 + self
+            .iter()
+            .map(|(key, value)| key.size_hint() + value.size_hint())
+            .sum::<usize>()
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        args.push(usize_arg(self.len())?)?;
+        for (key, value) in self {
+            key.args(args)?;
+            value.args(args)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "host")]
+    fn read_ref(heap: &'a [u8], args: &mut Iter<'b, u32>) -> Result<Self, ProtocolError> {
+        let len = next_len(args)?;
+        let mut map = HashMap::with_capacity_and_hasher(len.min(args.len()), S::default());
+        for _ in 0..len {
+            let key = K::read_ref(heap, args)?;
+            let value = V::read_ref(heap, args)?;
+            if map.insert(key, value).is_some() {
+                return Err(ProtocolError::from(MAP_INSERT_ERROR));
+            }
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, 'b, K, V> OutcomingRef<'a, 'b> for BTreeMap<K, V>
+where
+    K: OutcomingRef<'a, 'b> + Ord,
+    V: OutcomingRef<'a, 'b>,
+{
+    fn size_hint(&self) -> usize {
+        1 + self
+            .iter()
+            .map(|(key, value)| key.size_hint() + value.size_hint())
+            .sum::<usize>()
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        args.push(usize_arg(self.len())?)?;
+        for (key, value) in self {
+            key.args(args)?;
+            value.args(args)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "host")]
+    fn read_ref(heap: &'a [u8], args: &mut Iter<'b, u32>) -> Result<Self, ProtocolError> {
+        let len = next_len(args)?;
+        let mut map = BTreeMap::new();
+        for _ in 0..len {
+            let key = K::read_ref(heap, args)?;
+            let value = V::read_ref(heap, args)?;
+            if map.insert(key, value).is_some() {
+                return Err(ProtocolError::from(MAP_INSERT_ERROR));
+            }
+        }
+        Ok(map)
+    }
+}
+
+/**
+OutcomingRef of a tuple: the elements one after another, like its Outcoming.
+*/
+macro_rules! tuple_ref_impls {
+    ($($name:ident $var:ident $idx:tt),*) => {
+        impl<'a, 'b, $($name: OutcomingRef<'a, 'b>),*> OutcomingRef<'a, 'b> for ($($name,)*) {
+            fn size_hint(&self) -> usize {
+                0 $(+ self.$idx.size_hint())*
+            }
+
+            #[cfg(feature = "guest")]
+            #[allow(unused_variables)]
+            fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+                $(self.$idx.args(args)?;)*
+                Ok(())
+            }
+
+            #[cfg(feature = "host")]
+            #[allow(unused_variables)]
+            fn read_ref(heap: &'a [u8], args: &mut Iter<'b, u32>) -> Result<Self, ProtocolError> {
+                $(let $var = $name::read_ref(heap, args)?;)*
+                Ok(($($var,)*))
+            }
+        }
+    };
+}
+
+tuple_ref_impls!();
+tuple_ref_impls!(T1 t1 0);
+tuple_ref_impls!(T1 t1 0, T2 t2 1);
+tuple_ref_impls!(T1 t1 0, T2 t2 1, T3 t3 2);
+tuple_ref_impls!(T1 t1 0, T2 t2 1, T3 t3 2, T4 t4 3);
+tuple_ref_impls!(T1 t1 0, T2 t2 1, T3 t3 2, T4 t4 3, T5 t5 4);
+tuple_ref_impls!(T1 t1 0, T2 t2 1, T3 t3 2, T4 t4 3, T5 t5 4, T6 t6 5);
+tuple_ref_impls!(T1 t1 0, T2 t2 1, T3 t3 2, T4 t4 3, T5 t5 4, T6 t6 5, T7 t7 6);
+tuple_ref_impls!(T1 t1 0, T2 t2 1, T3 t3 2, T4 t4 3, T5 t5 4, T6 t6 5, T7 t7 6, T8 t8 7);
+tuple_ref_impls!(T1 t1 0, T2 t2 1, T3 t3 2, T4 t4 3, T5 t5 4, T6 t6 5, T7 t7 6, T8 t8 7, T9 t9 8);
+tuple_ref_impls!(T1 t1 0, T2 t2 1, T3 t3 2, T4 t4 3, T5 t5 4, T6 t6 5, T7 t7 6, T8 t8 7, T9 t9 8, T10 t10 9);
+tuple_ref_impls!(T1 t1 0, T2 t2 1, T3 t3 2, T4 t4 3, T5 t5 4, T6 t6 5, T7 t7 6, T8 t8 7, T9 t9 8, T10 t10 9, T11 t11 10);
+tuple_ref_impls!(T1 t1 0, T2 t2 1, T3 t3 2, T4 t4 3, T5 t5 4, T6 t6 5, T7 t7 6, T8 t8 7, T9 t9 8, T10 t10 9, T11 t11 10, T12 t12 11);
+
+/**
+Len of a sequence or a map. It comes from the guest, so the capacity is bounded by the args left.
+*/
+#[cfg(feature = "host")]
+fn next_len(args: &mut Iter<u32>) -> Result<usize, ProtocolError> {
+    args.next()
+        .map(|len| *len as usize)
+        .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))
+}
+
+/**
+Read a value with references into the heap from all the args.
+*/
+#[cfg(feature = "host")]
+pub fn read_ref<'a, 'b, T: OutcomingRef<'a, 'b>>(
+    heap: &'a [u8],
+    args: &'b [u32],
+) -> Result<T, ProtocolError> {
+    let mut args = args.iter();
+    let value = T::read_ref(heap, &mut args)?;
+    if args.len() > 0 {
+        return Err(ProtocolError::from(TRAILING_ARGS_ERROR));
+    }
+    Ok(value)
+}
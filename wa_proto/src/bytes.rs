@@ -0,0 +1,89 @@
+//! Bytes of an args stream for the disk and the transport between processes:
+//! the header - magic `wa`, format version (u16) and count of the args (u32),
+//! then the args, all in little endian.
+
+use crate::{
+    usize_arg, ProtocolError, Schema, BYTES_HEADER_ERROR, FORMAT_VERSION, PACKED_FORMAT_VERSION,
+    SLICE_LEN_ERROR,
+};
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::vec::Vec;
+
+pub const BYTES_MAGIC: [u8; 2] = *b"wa";
+
+pub const HEADER_LEN: usize = 8;
+
+/**
+Write the header and the args, more args than a u32 counts is an error.
+*/
+pub fn to_bytes(args: &[u32]) -> Result<Vec<u8>, ProtocolError> {
+    to_bytes_with_version(args, FORMAT_VERSION)
+}
+
+/**
+Write the header with the given version and the args, PACKED_FORMAT_VERSION for packed structs.
+*/
+pub fn to_bytes_with_version(args: &[u32], version: u16) -> Result<Vec<u8>, ProtocolError> {
+    let count = usize_arg(args.len())?;
+    let mut bytes: Vec<u8> = Vec::with_capacity(HEADER_LEN + args.len() * 4);
+    bytes.extend_from_slice(&BYTES_MAGIC);
+    bytes.extend_from_slice(&version.to_le_bytes());
+    bytes.extend_from_slice(&count.to_le_bytes());
+    for arg in args {
+        bytes.extend_from_slice(&arg.to_le_bytes());
+    }
+    Ok(bytes)
+}
+
+/**
+Write the header with the version of the layout of `T` (`T::FORMAT_VERSION`) and its args.
+*/
+pub fn to_bytes_of<T: Schema>(args: &[u32]) -> Result<Vec<u8>, ProtocolError> {
+    to_bytes_with_version(args, T::FORMAT_VERSION)
+}
+
+/**
+Read the args, checks the header and the count of the args.
+*/
+pub fn from_bytes(bytes: &[u8]) -> Result<Vec<u32>, ProtocolError> {
+    let (version, args) = from_bytes_with_version(bytes)?;
+    if version != FORMAT_VERSION {
+        return Err(ProtocolError::from(BYTES_HEADER_ERROR));
+    }
+    Ok(args)
+}
+
+/**
+Read the version and the args, the version is FORMAT_VERSION or PACKED_FORMAT_VERSION.
+*/
+pub fn from_bytes_with_version(bytes: &[u8]) -> Result<(u16, Vec<u32>), ProtocolError> {
+    if bytes.len() < HEADER_LEN || bytes[0..2] != BYTES_MAGIC {
+        return Err(ProtocolError::from(BYTES_HEADER_ERROR));
+    }
+    let version = u16::from_le_bytes(bytes[2..4].try_into()?);
+    if version != FORMAT_VERSION && version != PACKED_FORMAT_VERSION {
+        return Err(ProtocolError::from(BYTES_HEADER_ERROR));
+    }
+    let count = u32::from_le_bytes(bytes[4..8].try_into()?) as usize;
+    let body = &bytes[HEADER_LEN..];
+    if count.checked_mul(4) != Some(body.len()) {
+        return Err(ProtocolError::from(SLICE_LEN_ERROR));
+    }
+    let mut args: Vec<u32> = Vec::with_capacity(count);
+    for chunk in body.chunks_exact(4) {
+        args.push(u32::from_le_bytes(chunk.try_into()?));
+    }
+    Ok((version, args))
+}
+
+/**
+Read the args of a `T`, the version in the header must be the version of the layout of `T`,
+so the packed args aren't read as unpacked ones and back.
+*/
+pub fn from_bytes_of<T: Schema>(bytes: &[u8]) -> Result<Vec<u32>, ProtocolError> {
+    let (version, args) = from_bytes_with_version(bytes)?;
+    if version != T::FORMAT_VERSION {
+        return Err(ProtocolError::from(BYTES_HEADER_ERROR));
+    }
+    Ok(args)
+}
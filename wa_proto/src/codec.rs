@@ -0,0 +1,275 @@
+//! Serde<T> - any serde type as an opaque payload: len in bytes and 4 bytes per arg, like Bytes.
+//! The payload is encoded by a Codec: the wa_proto serde format by default, or bincode/postcard.
+
+#[cfg(feature = "host")]
+use crate::bytes_words;
+#[cfg(feature = "guest")]
+use crate::ArgsWriter;
+#[cfg(any(feature = "guest", feature = "host"))]
+use crate::{bytes_args, bytes_from_args, ARGS_NEXT_ERROR};
+use crate::{
+    from_args, from_compact, to_args, to_compact, Incoming, Outcoming, ProtocolError, Schema,
+    TypeSchema,
+};
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::vec::Vec;
+#[cfg(feature = "guest")]
+use core::slice::IterMut;
+#[cfg(feature = "host")]
+use core::{cell::RefMut, slice::Iter};
+use core::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+/**
+Encoding of the Serde payload.
+*/
+pub trait Codec {
+    /// Name of the codec in the schema of `Serde<T, Self>`
+    const NAME: &'static str;
+
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, ProtocolError>;
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ProtocolError>;
+}
+
+/**
+The wa_proto serde format, the args are stored as little endian bytes.
+*/
+pub struct ArgsCodec;
+
+impl Codec for ArgsCodec {
+    const NAME: &'static str = "args";
+
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, ProtocolError> {
+        let mut args: Vec<u32> = Vec::new();
+        to_args(value, &mut args)?;
+        Ok(args.iter().flat_map(|arg| arg.to_le_bytes()).collect())
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ProtocolError> {
+        let args: Vec<u32> = bytes
+            .chunks(4)
+            .map(|chunk| Ok(u32::from_le_bytes(chunk.try_into()?)))
+            .collect::<Result<_, ProtocolError>>()?;
+        from_args(&args)
+    }
+}
+
+/**
+The compact varint format, for payloads of small integers and short strings.
+*/
+pub struct CompactCodec;
+
+impl Codec for CompactCodec {
+    const NAME: &'static str = "compact";
+
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, ProtocolError> {
+        to_compact(value)
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ProtocolError> {
+        from_compact(bytes)
+    }
+}
+
+#[cfg(feature = "bincode")]
+pub struct Bincode;
+
+#[cfg(feature = "bincode")]
+impl Codec for Bincode {
+    const NAME: &'static str = "bincode";
+
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, ProtocolError> {
+        bincode::serialize(value).map_err(|e| ProtocolError(e.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ProtocolError> {
+        bincode::deserialize(bytes).map_err(|e| ProtocolError(e.to_string()))
+    }
+}
+
+#[cfg(feature = "postcard")]
+pub struct Postcard;
+
+#[cfg(all(feature = "postcard", feature = "std"))]
+impl Codec for Postcard {
+    const NAME: &'static str = "postcard";
+
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, ProtocolError> {
+        postcard::to_allocvec(value).map_err(|e| ProtocolError(e.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ProtocolError> {
+        postcard::from_bytes(bytes).map_err(|e| ProtocolError(e.to_string()))
+    }
+}
+
+#[cfg(all(feature = "postcard", not(feature = "std")))]
+impl Codec for Postcard {
+    const NAME: &'static str = "postcard";
+
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, ProtocolError> {
+        postcard::to_allocvec(value).map_err(|_| ProtocolError::from(crate::CODEC_ERROR))
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ProtocolError> {
+        postcard::from_bytes(bytes).map_err(|_| ProtocolError::from(crate::CODEC_ERROR))
+    }
+}
+
+/**
+Wrapper for the serde types inside the derived structs.
+`size_hint` encodes the value to measure the payload and `args` encodes it again,
+an encoding error comes out of `args`.
+*/
+pub struct Serde<T, C: Codec = ArgsCodec> {
+    value: T,
+    codec: PhantomData<C>,
+}
+
+/**
+Serde payload in the compact varint format.
+*/
+pub type Compact<T> = Serde<T, CompactCodec>;
+
+impl<T, C: Codec> Serde<T, C> {
+    pub fn new(value: T) -> Self {
+        Serde {
+            value,
+            codec: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T, C: Codec> From<T> for Serde<T, C> {
+    fn from(value: T) -> Self {
+        Serde::new(value)
+    }
+}
+
+impl<T, C: Codec> Deref for Serde<T, C> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, C: Codec> DerefMut for Serde<T, C> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: Clone, C: Codec> Clone for Serde<T, C> {
+    fn clone(&self) -> Self {
+        Serde::new(self.value.clone())
+    }
+}
+
+impl<T: PartialEq, C: Codec> PartialEq for Serde<T, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T: core::fmt::Debug, C: Codec> core::fmt::Debug for Serde<T, C> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("Serde").field(&self.value).finish()
+    }
+}
+
+/// The codec and the type inside are in the schema, so a changed payload type is a mismatch too.
+impl<T: Schema, C: Codec> Schema for Serde<T, C> {
+    const SCHEMA: &'static TypeSchema = &TypeSchema::Payload(C::NAME, T::SCHEMA);
+}
+
+impl<T: Serialize, C: Codec> Serde<T, C> {
+    /**
+    Count of the args of the payload, 0 if it can't be encoded: then args returns the error.
+    */
+    fn payload_words(&self) -> usize {
+        C::encode(&self.value).map_or(0, |bytes| 1 + bytes.len().div_ceil(4))
+    }
+}
+
+/**
+Bytes of the payload, `left` is the count of the args after the len:
+the len comes from the other side, so the capacity is bounded by the bytes the args can hold.
+*/
+#[cfg(any(feature = "guest", feature = "host"))]
+fn payload_from_args(
+    left: usize,
+    mut next: impl FnMut() -> Option<u32>,
+) -> Result<Vec<u8>, ProtocolError> {
+    let len = next().ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+    let mut bytes: Vec<u8> = Vec::with_capacity(len.min(left.saturating_mul(4)));
+    bytes_from_args(len, next, |byte| {
+        bytes.push(byte);
+        Ok(())
+    })?;
+    Ok(bytes)
+}
+
+impl<T: Serialize + DeserializeOwned, C: Codec> Incoming for Serde<T, C> {
+    /// Encodes the value to know the len of the payload, args sends the same payload.
+    fn size_hint(&self) -> usize {
+        self.payload_words()
+    }
+
+    #[cfg(feature = "guest")]
+    fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
+        let left = args.len().saturating_sub(1);
+        let bytes = payload_from_args(left, || args.next().map(|u| *u))?;
+        Ok((0, Serde::new(C::decode(&bytes)?)))
+    }
+
+    #[cfg(feature = "host")]
+    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
+        bytes_args(&C::encode(&self.value)?, args)
+    }
+
+    #[cfg(feature = "host")]
+    fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+        for _ in 0..len.div_ceil(4) {
+            args.next()
+                .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Serialize + DeserializeOwned, C: Codec> Outcoming for Serde<T, C> {
+    /// Encodes the value to know the len of the payload, args sends the same payload.
+    fn size_hint(&self) -> usize {
+        self.payload_words()
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        bytes_args(&C::encode(&self.value)?, args)
+    }
+
+    #[cfg(feature = "host")]
+    fn read(_: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
+        let left = args.len().saturating_sub(1);
+        let bytes = payload_from_args(left, || args.next().copied())?;
+        Ok(Serde::new(C::decode(&bytes)?))
+    }
+
+    /// Only jumps over the payload, the default validate decodes it.
+    #[cfg(feature = "host")]
+    fn skip(args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        bytes_words(args).map(drop)
+    }
+}
@@ -0,0 +1,716 @@
+//! Compact serde format over bytes for chatty protocols, where the size of the args dominates:
+//! - integers and chars - LEB128 varint, signed integers are zigzag encoded first
+//! - bool, u8 and i8 - one byte, f32 and f64 - little endian bytes
+//! - str and bytes - varint len and the bytes
+//! - Option - 0 or 1 byte and the value
+//! - seq and map - varint len and the items, tuples and structs - only the fields
+//! - enum variant - varint index of the variant and the fields
+//!
+//! Select it per call with `to_compact`/`from_compact` or per type with `Compact<T>`.
+
+use crate::{
+    ProtocolError, ARGS_NEXT_ERROR, SERDE_CUSTOM_ERROR, SERDE_UNSUPPORTED_ERROR,
+    TRAILING_ARGS_ERROR, VARINT_ERROR,
+};
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{string::String, vec::Vec};
+use core::fmt::{Display, Write};
+use serde::de::{
+    self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, Unexpected,
+    VariantAccess, Visitor,
+};
+use serde::{ser, Deserialize, Serialize};
+
+/**
+Serialize the value into the compact bytes.
+*/
+pub fn to_compact<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, ProtocolError> {
+    let mut bytes: Vec<u8> = Vec::new();
+    value.serialize(&mut CompactSerializer::new(&mut bytes))?;
+    Ok(bytes)
+}
+
+/**
+Deserialize the value from the compact bytes, all bytes must be read.
+*/
+pub fn from_compact<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<T, ProtocolError> {
+    let mut deserializer = CompactDeserializer::new(bytes);
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/**
+Append an unsigned LEB128 varint: 7 bits per byte, the high bit is set on all bytes but the last.
+*/
+pub fn write_varint(bytes: &mut Vec<u8>, mut value: u128) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/**
+Read an unsigned LEB128 varint, fails if it doesn't fit into `bits`.
+*/
+pub fn read_varint(bytes: &mut &[u8], bits: u32) -> Result<u128, ProtocolError> {
+    let mut value: u128 = 0;
+    let mut shift = 0;
+    loop {
+        let (&byte, rest) = bytes
+            .split_first()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
+        *bytes = rest;
+        let low = (byte & 0x7f) as u128;
+        if shift >= bits || (shift > 0 && low >> (bits - shift) != 0) {
+            return Err(ProtocolError::from(VARINT_ERROR));
+        }
+        value |= low << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+const fn zigzag(value: i128) -> u128 {
+    ((value << 1) ^ (value >> 127)) as u128
+}
+
+const fn unzigzag(value: u128) -> i128 {
+    (value >> 1) as i128 ^ -((value & 1) as i128)
+}
+
+pub struct CompactSerializer<'a> {
+    bytes: &'a mut Vec<u8>,
+}
+
+impl<'a> CompactSerializer<'a> {
+    pub fn new(bytes: &'a mut Vec<u8>) -> Self {
+        CompactSerializer { bytes }
+    }
+
+    fn varint(&mut self, value: u128) -> Result<(), ProtocolError> {
+        write_varint(self.bytes, value);
+        Ok(())
+    }
+
+    fn signed(&mut self, value: i128) -> Result<(), ProtocolError> {
+        self.varint(zigzag(value))
+    }
+
+    fn raw(&mut self, value: &[u8]) -> Result<(), ProtocolError> {
+        self.bytes.extend_from_slice(value);
+        Ok(())
+    }
+}
+
+impl ser::Serializer for &mut CompactSerializer<'_> {
+    type Ok = ();
+    type Error = ProtocolError;
+
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<(), ProtocolError> {
+        self.raw(&[v as u8])
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), ProtocolError> {
+        self.raw(&[v as u8])
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), ProtocolError> {
+        self.signed(v as i128)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), ProtocolError> {
+        self.signed(v as i128)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), ProtocolError> {
+        self.signed(v as i128)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), ProtocolError> {
+        self.signed(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), ProtocolError> {
+        self.raw(&[v])
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), ProtocolError> {
+        self.varint(v as u128)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), ProtocolError> {
+        self.varint(v as u128)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), ProtocolError> {
+        self.varint(v as u128)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), ProtocolError> {
+        self.varint(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), ProtocolError> {
+        self.raw(&v.to_le_bytes())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), ProtocolError> {
+        self.raw(&v.to_le_bytes())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), ProtocolError> {
+        self.varint(v as u128)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), ProtocolError> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), ProtocolError> {
+        self.varint(v.len() as u128)?;
+        self.raw(v)
+    }
+
+    fn serialize_none(self) -> Result<(), ProtocolError> {
+        self.raw(&[0])
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), ProtocolError> {
+        self.raw(&[1])?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        variant_index: u32,
+        _: &'static str,
+    ) -> Result<(), ProtocolError> {
+        self.varint(variant_index as u128)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), ProtocolError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        variant_index: u32,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), ProtocolError> {
+        self.varint(variant_index as u128)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self, ProtocolError> {
+        let len = len.ok_or_else(|| ProtocolError::from(SERDE_UNSUPPORTED_ERROR))?;
+        self.varint(len as u128)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self, ProtocolError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self, ProtocolError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        variant_index: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self, ProtocolError> {
+        self.varint(variant_index as u128)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self, ProtocolError> {
+        self.serialize_seq(len)
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, ProtocolError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        variant_index: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self, ProtocolError> {
+        self.varint(variant_index as u128)?;
+        Ok(self)
+    }
+
+    /// The len is known only after formatting, so the value is formatted into a String.
+    fn collect_str<T: Display + ?Sized>(self, value: &T) -> Result<(), ProtocolError> {
+        let mut s = String::new();
+        write!(s, "{}", value).map_err(|_| ProtocolError::from(SERDE_CUSTOM_ERROR))?;
+        self.serialize_str(&s)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl ser::SerializeSeq for &mut CompactSerializer<'_> {
+    type Ok = ();
+    type Error = ProtocolError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ProtocolError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut CompactSerializer<'_> {
+    type Ok = ();
+    type Error = ProtocolError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ProtocolError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut CompactSerializer<'_> {
+    type Ok = ();
+    type Error = ProtocolError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ProtocolError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut CompactSerializer<'_> {
+    type Ok = ();
+    type Error = ProtocolError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ProtocolError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut CompactSerializer<'_> {
+    type Ok = ();
+    type Error = ProtocolError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), ProtocolError> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ProtocolError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut CompactSerializer<'_> {
+    type Ok = ();
+    type Error = ProtocolError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), ProtocolError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut CompactSerializer<'_> {
+    type Ok = ();
+    type Error = ProtocolError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), ProtocolError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+}
+
+pub struct CompactDeserializer<'de> {
+    bytes: &'de [u8],
+}
+
+impl<'de> CompactDeserializer<'de> {
+    pub fn new(bytes: &'de [u8]) -> Self {
+        CompactDeserializer { bytes }
+    }
+
+    /**
+    Checks that all bytes are read.
+    */
+    pub fn end(&self) -> Result<(), ProtocolError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(ProtocolError::from(TRAILING_ARGS_ERROR))
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, ProtocolError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'de [u8], ProtocolError> {
+        if self.bytes.len() < len {
+            return Err(ProtocolError::from(ARGS_NEXT_ERROR));
+        }
+        let (head, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn varint(&mut self, bits: u32) -> Result<u128, ProtocolError> {
+        read_varint(&mut self.bytes, bits)
+    }
+
+    fn signed(&mut self, bits: u32) -> Result<i128, ProtocolError> {
+        Ok(unzigzag(self.varint(bits)?))
+    }
+
+    fn len(&mut self) -> Result<usize, ProtocolError> {
+        Ok(self.varint(u32::BITS)? as usize)
+    }
+
+    fn bytes(&mut self) -> Result<&'de [u8], ProtocolError> {
+        let len = self.len()?;
+        self.take(len)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut CompactDeserializer<'de> {
+    type Error = ProtocolError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, ProtocolError> {
+        Err(ProtocolError::from(SERDE_UNSUPPORTED_ERROR))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_bool(self.byte()? != 0)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_i8(self.byte()? as i8)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_i16(self.signed(i16::BITS)? as i16)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_i32(self.signed(i32::BITS)? as i32)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_i64(self.signed(i64::BITS)? as i64)
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_i128(self.signed(i128::BITS)?)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_u8(self.byte()?)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_u16(self.varint(u16::BITS)? as u16)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_u32(self.varint(u32::BITS)? as u32)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_u64(self.varint(u64::BITS)? as u64)
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_u128(self.varint(u128::BITS)?)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_f32(f32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_f64(f64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        let u = self.varint(u32::BITS)? as u32;
+        let c = char::from_u32(u).ok_or_else(|| {
+            <ProtocolError as de::Error>::invalid_value(Unexpected::Unsigned(u as u64), &"a char")
+        })?;
+        visitor.visit_char(c)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_borrowed_str(core::str::from_utf8(self.bytes()?)?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_borrowed_bytes(self.bytes()?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        if self.byte()? != 0 {
+            visitor.visit_some(self)
+        } else {
+            visitor.visit_none()
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ProtocolError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ProtocolError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        let len = self.len()?;
+        visitor.visit_seq(Counted {
+            de: self,
+            left: len,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ProtocolError> {
+        visitor.visit_seq(Counted {
+            de: self,
+            left: len,
+        })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ProtocolError> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        let len = self.len()?;
+        visitor.visit_map(Counted {
+            de: self,
+            left: len,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ProtocolError> {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ProtocolError> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _: V) -> Result<V::Value, ProtocolError> {
+        Err(ProtocolError::from(SERDE_UNSUPPORTED_ERROR))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, ProtocolError> {
+        Err(ProtocolError::from(SERDE_UNSUPPORTED_ERROR))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/**
+Items of a seq, tuple or struct and entries of a map with a known count.
+*/
+struct Counted<'a, 'de> {
+    de: &'a mut CompactDeserializer<'de>,
+    left: usize,
+}
+
+impl<'de> SeqAccess<'de> for Counted<'_, 'de> {
+    type Error = ProtocolError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, ProtocolError> {
+        if self.left == 0 {
+            return Ok(None);
+        }
+        self.left -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left)
+    }
+}
+
+impl<'de> MapAccess<'de> for Counted<'_, 'de> {
+    type Error = ProtocolError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, ProtocolError> {
+        if self.left == 0 {
+            return Ok(None);
+        }
+        self.left -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, ProtocolError> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left)
+    }
+}
+
+impl<'de> EnumAccess<'de> for &mut CompactDeserializer<'de> {
+    type Error = ProtocolError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), ProtocolError> {
+        let index = self.varint(u32::BITS)? as u32;
+        let deserializer: de::value::U32Deserializer<ProtocolError> = index.into_deserializer();
+        let value = seed.deserialize(deserializer)?;
+        Ok((value, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for &mut CompactDeserializer<'de> {
+    type Error = ProtocolError;
+
+    fn unit_variant(self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, ProtocolError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ProtocolError> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ProtocolError> {
+        de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+    }
+}
@@ -0,0 +1,388 @@
+//! serde Deserializer from the args, mirrors `Incoming::init` and `Outcoming::read`.
+//! The format isn't self-describing, so `deserialize_any` isn't supported.
+
+use crate::{
+    u128_from_args, u64_from_args, ProtocolError, ARGS_NEXT_ERROR, SERDE_UNSUPPORTED_ERROR,
+    TRAILING_ARGS_ERROR,
+};
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{string::String, vec::Vec};
+use core::fmt::Display;
+use core::slice::Iter;
+use serde::de::{
+    self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, Unexpected,
+    VariantAccess, Visitor,
+};
+use serde::Deserialize;
+
+#[cfg(feature = "std")]
+impl de::Error for ProtocolError {
+    fn custom<T: Display>(msg: T) -> Self {
+        ProtocolError(msg.to_string())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl de::Error for ProtocolError {
+    fn custom<T: Display>(_: T) -> Self {
+        ProtocolError::from(crate::SERDE_CUSTOM_ERROR)
+    }
+}
+
+/**
+Deserialize the value from the args, all args must be read.
+*/
+pub fn from_args<'de, T: Deserialize<'de>>(args: &'de [u32]) -> Result<T, ProtocolError> {
+    let mut deserializer = Deserializer::new(args);
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+pub struct Deserializer<'de> {
+    args: Iter<'de, u32>,
+}
+
+impl<'de> Deserializer<'de> {
+    pub fn new(args: &'de [u32]) -> Self {
+        Deserializer { args: args.iter() }
+    }
+
+    /**
+    Checks that all args are read.
+    */
+    pub fn end(&self) -> Result<(), ProtocolError> {
+        if self.args.len() == 0 {
+            Ok(())
+        } else {
+            Err(ProtocolError::from(TRAILING_ARGS_ERROR))
+        }
+    }
+
+    fn next(&mut self) -> Result<u32, ProtocolError> {
+        self.args
+            .next()
+            .copied()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))
+    }
+
+    fn next_u64(&mut self) -> Result<u64, ProtocolError> {
+        Ok(u64_from_args([self.next()?, self.next()?]))
+    }
+
+    fn next_u128(&mut self) -> Result<u128, ProtocolError> {
+        Ok(u128_from_args([
+            self.next()?,
+            self.next()?,
+            self.next()?,
+            self.next()?,
+        ]))
+    }
+
+    #[cfg(feature = "alloc")]
+    fn next_bytes(&mut self) -> Result<Vec<u8>, ProtocolError> {
+        let len = self.next()? as usize;
+        // the len comes from the args, don't allocate more than they can hold
+        if len.div_ceil(4) > self.args.len() {
+            return Err(ProtocolError::from(ARGS_NEXT_ERROR));
+        }
+        let mut vec: Vec<u8> = Vec::with_capacity(len);
+        let mut rem = len;
+        while rem > 0 {
+            let count = rem.min(4);
+            vec.extend_from_slice(&self.next()?.to_le_bytes()[..count]);
+            rem -= count;
+        }
+        Ok(vec)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = ProtocolError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, ProtocolError> {
+        Err(ProtocolError::from(SERDE_UNSUPPORTED_ERROR))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_bool(self.next()? != 0)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_i8(self.next()? as i8)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_i16(self.next()? as i16)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_i32(self.next()? as i32)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_i64(self.next_u64()? as i64)
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_i128(self.next_u128()? as i128)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_u8(self.next()? as u8)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_u16(self.next()? as u16)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_u32(self.next()?)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_u64(self.next_u64()?)
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_u128(self.next_u128()?)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_f32(f32::from_bits(self.next()?))
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_f64(f64::from_bits(self.next_u64()?))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        let u = self.next()?;
+        let c = char::from_u32(u).ok_or_else(|| {
+            <ProtocolError as de::Error>::invalid_value(Unexpected::Unsigned(u as u64), &"a char")
+        })?;
+        visitor.visit_char(c)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_string(String::from_utf8(self.next_bytes()?)?)
+    }
+
+    /// Without alloc the packed bytes can't be decoded.
+    #[cfg(not(feature = "alloc"))]
+    fn deserialize_str<V: Visitor<'de>>(self, _: V) -> Result<V::Value, ProtocolError> {
+        Err(ProtocolError::from(SERDE_UNSUPPORTED_ERROR))
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        self.deserialize_str(visitor)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_byte_buf(self.next_bytes()?)
+    }
+
+    #[cfg(not(feature = "alloc"))]
+    fn deserialize_bytes<V: Visitor<'de>>(self, _: V) -> Result<V::Value, ProtocolError> {
+        Err(ProtocolError::from(SERDE_UNSUPPORTED_ERROR))
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        if self.next()? != 0 {
+            visitor.visit_some(self)
+        } else {
+            visitor.visit_none()
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ProtocolError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ProtocolError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        let len = self.next()? as usize;
+        visitor.visit_seq(Counted {
+            de: self,
+            left: len,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ProtocolError> {
+        visitor.visit_seq(Counted {
+            de: self,
+            left: len,
+        })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ProtocolError> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ProtocolError> {
+        let len = self.next()? as usize;
+        visitor.visit_map(Counted {
+            de: self,
+            left: len,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ProtocolError> {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ProtocolError> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _: V) -> Result<V::Value, ProtocolError> {
+        Err(ProtocolError::from(SERDE_UNSUPPORTED_ERROR))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, ProtocolError> {
+        Err(ProtocolError::from(SERDE_UNSUPPORTED_ERROR))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/**
+Items of a seq, tuple or struct and entries of a map with a known count.
+*/
+struct Counted<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    left: usize,
+}
+
+impl<'de> SeqAccess<'de> for Counted<'_, 'de> {
+    type Error = ProtocolError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, ProtocolError> {
+        if self.left == 0 {
+            return Ok(None);
+        }
+        self.left -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left)
+    }
+}
+
+impl<'de> MapAccess<'de> for Counted<'_, 'de> {
+    type Error = ProtocolError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, ProtocolError> {
+        if self.left == 0 {
+            return Ok(None);
+        }
+        self.left -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, ProtocolError> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.left)
+    }
+}
+
+impl<'de> EnumAccess<'de> for &mut Deserializer<'de> {
+    type Error = ProtocolError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), ProtocolError> {
+        let index = self.next()?;
+        let deserializer: de::value::U32Deserializer<ProtocolError> = index.into_deserializer();
+        let value = seed.deserialize(deserializer)?;
+        Ok((value, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for &mut Deserializer<'de> {
+    type Error = ProtocolError;
+
+    fn unit_variant(self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, ProtocolError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ProtocolError> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ProtocolError> {
+        de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+    }
+}
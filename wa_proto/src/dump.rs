@@ -0,0 +1,460 @@
+//! Readable dump of an args stream: every value of the schema with its args,
+//! like `len=3 | "abc" packed 0x00636261 | Some | u64 7 (lo 0x00000007, hi 0x00000000)`.
+//! The first place where the args don't match the schema is marked with `!!`
+//! and the dump stops there.
+
+use crate::{
+    bytes_words, frame_end, int_fits, mask, min_words, next, skip_to_frame_end, words, Schema,
+    SchemaTree, Value, NANOS_PER_SEC,
+};
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt, slice::Iter};
+
+/**
+Dump of the args of a value of the schema, the segments are separated by ` | `.
+*/
+pub fn dump_args(schema: &SchemaTree, args: &[u32]) -> String {
+    let mut dumper = Dumper {
+        args: args.iter(),
+        segments: Vec::new(),
+    };
+    if dumper.node("", schema).is_ok() && dumper.args.len() > 0 {
+        let rest: Vec<String> = dumper
+            .args
+            .as_slice()
+            .iter()
+            .map(|word| hex(*word))
+            .collect();
+        dumper.segments.push(format!(
+            "!! {} trailing args: {}",
+            rest.len(),
+            rest.join(" ")
+        ));
+    }
+    dumper.segments.join(" | ")
+}
+
+/**
+Args with the schema of their type, the Debug output is the dump.
+*/
+pub struct ArgsDump<'a> {
+    schema: SchemaTree,
+    args: &'a [u32],
+}
+
+impl<'a> ArgsDump<'a> {
+    pub fn new(schema: SchemaTree, args: &'a [u32]) -> Self {
+        ArgsDump { schema, args }
+    }
+
+    pub fn of<T: Schema>(args: &'a [u32]) -> Self {
+        ArgsDump::new(SchemaTree::from(T::SCHEMA), args)
+    }
+}
+
+impl fmt::Debug for ArgsDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&dump_args(&self.schema, self.args))
+    }
+}
+
+fn hex(word: u32) -> String {
+    format!("{:#010x}", word)
+}
+
+/**
+Stops the dump after a mismatch, the segment with `!!` is already written.
+*/
+struct Mismatch;
+
+struct Dumper<'a> {
+    args: Iter<'a, u32>,
+    segments: Vec<String>,
+}
+
+impl Dumper<'_> {
+    fn push(&mut self, path: &str, text: String) {
+        if path.is_empty() {
+            self.segments.push(text);
+        } else {
+            self.segments.push(format!("{}: {}", path, text));
+        }
+    }
+
+    fn mismatch(&mut self, path: &str, text: String) -> Mismatch {
+        self.push(path, format!("!! {}", text));
+        Mismatch
+    }
+
+    fn ended(&mut self, path: &str, expected: &str) -> Mismatch {
+        self.mismatch(path, format!("expected {}, the args ended", expected))
+    }
+
+    fn next(&mut self, path: &str, expected: &str) -> Result<u32, Mismatch> {
+        next(&mut self.args).map_err(|_| self.ended(path, expected))
+    }
+
+    /**
+    Len of a list or a map, the count of the items to dump: a len beyond the args left
+    is a mismatch, and only the first of the items without args is dumped, the others are the same.
+    */
+    fn items_len(&mut self, path: &str, min: usize) -> Result<u32, Mismatch> {
+        let len = self.next(path, "len")?;
+        let left = self.args.len();
+        if (len as usize).saturating_mul(min) > left {
+            return Err(self.mismatch(
+                path,
+                format!("len={}, more items than the {} args left", len, left),
+            ));
+        }
+        if min == 0 && len > 1 {
+            self.push(path, format!("len={}, the same items without args", len));
+            return Ok(1);
+        }
+        self.push(path, format!("len={}", len));
+        Ok(len)
+    }
+
+    fn words<const N: usize>(&mut self, path: &str, expected: &str) -> Result<[u32; N], Mismatch> {
+        words(&mut self.args).map_err(|_| self.ended(path, expected))
+    }
+
+    fn bytes(&mut self, path: &str, expected: &str) -> Result<(Vec<u8>, String), Mismatch> {
+        let len = match self.args.as_slice().first() {
+            Some(len) => *len as usize,
+            None => return Err(self.ended(path, expected)),
+        };
+        self.push(path, format!("len={}", len));
+        match bytes_words(&mut self.args) {
+            Ok((_, words)) => {
+                let bytes = words
+                    .iter()
+                    .flat_map(|word| word.to_le_bytes())
+                    .take(len)
+                    .collect();
+                let words: Vec<String> = words.iter().map(|word| hex(*word)).collect();
+                Ok((bytes, words.join(" ")))
+            }
+            Err(_) => Err(self.ended(path, &format!("{} bytes of {}", len, expected))),
+        }
+    }
+
+    /**
+    Count of the args left after the frame of a framed struct or enum.
+    */
+    fn frame(&mut self, path: &str) -> Result<usize, Mismatch> {
+        let frame = match self.args.as_slice().first() {
+            Some(frame) => *frame,
+            None => return Err(self.ended(path, "frame")),
+        };
+        match frame_end(&mut self.args) {
+            Ok(end) => {
+                self.push(path, format!("frame={}", frame));
+                Ok(end)
+            }
+            Err(_) => {
+                let left = self.args.len();
+                Err(self.mismatch(
+                    path,
+                    format!("frame={} is longer than the {} args left", frame, left),
+                ))
+            }
+        }
+    }
+
+    fn skip_frame(&mut self, path: &str, end: usize) -> Result<(), Mismatch> {
+        let left = self.args.len();
+        if left < end {
+            return Err(self.mismatch(path, format!("read {} args past the frame", end - left)));
+        }
+        if left > end {
+            self.push(path, format!("skipped {} unknown args", left - end));
+        }
+        skip_to_frame_end(&mut self.args, end).map_err(|_| Mismatch)
+    }
+
+    /**
+    Marks an integer out of the range of its type, the typed read would truncate it.
+    */
+    fn in_range(
+        &mut self,
+        path: &str,
+        name: &str,
+        value: &Value,
+        text: String,
+    ) -> Result<String, Mismatch> {
+        if !int_fits(name, value) {
+            return Err(self.mismatch(path, format!("{} out of range", text)));
+        }
+        Ok(text)
+    }
+
+    fn node(&mut self, path: &str, schema: &SchemaTree) -> Result<(), Mismatch> {
+        match schema {
+            SchemaTree::Primitive(name) => self.primitive(path, name),
+            SchemaTree::Payload(_, _) => self.primitive(path, "Serde"),
+            SchemaTree::Alias(name, inner) => {
+                let path = if path.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{} as {}", path, name)
+                };
+                self.node(&path, inner)
+            }
+            SchemaTree::Option(inner) => match self.next(path, "Option")? {
+                0 => {
+                    self.push(path, "None".to_string());
+                    Ok(())
+                }
+                1 => {
+                    self.push(path, "Some".to_string());
+                    self.node(&format!("{}?", path), inner)
+                }
+                tag => Err(self.mismatch(path, format!("Option tag {}", tag))),
+            },
+            SchemaTree::Seq(inner) => {
+                let len = self.items_len(path, min_words(inner))?;
+                for index in 0..len {
+                    self.node(&format!("{}[{}]", path, index), inner)?;
+                }
+                Ok(())
+            }
+            SchemaTree::Array(inner, len) => {
+                for index in 0..*len {
+                    self.node(&format!("{}[{}]", path, index), inner)?;
+                }
+                Ok(())
+            }
+            SchemaTree::Tuple(items) => {
+                for (index, item) in items.iter().enumerate() {
+                    let path = if path.is_empty() {
+                        index.to_string()
+                    } else {
+                        format!("{}.{}", path, index)
+                    };
+                    self.node(&path, item)?;
+                }
+                Ok(())
+            }
+            SchemaTree::Map(key, value) => {
+                let len = self.items_len(path, min_words(key) + min_words(value))?;
+                for index in 0..len {
+                    self.node(&format!("{}[{}].key", path, index), key)?;
+                    self.node(&format!("{}[{}].value", path, index), value)?;
+                }
+                Ok(())
+            }
+            SchemaTree::Struct {
+                name,
+                packed,
+                framed,
+                fields,
+            } => {
+                let path = if path.is_empty() {
+                    name.clone()
+                } else {
+                    path.to_string()
+                };
+                let end = if *framed {
+                    Some(self.frame(&path)?)
+                } else {
+                    None
+                };
+                if *packed {
+                    let mut used = u32::BITS;
+                    let mut word = 0;
+                    let mut items: Vec<String> = Vec::new();
+                    for field in fields.iter().filter(|field| field.bits > 0) {
+                        if used + field.bits > u32::BITS {
+                            if !items.is_empty() {
+                                self.push(
+                                    &path,
+                                    format!("packed {}: {}", hex(word), items.join(", ")),
+                                );
+                                items.clear();
+                            }
+                            word = self.next(&path, "packed fields")?;
+                            used = 0;
+                        }
+                        let bits = (word >> used) & mask(field.bits);
+                        used += field.bits;
+                        match packed_text(&field.schema, bits) {
+                            Ok(text) => items.push(format!("{} {}", field.name, text)),
+                            Err(text) => {
+                                items.push(format!("{} {}", field.name, text));
+                                return Err(self.mismatch(
+                                    &path,
+                                    format!("packed {}: {}", hex(word), items.join(", ")),
+                                ));
+                            }
+                        }
+                    }
+                    if !items.is_empty() {
+                        self.push(&path, format!("packed {}: {}", hex(word), items.join(", ")));
+                    }
+                }
+                for field in fields.iter().filter(|field| !*packed || field.bits == 0) {
+                    let field_path = format!("{}.{}", path, field.name);
+                    if let Some(end) = end {
+                        if field.since > 0 && self.args.len() <= end {
+                            self.push(&field_path, format!("absent since {}", field.since));
+                            continue;
+                        }
+                    }
+                    self.node(&field_path, &field.schema)?;
+                }
+                match end {
+                    Some(end) => self.skip_frame(&path, end),
+                    None => Ok(()),
+                }
+            }
+            SchemaTree::Enum {
+                name,
+                framed,
+                variants,
+            } => {
+                let path = if path.is_empty() {
+                    name.clone()
+                } else {
+                    path.to_string()
+                };
+                let tag = self.next(&path, "enum tag")?;
+                let end = if *framed {
+                    Some(self.frame(&path)?)
+                } else {
+                    None
+                };
+                match variants.get(tag as usize) {
+                    Some((variant, _, schema)) => {
+                        self.push(&path, format!("tag={} {}", tag, variant));
+                        if let Some(schema) = schema {
+                            self.node(&format!("{}::{}", path, variant), schema)?;
+                        }
+                    }
+                    None => match variants.iter().find(|(_, other, _)| *other) {
+                        Some((variant, _, _)) => {
+                            self.push(&path, format!("tag={} unknown, read as {}", tag, variant))
+                        }
+                        None => {
+                            return Err(self.mismatch(
+                                &path,
+                                format!("tag={} out of the {} variants", tag, variants.len()),
+                            ))
+                        }
+                    },
+                }
+                match end {
+                    Some(end) => self.skip_frame(&path, end),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+
+    fn primitive(&mut self, path: &str, name: &str) -> Result<(), Mismatch> {
+        let text = match name {
+            "bool" => match self.next(path, name)? {
+                0 => "false".to_string(),
+                1 => "true".to_string(),
+                word => return Err(self.mismatch(path, format!("bool {}", word))),
+            },
+            "u8" | "u16" | "u32" | "usize" => {
+                let word = self.next(path, name)?;
+                let text = format!("{} {}", name, word);
+                self.in_range(path, name, &Value::UInt(word as u128), text)?
+            }
+            "i8" | "i32" | "isize" => {
+                let word = self.next(path, name)? as i32;
+                let text = format!("{} {}", name, word);
+                self.in_range(path, name, &Value::Int(word as i128), text)?
+            }
+            "f32" => format!("f32 {}", f32::from_bits(self.next(path, name)?)),
+            "u64" | "i64" | "f64" => {
+                let [lo, hi] = self.words::<2>(path, name)?;
+                let bits = (hi as u64) << 32 | lo as u64;
+                let value = match name {
+                    "u64" => bits.to_string(),
+                    "i64" => (bits as i64).to_string(),
+                    _ => f64::from_bits(bits).to_string(),
+                };
+                format!("{} {} (lo {}, hi {})", name, value, hex(lo), hex(hi))
+            }
+            "u128" | "i128" => {
+                let words = self.words::<4>(path, name)?;
+                let bits = words
+                    .iter()
+                    .rev()
+                    .fold(0_u128, |bits, word| bits << 32 | *word as u128);
+                let value = match name {
+                    "u128" => bits.to_string(),
+                    _ => (bits as i128).to_string(),
+                };
+                let words: Vec<String> = words.iter().map(|word| hex(*word)).collect();
+                format!("{} {} ({})", name, value, words.join(" "))
+            }
+            "String" => {
+                let (bytes, words) = self.bytes(path, name)?;
+                match core::str::from_utf8(&bytes) {
+                    Ok(s) => format!("{:?} packed {}", s, words),
+                    Err(_) => {
+                        return Err(self.mismatch(path, format!("invalid utf-8 in {}", words)))
+                    }
+                }
+            }
+            "Bytes" | "Serde" => {
+                let (bytes, words) = self.bytes(path, name)?;
+                let bytes: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+                format!("{} {} packed {}", name, bytes.join(" "), words)
+            }
+            // the bytes are in the heap of the guest, the args have only the len and the pointer
+            "&str" | "&Bytes" => {
+                let [len, ptr] = self.words::<2>(path, name)?;
+                format!("{} len={} ptr={}", name, len, hex(ptr))
+            }
+            "Duration" | "SystemTime" => {
+                let [lo, hi] = self.words::<2>(path, name)?;
+                let bits = (hi as u64) << 32 | lo as u64;
+                let nanos = self.next(path, "nanos")?;
+                let secs = match name {
+                    "Duration" => bits.to_string(),
+                    _ => (bits as i64).to_string(),
+                };
+                let text = format!("{} {}s {}ns", name, secs, nanos);
+                if nanos >= NANOS_PER_SEC {
+                    return Err(self.mismatch(path, format!("{}, nanos out of range", text)));
+                }
+                text
+            }
+            _ => return Err(self.mismatch(path, format!("unknown type {}", name))),
+        };
+        self.push(path, text);
+        Ok(())
+    }
+}
+
+/**
+Text of a packed field, the error is the text of a tag out of the variants.
+*/
+fn packed_text(schema: &SchemaTree, bits: u32) -> Result<String, String> {
+    let text = match schema {
+        SchemaTree::Primitive(name) if name == "bool" => (bits == 1).to_string(),
+        SchemaTree::Primitive(name) if name == "i8" => (bits as u8 as i8).to_string(),
+        SchemaTree::Enum { variants, .. } => match variants.get(bits as usize) {
+            Some((variant, _, _)) => variant.clone(),
+            None => {
+                return Err(format!(
+                    "tag={} out of the {} variants",
+                    bits,
+                    variants.len()
+                ))
+            }
+        },
+        _ => bits.to_string(),
+    };
+    Ok(text)
+}
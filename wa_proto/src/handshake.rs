@@ -0,0 +1,273 @@
+//! Handshake of the module: the guest exports the wire version, the flags of its encoding
+//! and the schemas of the messages it speaks, the host checks them at load time,
+//! before the first call.
+//!
+//! The handshake is sent as args: the version, the flags, the count of the messages,
+//! then the name and the schema args of every message.
+//!
+//! The guest encodes with the flags it was built with, the host adapts to them:
+//! `check` returns the flags of the guest, and the host writes and reads the values
+//! of that guest inside `with_wire_flags(flags, ..)`, so the time types take the layout
+//! of the guest (whole seconds with `LEGACY_TIME_FLAG`, nanos without it).
+//! Lens and `usize` are an arg on every side, so the pointer width isn't a flag:
+//! a 64-bit side refuses to send a larger value (`usize_arg`).
+
+#[cfg(feature = "alloc")]
+use crate::{
+    count, next, read_name, schema_diff, FieldTree, SchemaTree, TIME_LAYOUTS, TRAILING_ARGS_ERROR,
+};
+use crate::{
+    name_args, schema_args, usize_arg, ArgsWriter, ProtocolError, Schema, TypeSchema,
+    FORMAT_VERSION, HANDSHAKE_ERROR,
+};
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{boxed::Box, format, string::String, vec::Vec};
+#[cfg(not(feature = "std"))]
+use core::sync::atomic::{AtomicU32, Ordering};
+#[cfg(feature = "std")]
+use std::cell::Cell;
+
+/**
+Time is sent in whole seconds (milliseconds for chrono), the `legacy_time` feature.
+*/
+pub const LEGACY_TIME_FLAG: u32 = 1;
+
+/**
+Flags known to this version, a guest with other bits set is refused.
+*/
+pub const KNOWN_FLAGS: u32 = LEGACY_TIME_FLAG;
+
+/**
+Flags of the encoding of this build, the default of `wire_flags`.
+*/
+pub const WIRE_FLAGS: u32 = if cfg!(feature = "legacy_time") {
+    LEGACY_TIME_FLAG
+} else {
+    0
+};
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    static FLAGS: Cell<u32> = const { Cell::new(WIRE_FLAGS) };
+}
+
+#[cfg(not(feature = "std"))]
+static FLAGS: AtomicU32 = AtomicU32::new(WIRE_FLAGS);
+
+/**
+Flags the values are written and read with: `WIRE_FLAGS` outside of `with_wire_flags`.
+With std they are per thread.
+*/
+pub fn wire_flags() -> u32 {
+    #[cfg(feature = "std")]
+    return FLAGS.with(Cell::get);
+    #[cfg(not(feature = "std"))]
+    return FLAGS.load(Ordering::Relaxed);
+}
+
+fn replace_wire_flags(flags: u32) -> u32 {
+    #[cfg(feature = "std")]
+    return FLAGS.with(|cell| cell.replace(flags));
+    #[cfg(not(feature = "std"))]
+    return FLAGS.swap(flags, Ordering::Relaxed);
+}
+
+/**
+Run `f` with the values written and read with the flags, usually the ones `Handshake::check`
+agreed on with a guest. The flags of before are back after `f`, even if it panics.
+Unknown flags are refused.
+*/
+pub fn with_wire_flags<R>(flags: u32, f: impl FnOnce() -> R) -> Result<R, ProtocolError> {
+    struct Restore(u32);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            replace_wire_flags(self.0);
+        }
+    }
+
+    if flags & !KNOWN_FLAGS != 0 {
+        return Err(ProtocolError::from(HANDSHAKE_ERROR));
+    }
+    let _restore = Restore(replace_wire_flags(flags));
+    Ok(f())
+}
+
+/**
+Message of the module: the name of the call or of the type and its schema.
+*/
+#[derive(Debug, PartialEq, Eq)]
+pub struct Message {
+    pub name: &'static str,
+    pub schema: &'static TypeSchema,
+}
+
+impl Message {
+    pub const fn of<T: Schema>(name: &'static str) -> Self {
+        Message {
+            name,
+            schema: T::SCHEMA,
+        }
+    }
+}
+
+/**
+Handshake of one side, usually a static like
+`static HANDSHAKE: Handshake = Handshake::new(&[Message::of::<Order>("order")])`.
+*/
+#[derive(Debug, PartialEq, Eq)]
+pub struct Handshake {
+    /**
+    `FORMAT_VERSION` of the args, the packed structs are told apart by their schemas.
+    */
+    pub version: u16,
+    pub flags: u32,
+    pub messages: &'static [Message],
+}
+
+impl Handshake {
+    /**
+    Handshake with the format version and the flags of this build.
+    */
+    pub const fn new(messages: &'static [Message]) -> Self {
+        Handshake {
+            version: FORMAT_VERSION,
+            flags: WIRE_FLAGS,
+            messages,
+        }
+    }
+
+    /**
+    Write the handshake as args, the guest exports them to the host.
+    */
+    pub fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        args.push(self.version as u32)?;
+        args.push(self.flags)?;
+        args.push(usize_arg(self.messages.len())?)?;
+        for message in self.messages {
+            name_args(message.name, args)?;
+            schema_args(message.schema, args)?;
+        }
+        Ok(())
+    }
+
+    /**
+    Check the handshake args of the guest: the same version, known flags,
+    and every message of the host with a compatible schema in the time layout of the guest.
+    The guest may speak more messages than the host uses.
+    Returns the flags of the guest for `with_wire_flags`, the error has the differences with std.
+    */
+    #[cfg(feature = "alloc")]
+    pub fn check(&self, guest_args: &[u32]) -> Result<u32, ProtocolError> {
+        let mut args = guest_args.iter();
+        let version = next(&mut args)?;
+        if version != self.version as u32 {
+            // the rest of the args may have another layout
+            return Err(handshake_error(&[format!(
+                "format version: host {}, guest {}",
+                self.version, version
+            )]));
+        }
+        let mut diff: Vec<String> = Vec::new();
+        let flags = next(&mut args)?;
+        if flags & !KNOWN_FLAGS != 0 {
+            diff.push(format!("unknown flags: {:#x}", flags & !KNOWN_FLAGS));
+        }
+        // every message takes at least two args, the len of the name and the schema tag
+        let len = count(&mut args)?;
+        let guest = (0..len)
+            .map(|_| Ok((read_name(&mut args)?, SchemaTree::read(&mut args)?)))
+            .collect::<Result<Vec<(String, SchemaTree)>, ProtocolError>>()?;
+        if args.len() != 0 {
+            return Err(ProtocolError::from(TRAILING_ARGS_ERROR));
+        }
+        let legacy = flags & LEGACY_TIME_FLAG != 0;
+        for message in self.messages {
+            let host = time_layout(&SchemaTree::from(message.schema), legacy);
+            match guest.iter().find(|(name, _)| name == message.name) {
+                Some((_, tree)) if tree.hash() == host.hash() => {}
+                Some((_, tree)) => {
+                    diff.extend(
+                        schema_diff(&host, tree)
+                            .into_iter()
+                            .map(|line| format!("{}: {}", message.name, line)),
+                    );
+                }
+                None => diff.push(format!("{}: message only on the host", message.name)),
+            }
+        }
+        if diff.is_empty() {
+            Ok(flags)
+        } else {
+            Err(handshake_error(&diff))
+        }
+    }
+}
+
+/**
+The tree with the time types in one layout: with nanos, or whole seconds when `legacy`.
+*/
+#[cfg(feature = "alloc")]
+fn time_layout(tree: &SchemaTree, legacy: bool) -> SchemaTree {
+    let boxed = |tree: &SchemaTree| Box::new(time_layout(tree, legacy));
+    match tree {
+        SchemaTree::Alias(name, inner) => {
+            let layout = TIME_LAYOUTS.iter().find_map(|(nanos, old)| {
+                let (nanos, old) = (SchemaTree::from(*nanos), SchemaTree::from(*old));
+                (*tree == nanos || *tree == old).then_some(if legacy { old } else { nanos })
+            });
+            layout.unwrap_or_else(|| SchemaTree::Alias(name.clone(), boxed(inner)))
+        }
+        SchemaTree::Primitive(_) => tree.clone(),
+        SchemaTree::Option(inner) => SchemaTree::Option(boxed(inner)),
+        SchemaTree::Seq(inner) => SchemaTree::Seq(boxed(inner)),
+        SchemaTree::Array(inner, len) => SchemaTree::Array(boxed(inner), *len),
+        SchemaTree::Map(key, value) => SchemaTree::Map(boxed(key), boxed(value)),
+        SchemaTree::Tuple(items) => {
+            SchemaTree::Tuple(items.iter().map(|item| time_layout(item, legacy)).collect())
+        }
+        SchemaTree::Payload(codec, inner) => SchemaTree::Payload(codec.clone(), boxed(inner)),
+        SchemaTree::Struct {
+            name,
+            packed,
+            framed,
+            fields,
+        } => SchemaTree::Struct {
+            name: name.clone(),
+            packed: *packed,
+            framed: *framed,
+            fields: fields
+                .iter()
+                .map(|field| FieldTree {
+                    schema: time_layout(&field.schema, legacy),
+                    ..field.clone()
+                })
+                .collect(),
+        },
+        SchemaTree::Enum {
+            name,
+            framed,
+            variants,
+        } => SchemaTree::Enum {
+            name: name.clone(),
+            framed: *framed,
+            variants: variants
+                .iter()
+                .map(|(name, other, schema)| {
+                    let schema = schema.as_ref().map(|schema| time_layout(schema, legacy));
+                    (name.clone(), *other, schema)
+                })
+                .collect(),
+        },
+    }
+}
+
+#[cfg(feature = "std")]
+fn handshake_error(diff: &[String]) -> ProtocolError {
+    ProtocolError(format!("handshake: {}", diff.join("; ")))
+}
+
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+fn handshake_error(_: &[String]) -> ProtocolError {
+    ProtocolError(HANDSHAKE_ERROR)
+}
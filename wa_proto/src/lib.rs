@@ -1,20 +1,66 @@
 #![cfg_attr(not(feature = "std"), no_std)]
+// without a role only the constants of the traits are compiled
+#![cfg_attr(not(any(feature = "host", feature = "guest")), allow(dead_code))]
 
-#[cfg(not(feature = "std"))]
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
 extern crate alloc;
 
+mod args;
+mod borrowed;
+#[cfg(feature = "alloc")]
+mod bytes;
+#[cfg(all(feature = "serde", feature = "alloc"))]
+mod codec;
+#[cfg(all(feature = "serde", feature = "alloc"))]
+mod compact;
+#[cfg(feature = "serde")]
+mod de;
+#[cfg(feature = "alloc")]
+mod dump;
+mod handshake;
+mod packed;
 mod protocol;
+mod schema;
+#[cfg(feature = "alloc")]
+mod schema_text;
+#[cfg(feature = "serde")]
+mod ser;
+mod stream;
+#[cfg(feature = "alloc")]
+mod value;
+mod view;
 
 #[cfg(all(any(feature = "hashmap", feature = "std"), feature = "map"))]
 use core::hash::BuildHasherDefault;
-#[cfg(all(not(feature = "std"), feature = "hashmap"))]
+#[cfg(all(not(feature = "std"), feature = "hashmap", feature = "map"))]
 use hashbrown::HashMap;
 #[cfg(all(any(feature = "hashmap", feature = "std"), feature = "map"))]
 use rustc_hash::FxHasher;
-#[cfg(feature = "std")]
+#[cfg(all(feature = "std", feature = "map"))]
 use std::collections::HashMap;
 
+pub use args::*;
+pub use borrowed::*;
+#[cfg(feature = "alloc")]
+pub use bytes::*;
+#[cfg(all(feature = "serde", feature = "alloc"))]
+pub use codec::*;
+#[cfg(all(feature = "serde", feature = "alloc"))]
+pub use compact::*;
+#[cfg(feature = "serde")]
+pub use de::*;
+#[cfg(feature = "alloc")]
+pub use dump::*;
+pub use handshake::*;
+pub use packed::*;
 pub use protocol::*;
+pub use schema::*;
+#[cfg(feature = "serde")]
+pub use ser::*;
+pub use stream::*;
+#[cfg(feature = "alloc")]
+pub use value::*;
+pub use view::*;
 pub use wa_proto_macro::*;
 
 #[cfg(all(any(feature = "hashmap", feature = "std"), feature = "map"))]
@@ -22,3 +68,49 @@ type FxBuildHasher = BuildHasherDefault<FxHasher>;
 
 #[cfg(all(any(feature = "hashmap", feature = "std"), feature = "map"))]
 type FxHashMap<K, V> = HashMap<K, V, FxBuildHasher>;
+
+/**
+Items used by the code generated in wa_proto_macro.
+*/
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(all(not(feature = "std"), feature = "alloc"))]
+    pub use alloc::vec::Vec;
+    #[cfg(feature = "std")]
+    pub use std::vec::Vec;
+}
+
+/**
+Keeps the items only with the `host` feature of wa_proto,
+so the generated code doesn't depend on the features of the user crate.
+*/
+#[cfg(feature = "host")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __host {
+    ($($item:tt)*) => { $($item)* };
+}
+
+#[cfg(not(feature = "host"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __host {
+    ($($item:tt)*) => {};
+}
+
+/**
+Keeps the items only with the `guest` feature of wa_proto.
+*/
+#[cfg(feature = "guest")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __guest {
+    ($($item:tt)*) => { $($item)* };
+}
+
+#[cfg(not(feature = "guest"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __guest {
+    ($($item:tt)*) => {};
+}
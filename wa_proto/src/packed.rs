@@ -0,0 +1,185 @@
+//! Bit-packing of the small fields of `#[coming(packed)]` structs:
+//! bool, u8, i8, u16 and simple enums are packed into shared args before the other fields,
+//! the first field in the low bits, a field never spans two args.
+
+use crate::{ArgsWriter, ProtocolError, ARGS_NEXT_ERROR};
+
+/**
+Value that takes only a few bits of an arg.
+*/
+pub trait PackBits: Sized {
+    const BITS: u32;
+
+    fn pack(&self) -> u32;
+
+    fn unpack(bits: u32) -> Result<Self, ProtocolError>;
+}
+
+impl PackBits for bool {
+    const BITS: u32 = 1;
+
+    fn pack(&self) -> u32 {
+        *self as u32
+    }
+
+    fn unpack(bits: u32) -> Result<Self, ProtocolError> {
+        Ok(bits == 1)
+    }
+}
+
+impl PackBits for u8 {
+    const BITS: u32 = 8;
+
+    fn pack(&self) -> u32 {
+        *self as u32
+    }
+
+    fn unpack(bits: u32) -> Result<Self, ProtocolError> {
+        Ok(bits as u8)
+    }
+}
+
+impl PackBits for i8 {
+    const BITS: u32 = 8;
+
+    fn pack(&self) -> u32 {
+        *self as u8 as u32
+    }
+
+    fn unpack(bits: u32) -> Result<Self, ProtocolError> {
+        Ok(bits as u8 as i8)
+    }
+}
+
+impl PackBits for u16 {
+    const BITS: u32 = 16;
+
+    fn pack(&self) -> u32 {
+        *self as u32
+    }
+
+    fn unpack(bits: u32) -> Result<Self, ProtocolError> {
+        Ok(bits as u16)
+    }
+}
+
+/**
+Bits of a simple enum with the given count of variants.
+*/
+pub const fn enum_bits(variants: u32) -> u32 {
+    if variants <= 2 {
+        1
+    } else {
+        u32::BITS - (variants - 1).leading_zeros()
+    }
+}
+
+/**
+Count of the args taken by the packed fields with the given bits.
+*/
+pub const fn packed_words(bits: &[u32]) -> usize {
+    let mut words = 0;
+    let mut used = u32::BITS;
+    let mut i = 0;
+    while i < bits.len() {
+        if used + bits[i] > u32::BITS {
+            words += 1;
+            used = 0;
+        }
+        used += bits[i];
+        i += 1;
+    }
+    words
+}
+
+/**
+Packs the fields into args, the same layout as `packed_words`.
+*/
+#[derive(Default)]
+pub struct BitWriter {
+    word: u32,
+    used: u32,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        BitWriter::default()
+    }
+
+    pub fn write<T: PackBits, W: ArgsWriter>(
+        &mut self,
+        value: &T,
+        args: &mut W,
+    ) -> Result<(), ProtocolError> {
+        if self.used + T::BITS > u32::BITS {
+            self.flush(args)?;
+        }
+        self.word |= (value.pack() & mask(T::BITS)) << self.used;
+        self.used += T::BITS;
+        Ok(())
+    }
+
+    /**
+    Write the last arg.
+    */
+    pub fn finish<W: ArgsWriter>(mut self, args: &mut W) -> Result<(), ProtocolError> {
+        if self.used > 0 {
+            self.flush(args)?;
+        }
+        Ok(())
+    }
+
+    fn flush<W: ArgsWriter>(&mut self, args: &mut W) -> Result<(), ProtocolError> {
+        args.push(self.word)?;
+        self.word = 0;
+        self.used = 0;
+        Ok(())
+    }
+}
+
+/**
+Unpacks the fields written by `BitWriter`.
+*/
+pub struct BitReader {
+    word: u32,
+    used: u32,
+}
+
+impl Default for BitReader {
+    fn default() -> Self {
+        BitReader {
+            word: 0,
+            used: u32::BITS,
+        }
+    }
+}
+
+impl BitReader {
+    pub fn new() -> Self {
+        BitReader::default()
+    }
+
+    pub fn read<T: PackBits>(
+        &mut self,
+        mut next: impl FnMut() -> Option<u32>,
+    ) -> Result<T, ProtocolError> {
+        if self.used + T::BITS > u32::BITS {
+            self.word = next().ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
+            self.used = 0;
+        }
+        let bits = (self.word >> self.used) & mask(T::BITS);
+        self.used += T::BITS;
+        T::unpack(bits)
+    }
+}
+
+/**
+Mask of the low bits of a packed field.
+*/
+pub(crate) const fn mask(bits: u32) -> u32 {
+    if bits >= u32::BITS {
+        u32::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
@@ -4,38 +4,55 @@
 //! но из wasm она не будет передаваться никогда. Тогда для этой
 //! структуры излишне имплементировать Outcoming.
 
+#[cfg(any(feature = "guest", feature = "host", feature = "serde"))]
+use crate::ArgsWriter;
+#[cfg(any(feature = "chrono", feature = "time", feature = "alloc"))]
+use crate::TypeSchema;
+#[cfg(any(feature = "chrono", feature = "time"))]
+use crate::{wire_flags, Schema, LEGACY_TIME_FLAG};
 #[cfg(all(any(feature = "hashmap", feature = "std"), feature = "map"))]
 use crate::{FxBuildHasher, FxHashMap};
-#[cfg(not(feature = "std"))]
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
 use alloc::{boxed::Box, collections::BTreeMap, string::String, vec::Vec};
 #[cfg(feature = "chrono")]
-use chrono::{Date, DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Utc};
-#[cfg(feature = "std")]
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Month, NaiveDate, NaiveDateTime, NaiveTime,
+    Timelike, Utc, Weekday,
+};
+#[cfg(feature = "host")]
+use core::cell::RefMut;
+#[cfg(any(feature = "std", feature = "serde"))]
 use core::fmt;
-#[cfg(not(feature = "std"))]
-use core::{convert::TryInto, slice::IterMut};
+#[cfg(any(feature = "std", feature = "hashmap"))]
+use core::hash::Hash;
+use core::ops::Deref;
+#[cfg(any(feature = "host", feature = "alloc"))]
+use core::slice::Iter;
+#[cfg(feature = "guest")]
+use core::slice::IterMut;
 #[cfg(all(not(feature = "std"), feature = "hashmap"))]
 use hashbrown::HashMap;
 #[cfg(feature = "std")]
 use std::{
-    cell::RefMut,
     collections::{BTreeMap, HashMap},
-    convert::TryInto,
     error::Error,
-    hash::Hash,
-    slice::Iter,
     string::String,
     vec::Vec,
 };
 
 // TODO: alignment memory of 32 bits?
 
+/**
+Error of the protocol: a message with std and an error code without std.
+Depends only on `std`, not on the host/guest role.
+*/
 #[derive(Default)]
 #[cfg_attr(feature = "std", derive(Debug))]
 #[cfg(feature = "std")]
 pub struct ProtocolError(pub String);
 
 #[derive(Default)]
+#[cfg_attr(feature = "serde", derive(Debug))]
 #[cfg(not(feature = "std"))]
 pub struct ProtocolError(pub u32);
 
@@ -57,6 +74,51 @@ pub const ENUM_FROM_U32_ERROR: &str = "enum from u32 error";
 pub const ENUM_FROM_U32_ERROR: u32 = 7;
 
 pub const TIME_PARSE_ERROR: u32 = 8;
+pub const SLICE_LEN_ERROR: u32 = 9;
+pub const ARGS_BUF_FULL_ERROR: u32 = 10;
+pub const CAPACITY_ERROR: u32 = 11;
+pub const SERDE_CUSTOM_ERROR: u32 = 12;
+pub const SERDE_UNSUPPORTED_ERROR: u32 = 13;
+pub const TRAILING_ARGS_ERROR: u32 = 14;
+pub const CODEC_ERROR: u32 = 15;
+pub const BYTES_HEADER_ERROR: u32 = 16;
+pub const VARINT_ERROR: u32 = 17;
+pub const SCHEMA_MISMATCH_ERROR: u32 = 18;
+pub const HANDSHAKE_ERROR: u32 = 19;
+pub const STREAM_ORDER_ERROR: u32 = 20;
+pub const USIZE_RANGE_ERROR: u32 = 21;
+
+pub(crate) const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+/**
+message of the error code, used for ProtocolError with std
+*/
+#[cfg(feature = "std")]
+const fn error_code_message(code: u32) -> &'static str {
+    match code {
+        2 => ARGS_NEXT_ERROR,
+        BYTES_INTO_ARR4_ERROR => "bytes into [u8; 4] error",
+        BYTES_INTO_ARR8_ERROR => "bytes into [u8; 8] error",
+        MAP_INSERT_ERROR => "map already have item",
+        STRING_FROM_BYTES_ERROR => "utf-8 string bytes error",
+        7 => ENUM_FROM_U32_ERROR,
+        TIME_PARSE_ERROR => "time from u32 error",
+        SLICE_LEN_ERROR => "slice len error",
+        ARGS_BUF_FULL_ERROR => "args buffer is full",
+        CAPACITY_ERROR => "capacity exceeded",
+        SERDE_CUSTOM_ERROR => "serde error",
+        SERDE_UNSUPPORTED_ERROR => "not supported by the wa_proto format",
+        TRAILING_ARGS_ERROR => "trailing args",
+        CODEC_ERROR => "codec error",
+        BYTES_HEADER_ERROR => "bytes header error",
+        VARINT_ERROR => "varint overflow",
+        SCHEMA_MISMATCH_ERROR => "schema mismatch",
+        HANDSHAKE_ERROR => "handshake error",
+        STREAM_ORDER_ERROR => "stream chunk out of order",
+        USIZE_RANGE_ERROR => "usize beyond u32",
+        _ => "",
+    }
+}
 
 #[cfg(feature = "std")]
 impl From<&str> for ProtocolError {
@@ -66,30 +128,49 @@ impl From<&str> for ProtocolError {
 }
 
 #[cfg(feature = "std")]
-impl From<std::array::TryFromSliceError> for ProtocolError {
-    fn from(_: std::array::TryFromSliceError) -> Self {
-        ProtocolError("slice len error".to_string())
+impl From<u32> for ProtocolError {
+    fn from(code: u32) -> Self {
+        ProtocolError(error_code_message(code).to_string())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl From<u32> for ProtocolError {
+    fn from(s: u32) -> Self {
+        ProtocolError(s)
+    }
+}
+
+impl From<core::str::Utf8Error> for ProtocolError {
+    fn from(_: core::str::Utf8Error) -> Self {
+        ProtocolError::from(STRING_FROM_BYTES_ERROR)
+    }
+}
+
+impl From<core::array::TryFromSliceError> for ProtocolError {
+    fn from(_: core::array::TryFromSliceError) -> Self {
+        ProtocolError::from(SLICE_LEN_ERROR)
     }
 }
 
 #[cfg(feature = "std")]
 impl From<std::string::FromUtf8Error> for ProtocolError {
     fn from(_: std::string::FromUtf8Error) -> Self {
-        ProtocolError("utf-8 string bytes error".to_string())
+        ProtocolError::from(STRING_FROM_BYTES_ERROR)
     }
 }
 
-#[cfg(feature = "std")]
-impl From<time::error::ComponentRange> for ProtocolError {
-    fn from(_: time::error::ComponentRange) -> Self {
-        ProtocolError("time from u32 error".to_string())
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+impl From<alloc::string::FromUtf8Error> for ProtocolError {
+    fn from(_: alloc::string::FromUtf8Error) -> Self {
+        ProtocolError::from(STRING_FROM_BYTES_ERROR)
     }
 }
 
-#[cfg(not(feature = "std"))]
-impl From<u32> for ProtocolError {
-    fn from(s: u32) -> Self {
-        ProtocolError(s)
+#[cfg(feature = "time")]
+impl From<time::error::ComponentRange> for ProtocolError {
+    fn from(_: time::error::ComponentRange) -> Self {
+        ProtocolError::from(TIME_PARSE_ERROR)
     }
 }
 
@@ -107,6 +188,17 @@ impl fmt::Display for ProtocolError {
 #[cfg(feature = "std")]
 impl Error for ProtocolError {}
 
+/// serde errors need Display and Error without std too.
+#[cfg(all(not(feature = "std"), feature = "serde"))]
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Protocol error code: {}", self.0)
+    }
+}
+
+#[cfg(all(not(feature = "std"), feature = "serde"))]
+impl core::error::Error for ProtocolError {}
+
 /**
 Incoming trait (Deserializable) - (Входящее сообщение) если структура реализует этот трейт,
 то значит что эту структуру можно передать в wasm.
@@ -114,6 +206,7 @@ Incoming trait (Deserializable) - (Входящее сообщение) если
 - о длине строки или массива
 - о типе варианта перечисления
 - и другие данные, которые помогут определить полностью сообщение.
+
 Важно одно ограничение - Структура должна быть построена на основе перечислений,
 а не на основе дженериков (или хранить внутри себя информацию о соответствущем дженерике)?
 Зачем это ограничение, если мы уже реализовали трейт для HashMap и Vec с дженериками?
@@ -131,11 +224,27 @@ pub trait Incoming<T = Self> {
      */
     const IS_NEED_INIT_FILL: bool = false;
 
+    /**
+    Количество аргументов, если оно не зависит от значения:
+    примитивы, массивы, кортежи и структуры только из таких типов.
+    None для строк, векторов и других типов переменной длины.
+    */
+    const FIXED_WORDS: Option<usize> = None;
+
+    /**
+    Количество аргументов, которые добавит args,
+    для Vec::with_capacity или буфера на стеке.
+    Типы переменной длины должны переопределить этот метод.
+    */
+    fn size_hint(&self) -> usize {
+        Self::FIXED_WORDS.unwrap_or(0)
+    }
+
     /**
     Инициализируем кусок памяти в wasm для последующего заполнения.
     Вызывается в wasm.
     */
-    #[cfg(not(feature = "std"))]
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError>
     where
         Self: Sized;
@@ -143,9 +252,10 @@ pub trait Incoming<T = Self> {
     /**
     Добавление в аргументы вспомогательных данных,
     таких как длина строки/массива и др.
-    Вызывается на хосте.
+    Вызывается на хосте. Хост всегда собирается с alloc (feature host включает alloc),
+    поэтому аргументы пишутся в Vec, ArgsWriter нужен только wasm без аллокатора.
     */
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError>;
 
     /**
@@ -154,7 +264,7 @@ pub trait Incoming<T = Self> {
     Иногда инициализация не нужна, если значение можно полностью передать в аргументах,
     уместить в значении u32.
     */
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, heap: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError>;
 }
 
@@ -171,332 +281,575 @@ pub trait Outcoming<T = Self> {
      */
     const IS_NEED_READ: bool = false;
 
+    /**
+    Количество аргументов, если оно не зависит от значения, как в Incoming.
+    */
+    const FIXED_WORDS: Option<usize> = None;
+
+    /**
+    Количество аргументов, которые добавит args в wasm.
+    */
+    fn size_hint(&self) -> usize {
+        Self::FIXED_WORDS.unwrap_or(0)
+    }
+
     /**
     Заполнение массива чисел вспомогательными данными,
     такими как длина строки или массива и др.
     Вызывается в wasm.
     */
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError>;
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError>;
 
     /**
     Чтение данных из памяти wasm.
     Вызывается на хосте.
     */
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError>
     where
         Self: Sized;
+
+    /**
+    Проверка аргументов без создания значения: успешна тогда же, когда read,
+    и сдвигает args так же (кроме повторяющихся ключей словарей, их не проверить без памяти).
+    По умолчанию читает и отбрасывает значение, типы со строками и коллекциями
+    переопределяют метод, чтобы не выделять память.
+    Вызывается на хосте.
+    */
+    #[cfg(feature = "host")]
+    fn validate(heap: &[u8], args: &mut Iter<u32>) -> Result<(), ProtocolError>
+    where
+        Self: Sized,
+    {
+        Self::read(heap, args).map(drop)
+    }
+
+    /**
+    Пропуск аргументов значения без проверки содержимого,
+    проверяется только то, что аргументов хватает.
+    По умолчанию пропускает FIXED_WORDS аргументов или вызывает validate.
+    Вызывается на хосте.
+    */
+    #[cfg(feature = "host")]
+    fn skip(args: &mut Iter<u32>) -> Result<(), ProtocolError>
+    where
+        Self: Sized,
+    {
+        match Self::FIXED_WORDS {
+            Some(words) => skip_args(args, words),
+            None => Self::validate(&[], args),
+        }
+    }
+}
+
+/**
+Check that the args are exactly one value of T, without building the value.
+*/
+#[cfg(feature = "host")]
+pub fn validate_args<T: Outcoming>(heap: &[u8], args: &[u32]) -> Result<(), ProtocolError> {
+    let mut args = args.iter();
+    T::validate(heap, &mut args)?;
+    if args.len() > 0 {
+        return Err(ProtocolError::from(TRAILING_ARGS_ERROR));
+    }
+    Ok(())
+}
+
+/**
+Skip `count` args, an error if fewer args are left.
+*/
+#[cfg(any(feature = "host", feature = "alloc"))]
+pub fn skip_args(args: &mut Iter<u32>, count: usize) -> Result<(), ProtocolError> {
+    let rest = args.as_slice();
+    if rest.len() < count {
+        return Err(ProtocolError::from(ARGS_NEXT_ERROR));
+    }
+    *args = rest[count..].iter();
+    Ok(())
+}
+
+/**
+Count of the args left after the frame of a framed struct or enum:
+reads the frame, the count of the args that follow it.
+*/
+pub fn frame_end<I>(args: &mut I) -> Result<usize, ProtocolError>
+where
+    I: ExactSizeIterator,
+    I::Item: Deref<Target = u32>,
+{
+    let frame = *args
+        .next()
+        .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+    args.len()
+        .checked_sub(frame)
+        .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))
+}
+
+/**
+Move past the args of the frame unknown to this version,
+an error if the fields already read past the end of the frame.
+*/
+pub fn skip_to_frame_end<I: ExactSizeIterator>(
+    args: &mut I,
+    end: usize,
+) -> Result<(), ProtocolError> {
+    if args.len() < end {
+        return Err(ProtocolError::from(SLICE_LEN_ERROR));
+    }
+    while args.len() > end {
+        args.next();
+    }
+    Ok(())
+}
+
+/**
+Limit for the len of a collection before the loop over its items:
+every item takes an arg at least, unless its type has no args at all.
+*/
+#[cfg(feature = "host")]
+pub fn check_len<T: Outcoming>(len: usize, args: &Iter<u32>) -> Result<(), ProtocolError> {
+    if T::FIXED_WORDS != Some(0) && len > args.len() {
+        return Err(ProtocolError::from(ARGS_NEXT_ERROR));
+    }
+    Ok(())
+}
+
+/**
+Skip the args of `len` items of T.
+*/
+#[cfg(feature = "host")]
+pub(crate) fn skip_items<T: Outcoming>(
+    len: usize,
+    args: &mut Iter<u32>,
+) -> Result<(), ProtocolError> {
+    match T::FIXED_WORDS {
+        Some(words) => skip_args(
+            args,
+            len.checked_mul(words)
+                .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?,
+        ),
+        None => {
+            check_len::<T>(len, args)?;
+            (0..len).try_for_each(|_| T::skip(args))
+        }
+    }
+}
+
+/**
+Validate the args of `len` items of T.
+*/
+#[cfg(feature = "host")]
+fn validate_items<T: Outcoming>(
+    len: usize,
+    heap: &[u8],
+    args: &mut Iter<u32>,
+) -> Result<(), ProtocolError> {
+    check_len::<T>(len, args)?;
+    (0..len).try_for_each(|_| T::validate(heap, args))
+}
+
+/**
+Len and words of a byte string, the cursor is moved past them without copying the bytes.
+*/
+#[cfg(any(feature = "host", feature = "alloc"))]
+pub(crate) fn bytes_words<'a>(
+    args: &mut Iter<'a, u32>,
+) -> Result<(usize, &'a [u32]), ProtocolError> {
+    let len = *args
+        .next()
+        .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+    let rest = args.as_slice();
+    let words = rest
+        .get(..len.div_ceil(4))
+        .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
+    *args = rest[words.len()..].iter();
+    Ok((len, words))
+}
+
+/**
+UTF-8 check of the bytes of a string in the words, through a buffer on the stack:
+a char split between two fills of the buffer is carried over to the next one.
+*/
+#[cfg(feature = "host")]
+pub(crate) fn check_utf8(len: usize, words: &[u32]) -> Result<(), ProtocolError> {
+    let mut buf = [0u8; 64];
+    let mut filled = 0;
+    let mut left = len;
+    for word in words {
+        let count = left.min(4);
+        buf[filled..filled + count].copy_from_slice(&word.to_le_bytes()[..count]);
+        filled += count;
+        left -= count;
+        if filled + 4 <= buf.len() && left > 0 {
+            continue;
+        }
+        match core::str::from_utf8(&buf[..filled]) {
+            Ok(_) => filled = 0,
+            // an incomplete char at the end, the rest of it is in the next words
+            Err(error) if error.error_len().is_none() && left > 0 => {
+                buf.copy_within(error.valid_up_to()..filled, 0);
+                filled -= error.valid_up_to();
+            }
+            Err(error) => return Err(ProtocolError::from(error)),
+        }
+    }
+    Ok(())
+}
+
+/**
+FIXED_WORDS of two values one after another, None if any of them isn't fixed.
+*/
+pub const fn add_fixed_words(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a + b),
+        _ => None,
+    }
+}
+
+/**
+FIXED_WORDS of N values of the same type.
+*/
+pub const fn mul_fixed_words(words: Option<usize>, n: usize) -> Option<usize> {
+    match words {
+        Some(words) => Some(words * n),
+        None => None,
+    }
+}
+
+/**
+Args of a u64: the low word first, each word is a u32 value,
+so the args don't depend on the endianness of the host or the guest.
+i64 and f64 (`to_bits`) use the same args.
+*/
+pub const fn u64_into_args(v: u64) -> [u32; 2] {
+    [v as u32, (v >> 32) as u32]
+}
+
+pub const fn u64_from_args(args: [u32; 2]) -> u64 {
+    (args[1] as u64) << 32 | args[0] as u64
+}
+
+/**
+Args of a u128: 4 words from the lowest, like u64.
+i128 uses the same args.
+*/
+pub const fn u128_into_args(v: u128) -> [u32; 4] {
+    [
+        v as u32,
+        (v >> 32) as u32,
+        (v >> 64) as u32,
+        (v >> 96) as u32,
+    ]
+}
+
+pub const fn u128_from_args(args: [u32; 4]) -> u128 {
+    (args[3] as u128) << 96 | (args[2] as u128) << 64 | (args[1] as u128) << 32 | args[0] as u128
+}
+
+/**
+Arg of a len or of a usize: one u32 on every side, so a wasm32 guest and a 64-bit host
+agree on the layout. A larger value is an error instead of a truncated len.
+*/
+pub fn usize_arg(value: usize) -> Result<u32, ProtocolError> {
+    u32::try_from(value).map_err(|_| ProtocolError::from(USIZE_RANGE_ERROR))
+}
+
+/**
+Args of a byte string: the length, then 4 bytes per arg in little endian,
+the last arg padded with zeros.
+*/
+#[cfg(any(
+    feature = "alloc",
+    all(feature = "heapless", any(feature = "guest", feature = "host")),
+    feature = "serde"
+))]
+pub(crate) fn bytes_args<W: ArgsWriter>(bytes: &[u8], args: &mut W) -> Result<(), ProtocolError> {
+    args.push(usize_arg(bytes.len())?)?;
+    for chunk in bytes.chunks(4) {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        args.push(u32::from_le_bytes(word))?;
+    }
+    Ok(())
 }
 
 impl Incoming for bool {
-    #[cfg(not(feature = "std"))]
+    const FIXED_WORDS: Option<usize> = Some(1);
+
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let el: u32 = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
+        let el: u32 = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         Ok((el, el != 0))
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
         args.push(if *self { 1 } else { 0 });
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
         args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         Ok(())
     }
 }
 
 impl Outcoming for bool {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        args.push(if *self { 1 } else { 0 });
+    const FIXED_WORDS: Option<usize> = Some(1);
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        args.push(if *self { 1 } else { 0 })?;
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(_: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
         let el: u32 = *args
             .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         Ok(el != 0)
     }
 }
 
 impl Incoming for u8 {
-    #[cfg(not(feature = "std"))]
+    const FIXED_WORDS: Option<usize> = Some(1);
+
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let el: u32 = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
+        let el: u32 = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         Ok((el, el as u8))
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
         args.push(*self as u32);
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
         args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         Ok(())
     }
 }
 
 impl Outcoming for u8 {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        args.push(*self as u32);
+    const FIXED_WORDS: Option<usize> = Some(1);
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        args.push(*self as u32)?;
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(_: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
         let el: u32 = *args
             .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         Ok(el as u8)
     }
 }
 
 impl Incoming for i32 {
-    #[cfg(not(feature = "std"))]
+    const FIXED_WORDS: Option<usize> = Some(1);
+
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let el: u32 = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
+        let el: u32 = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         Ok((el, el as i32))
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
         args.push(*self as u32);
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
         args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         Ok(())
     }
 }
 
 impl Outcoming for i32 {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        args.push(*self as u32);
+    const FIXED_WORDS: Option<usize> = Some(1);
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        args.push(*self as u32)?;
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(_: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
         Ok(*args
             .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))? as i32)
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as i32)
     }
 }
 
-impl Incoming for i64 {
-    #[cfg(not(feature = "std"))]
-    fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let el1: u32 = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
-        let el2: u32 = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
-        let b1: [u8; 4] = el1.to_le_bytes();
-        let b2: [u8; 4] = el2.to_be_bytes();
-        let c: Vec<u8> = [&b1[..], &b2[..]].concat();
-        let d: &[u8; 8] = &c[..]
-            .try_into()
-            .map_err(|_| ProtocolError(BYTES_INTO_ARR8_ERROR))?;
-        let e = i64::from_le_bytes(*d);
-        Ok((0, e))
-    }
-
-    #[cfg(feature = "std")]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let bytes: [u8; 8] = self.to_le_bytes();
-        let arr1: &[u8; 4] = bytes[0..4].try_into()?;
-        let arr2: &[u8; 4] = bytes[4..8].try_into()?;
-        let arg1: u32 = u32::from_le_bytes(*arr1);
-        let arg2: u32 = u32::from_le_bytes(*arr2);
-        args.push(arg1);
-        args.push(arg2);
-        Ok(())
-    }
+/**
+impls for the values wider than one arg: N args from `$into_fn` and back with `$from_fn`.
+*/
+macro_rules! wide_impls {
+    ($t:ty, $n:literal, $into_fn:expr, $from_fn:expr) => {
+        impl Incoming for $t {
+            const FIXED_WORDS: Option<usize> = Some($n);
+
+            #[cfg(feature = "guest")]
+            fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
+                let mut words = [0u32; $n];
+                for word in &mut words {
+                    *word = *args
+                        .next()
+                        .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
+                }
+                Ok((0, ($from_fn)(words)))
+            }
 
-    #[cfg(feature = "std")]
-    fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
-        args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        Ok(())
-    }
-}
+            #[cfg(feature = "host")]
+            fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
+                args.extend_from_slice(&($into_fn)(*self));
+                Ok(())
+            }
 
-impl Outcoming for i64 {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let bytes: [u8; 8] = self.to_le_bytes();
-        let arr1: &[u8; 4] = bytes[0..4]
-            .try_into()
-            .map_err(|_| ProtocolError(BYTES_INTO_ARR4_ERROR))?;
-        let arr2: &[u8; 4] = bytes[4..8]
-            .try_into()
-            .map_err(|_| ProtocolError(BYTES_INTO_ARR4_ERROR))?;
-        let arg1: u32 = u32::from_le_bytes(*arr1);
-        let arg2: u32 = u32::from_le_bytes(*arr2);
-        args.push(arg1);
-        args.push(arg2);
-        Ok(())
-    }
+            #[cfg(feature = "host")]
+            fn fill(
+                &self,
+                _: &mut RefMut<[u8]>,
+                args: &mut Iter<u32>,
+            ) -> Result<(), ProtocolError> {
+                for _ in 0..$n {
+                    args.next()
+                        .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
+                }
+                Ok(())
+            }
+        }
 
-    #[cfg(feature = "std")]
-    fn read(_: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
-        let el1: u32 = *args
-            .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        let el2: u32 = *args
-            .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        let b1: [u8; 4] = el1.to_le_bytes();
-        let b2: [u8; 4] = el2.to_be_bytes();
-        let c: Vec<u8> = [&b1[..], &b2[..]].concat();
-        let d: &[u8; 8] = &c[..].try_into()?;
-        let e = i64::from_le_bytes(*d);
-        Ok(e)
-    }
-}
+        impl Outcoming for $t {
+            const FIXED_WORDS: Option<usize> = Some($n);
+
+            #[cfg(feature = "guest")]
+            fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+                for word in ($into_fn)(*self) {
+                    args.push(word)?;
+                }
+                Ok(())
+            }
+
+            #[cfg(feature = "host")]
+            fn read(_: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
+                let mut words = [0u32; $n];
+                for word in &mut words {
+                    *word = *args
+                        .next()
+                        .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
+                }
+                Ok(($from_fn)(words))
+            }
+        }
+    };
+}
+
+wide_impls!(u64, 2, u64_into_args, u64_from_args);
+wide_impls!(
+    i64,
+    2,
+    |v: i64| u64_into_args(v as u64),
+    |args| u64_from_args(args) as i64
+);
+wide_impls!(f64, 2, |v: f64| u64_into_args(v.to_bits()), |args| {
+    f64::from_bits(u64_from_args(args))
+});
+wide_impls!(u128, 4, u128_into_args, u128_from_args);
+wide_impls!(
+    i128,
+    4,
+    |v: i128| u128_into_args(v as u128),
+    |args| u128_from_args(args) as i128
+);
 
 impl Incoming for u32 {
-    #[cfg(not(feature = "std"))]
+    const FIXED_WORDS: Option<usize> = Some(1);
+
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let el: u32 = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
+        let el: u32 = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         Ok((el, el))
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
         args.push(*self);
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
         args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         Ok(())
     }
 }
 
 impl Outcoming for u32 {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        args.push(*self);
-        Ok(())
-    }
-
-    #[cfg(feature = "std")]
-    fn read(_: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
-        Ok(*args
-            .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?)
-    }
-}
-
-impl Incoming for u64 {
-    #[cfg(not(feature = "std"))]
-    fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let el1: u32 = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
-        let el2: u32 = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
-        let b1: [u8; 4] = el1.to_le_bytes();
-        let b2: [u8; 4] = el2.to_be_bytes();
-        let c: Vec<u8> = [&b1[..], &b2[..]].concat();
-        let d: &[u8; 8] = &c[..]
-            .try_into()
-            .map_err(|_| ProtocolError(BYTES_INTO_ARR8_ERROR))?;
-        let e = u64::from_le_bytes(*d);
-        Ok((0, e))
-    }
-
-    #[cfg(feature = "std")]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let bytes: [u8; 8] = self.to_le_bytes();
-        let arr1: &[u8; 4] = bytes[0..4].try_into()?;
-        let arr2: &[u8; 4] = bytes[4..8].try_into()?;
-        let arg1: u32 = u32::from_le_bytes(*arr1);
-        let arg2: u32 = u32::from_le_bytes(*arr2);
-        args.push(arg1);
-        args.push(arg2);
-        Ok(())
-    }
-
-    #[cfg(feature = "std")]
-    fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
-        args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        Ok(())
-    }
-}
+    const FIXED_WORDS: Option<usize> = Some(1);
 
-impl Outcoming for u64 {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let bytes: [u8; 8] = self.to_le_bytes();
-        let arr1: &[u8; 4] = bytes[0..4]
-            .try_into()
-            .map_err(|_| ProtocolError(BYTES_INTO_ARR4_ERROR))?;
-        let arr2: &[u8; 4] = bytes[4..8]
-            .try_into()
-            .map_err(|_| ProtocolError(BYTES_INTO_ARR4_ERROR))?;
-        let arg1: u32 = u32::from_le_bytes(*arr1);
-        let arg2: u32 = u32::from_le_bytes(*arr2);
-        args.push(arg1);
-        args.push(arg2);
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        args.push(*self)?;
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(_: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
-        let el1: u32 = *args
-            .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        let el2: u32 = *args
+        Ok(*args
             .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        let b1: [u8; 4] = el1.to_le_bytes();
-        let b2: [u8; 4] = el2.to_be_bytes();
-        let c: Vec<u8> = [&b1[..], &b2[..]].concat();
-        let d: &[u8; 8] = &c[..].try_into()?;
-        let e = u64::from_le_bytes(*d);
-        Ok(e)
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?)
     }
 }
 
 // only for wasm32 and runner target_pointer_width = "32"
 // #[cfg(all(not(feature = "std"), target_pointer_width = "32"))]
 impl Incoming for usize {
+    const FIXED_WORDS: Option<usize> = Some(1);
+
     // NOTE: for wasm64 required implement other fn
-    #[cfg(not(feature = "std"))]
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let el: u32 = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
+        let el: u32 = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         Ok((0, el as usize))
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        args.push(*self as u32);
+        args.push(usize_arg(*self)?);
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
         args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         Ok(())
     }
 }
@@ -504,70 +857,84 @@ impl Incoming for usize {
 // only for wasm32 and runner target_pointer_width = "32"
 // #[cfg(all(not(feature = "std"), target_pointer_width = "32"))]
 impl Outcoming for usize {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        args.push(*self as u32);
+    const FIXED_WORDS: Option<usize> = Some(1);
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        args.push(usize_arg(*self)?)?;
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(_: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
         let el: u32 = *args
             .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         Ok(el as usize)
     }
 }
 
-// only for wasm32 and runner target_pointer_width = "32"
+// one arg on every side, sign extended on a 64-bit side
 impl Incoming for isize {
-    #[cfg(not(feature = "std"))]
+    const FIXED_WORDS: Option<usize> = Some(1);
+
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let el: u32 = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
-        Ok((0, el as isize))
+        let el: u32 = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
+        Ok((0, el as i32 as isize))
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        args.push(*self as u32);
+        args.push(i32::try_from(*self).map_err(|_| ProtocolError::from(USIZE_RANGE_ERROR))? as u32);
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
         args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         Ok(())
     }
 }
 
-// only for wasm32 and runner target_pointer_width = "32"
+// one arg on every side, sign extended on a 64-bit side
 impl Outcoming for isize {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        args.push(*self as u32);
+    const FIXED_WORDS: Option<usize> = Some(1);
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        args.push(
+            i32::try_from(*self).map_err(|_| ProtocolError::from(USIZE_RANGE_ERROR))? as u32,
+        )?;
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(_: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
         let el: u32 = *args
             .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        Ok(el as isize)
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
+        Ok(el as i32 as isize)
     }
 }
 
 impl Incoming for f32 {
-    #[cfg(not(feature = "std"))]
+    const FIXED_WORDS: Option<usize> = Some(1);
+
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let el: u32 = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
+        let el: u32 = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         let bytes: [u8; 4] = el.to_le_bytes();
         let f = f32::from_le_bytes(bytes);
         Ok((0, f))
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
         let bytes = self.to_le_bytes();
         let u = u32::from_le_bytes(bytes);
@@ -575,439 +942,968 @@ impl Incoming for f32 {
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
         args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         Ok(())
     }
 }
 
 impl Outcoming for f32 {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
+    const FIXED_WORDS: Option<usize> = Some(1);
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
         let bytes: [u8; 4] = self.to_le_bytes();
         let u = u32::from_le_bytes(bytes);
-        args.push(u);
+        args.push(u)?;
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(_: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
         let el: u32 = *args
             .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         let bytes: [u8; 4] = el.to_le_bytes();
         let f = f32::from_le_bytes(bytes);
         Ok(f)
     }
 }
 
-impl Incoming for f64 {
-    #[cfg(not(feature = "std"))]
-    fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let el1: u32 = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
-        let el2: u32 = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
-        let b1: [u8; 4] = el1.to_le_bytes();
-        let b2: [u8; 4] = el2.to_be_bytes();
-        let c: Vec<u8> = [&b1[..], &b2[..]].concat();
-        let d: &[u8; 8] = &c[..]
-            .try_into()
-            .map_err(|_| ProtocolError(BYTES_INTO_ARR8_ERROR))?;
-        let e = f64::from_le_bytes(*d);
-        Ok((0, e))
-    }
-
-    #[cfg(feature = "std")]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let bytes: [u8; 8] = self.to_le_bytes();
-        let arr1: &[u8; 4] = bytes[0..4].try_into()?;
-        let arr2: &[u8; 4] = bytes[4..8].try_into()?;
-        let arg1: u32 = u32::from_le_bytes(*arr1);
-        let arg2: u32 = u32::from_le_bytes(*arr2);
-        args.push(arg1);
-        args.push(arg2);
-        Ok(())
-    }
+/**
+Implements Incoming and Outcoming for a type through its wire representation `$repr`:
+`$into` converts a value into the representation and `$from` validates it back.
+*/
+#[cfg(any(feature = "chrono", feature = "time"))]
+macro_rules! repr_impls {
+    ($(#[$attr:meta])* $typ:ty, $repr:ty, $into:expr, $from:expr) => {
+        $(#[$attr])*
+        impl Incoming for $typ {
+            const FIXED_WORDS: Option<usize> = <$repr as Incoming>::FIXED_WORDS;
+
+            #[cfg(feature = "guest")]
+            fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
+                let (_, repr) = <$repr as Incoming>::init(args)?;
+                Ok((0, $from(repr)?))
+            }
 
-    #[cfg(feature = "std")]
-    fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
-        args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        Ok(())
-    }
+            #[cfg(feature = "host")]
+            fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
+                let repr: $repr = $into(self);
+                Incoming::args(&repr, args)
+            }
+
+            #[cfg(feature = "host")]
+            fn fill(
+                &self,
+                heap: &mut RefMut<[u8]>,
+                args: &mut Iter<u32>,
+            ) -> Result<(), ProtocolError> {
+                let repr: $repr = $into(self);
+                Incoming::fill(&repr, heap, args)
+            }
+        }
+
+        $(#[$attr])*
+        impl Outcoming for $typ {
+            const FIXED_WORDS: Option<usize> = <$repr as Outcoming>::FIXED_WORDS;
+
+            #[cfg(feature = "guest")]
+            fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+                let repr: $repr = $into(self);
+                Outcoming::args(&repr, args)
+            }
+
+            #[cfg(feature = "host")]
+            fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
+                let repr = <$repr as Outcoming>::read(heap, args)?;
+                $from(repr)
+            }
+        }
+
+        $(#[$attr])*
+        impl Schema for $typ {
+            const SCHEMA: &'static TypeSchema =
+                &TypeSchema::Alias(stringify!($typ), <$repr as Schema>::SCHEMA);
+        }
+    };
 }
 
-impl Outcoming for f64 {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let bytes: [u8; 8] = self.to_le_bytes();
-        let arr1: &[u8; 4] = bytes[0..4]
-            .try_into()
-            .map_err(|_| ProtocolError(BYTES_INTO_ARR4_ERROR))?;
-        let arr2: &[u8; 4] = bytes[4..8]
-            .try_into()
-            .map_err(|_| ProtocolError(BYTES_INTO_ARR4_ERROR))?;
-        let arg1: u32 = u32::from_le_bytes(*arr1);
-        let arg2: u32 = u32::from_le_bytes(*arr2);
-        args.push(arg1);
-        args.push(arg2);
-        Ok(())
-    }
+/**
+The time types with nanos have a second layout of the `legacy_time` flag.
+The layout is chosen by the wire flags when a value is written or read,
+so a host speaks the layout the guest was built with (`with_wire_flags`).
+*/
+#[cfg(any(feature = "chrono", feature = "time"))]
+fn legacy_time() -> bool {
+    wire_flags() & LEGACY_TIME_FLAG != 0
+}
 
-    #[cfg(feature = "std")]
-    fn read(_: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
-        let el1: u32 = *args
-            .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        let el2: u32 = *args
-            .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        let b1: [u8; 4] = el1.to_le_bytes();
-        let b2: [u8; 4] = el2.to_be_bytes();
-        let c: Vec<u8> = [&b1[..], &b2[..]].concat();
-        let d: &[u8; 8] = &c[..].try_into()?;
-        let e = f64::from_le_bytes(*d);
-        Ok(e)
-    }
+/**
+Both layouts of a time type, `Handshake::check` compares the schemas in the layout of the guest.
+*/
+#[cfg(any(feature = "chrono", feature = "time"))]
+pub(crate) trait TimeLayout {
+    const NANOS: &'static TypeSchema;
+    const LEGACY: &'static TypeSchema;
 }
 
+/**
+Implements Incoming and Outcoming for a time type with two wire representations:
+`$repr` with nanos and `$legacy` of the `legacy_time` flag, each with its `into` and `from`.
+The number of args depends on the flags, so there is no FIXED_WORDS,
+and the schema is the layout of the build.
+*/
+#[cfg(any(feature = "chrono", feature = "time"))]
+macro_rules! time_impls {
+    ($typ:ty, $repr:ty, $into:expr, $from:expr, $legacy:ty, $into_legacy:expr, $from_legacy:expr) => {
+        impl Incoming for $typ {
+            fn size_hint(&self) -> usize {
+                if legacy_time() {
+                    let repr: $legacy = $into_legacy(self);
+                    Incoming::size_hint(&repr)
+                } else {
+                    let repr: $repr = $into(self);
+                    Incoming::size_hint(&repr)
+                }
+            }
+
+            #[cfg(feature = "guest")]
+            fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
+                if legacy_time() {
+                    let (_, repr) = <$legacy as Incoming>::init(args)?;
+                    Ok((0, $from_legacy(repr)?))
+                } else {
+                    let (_, repr) = <$repr as Incoming>::init(args)?;
+                    Ok((0, $from(repr)?))
+                }
+            }
+
+            #[cfg(feature = "host")]
+            fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
+                if legacy_time() {
+                    let repr: $legacy = $into_legacy(self);
+                    Incoming::args(&repr, args)
+                } else {
+                    let repr: $repr = $into(self);
+                    Incoming::args(&repr, args)
+                }
+            }
+
+            #[cfg(feature = "host")]
+            fn fill(
+                &self,
+                heap: &mut RefMut<[u8]>,
+                args: &mut Iter<u32>,
+            ) -> Result<(), ProtocolError> {
+                if legacy_time() {
+                    let repr: $legacy = $into_legacy(self);
+                    Incoming::fill(&repr, heap, args)
+                } else {
+                    let repr: $repr = $into(self);
+                    Incoming::fill(&repr, heap, args)
+                }
+            }
+        }
+
+        impl Outcoming for $typ {
+            fn size_hint(&self) -> usize {
+                Incoming::size_hint(self)
+            }
+
+            #[cfg(feature = "guest")]
+            fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+                if legacy_time() {
+                    let repr: $legacy = $into_legacy(self);
+                    Outcoming::args(&repr, args)
+                } else {
+                    let repr: $repr = $into(self);
+                    Outcoming::args(&repr, args)
+                }
+            }
+
+            #[cfg(feature = "host")]
+            fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
+                if legacy_time() {
+                    $from_legacy(<$legacy as Outcoming>::read(heap, args)?)
+                } else {
+                    $from(<$repr as Outcoming>::read(heap, args)?)
+                }
+            }
+        }
+
+        impl TimeLayout for $typ {
+            const NANOS: &'static TypeSchema =
+                &TypeSchema::Alias(stringify!($typ), <$repr as Schema>::SCHEMA);
+            const LEGACY: &'static TypeSchema =
+                &TypeSchema::Alias(stringify!($typ), <$legacy as Schema>::SCHEMA);
+        }
+
+        impl Schema for $typ {
+            const SCHEMA: &'static TypeSchema = if cfg!(feature = "legacy_time") {
+                <$typ as TimeLayout>::LEGACY
+            } else {
+                <$typ as TimeLayout>::NANOS
+            };
+        }
+    };
+}
+
+#[cfg(feature = "std")]
+fn time_error(message: &str) -> ProtocolError {
+    ProtocolError::from(message)
+}
+
+#[cfg(not(feature = "std"))]
+fn time_error(_: &str) -> ProtocolError {
+    ProtocolError(TIME_PARSE_ERROR)
+}
+
+/**
+Wire representation of durations: whole seconds and subsecond nanos with the same sign.
+With the `legacy_time` flag only whole seconds (milliseconds for chrono) are sent.
+*/
+#[cfg(any(feature = "chrono", feature = "time"))]
+type DurationRepr = (i64, i32);
+
+/**
+Wire representation of timestamps: unix seconds and subsecond nanos.
+With the `legacy_time` flag only unix seconds are sent.
+*/
 #[cfg(feature = "chrono")]
-impl Incoming for Duration {
-    #[cfg(not(feature = "std"))]
-    fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let (_, e) = i64::init(args)?;
-        let duration = Duration::milliseconds(e);
-        Ok((0, duration))
-    }
+type TimestampRepr = (i64, u32);
 
-    #[cfg(feature = "std")]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let e = self.num_milliseconds();
-        e.args(args)?;
-        Ok(())
+#[cfg(any(feature = "chrono", feature = "time"))]
+fn check_subsec_nanos(nanos: i32) -> Result<(), ProtocolError> {
+    if nanos.unsigned_abs() >= NANOS_PER_SEC {
+        return Err(time_error("subsecond nanos out of range"));
     }
+    Ok(())
+}
 
-    #[cfg(feature = "std")]
-    fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
-        args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        Ok(())
-    }
+#[cfg(feature = "chrono")]
+fn chrono_duration_into_repr(duration: &Duration) -> DurationRepr {
+    (duration.num_seconds(), duration.subsec_nanos())
 }
 
 #[cfg(feature = "chrono")]
-impl Outcoming for Duration {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let e = self.num_milliseconds();
-        e.args(args)?;
-        Ok(())
-    }
+fn chrono_duration_from_repr((secs, nanos): DurationRepr) -> Result<Duration, ProtocolError> {
+    check_subsec_nanos(nanos)?;
+    Duration::try_seconds(secs)
+        .and_then(|d| d.checked_add(&Duration::nanoseconds(nanos as i64)))
+        .ok_or_else(|| time_error("duration out of range"))
+}
 
-    #[cfg(feature = "std")]
-    fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
-        let e = i64::read(heap, args)?;
-        Ok(Duration::milliseconds(e))
-    }
+#[cfg(feature = "chrono")]
+fn chrono_duration_into_legacy(duration: &Duration) -> i64 {
+    duration.num_milliseconds()
 }
 
 #[cfg(feature = "chrono")]
-impl Incoming for DateTime<Utc> {
-    #[cfg(not(feature = "std"))]
-    fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let (_, secs) = i64::init(args)?;
-        let dt = Self::from_utc(NaiveDateTime::from_timestamp(secs, 0), Utc);
-        Ok((0, dt))
-    }
+fn chrono_duration_from_legacy(millis: i64) -> Result<Duration, ProtocolError> {
+    Duration::try_milliseconds(millis).ok_or_else(|| time_error("duration out of range"))
+}
 
-    #[cfg(feature = "std")]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let secs = self.timestamp();
-        secs.args(args)?;
-        Ok(())
-    }
+#[cfg(feature = "chrono")]
+time_impls!(
+    Duration,
+    DurationRepr,
+    chrono_duration_into_repr,
+    chrono_duration_from_repr,
+    i64,
+    chrono_duration_into_legacy,
+    chrono_duration_from_legacy
+);
 
-    #[cfg(feature = "std")]
-    fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
-        args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        Ok(())
-    }
+#[cfg(feature = "chrono")]
+fn chrono_utc_into_repr(dt: &DateTime<Utc>) -> TimestampRepr {
+    (dt.timestamp(), dt.timestamp_subsec_nanos())
 }
 
 #[cfg(feature = "chrono")]
-impl Outcoming for DateTime<Utc> {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let secs = self.timestamp();
-        secs.args(args)?;
-        Ok(())
-    }
+fn chrono_utc_from_repr((secs, nanos): TimestampRepr) -> Result<DateTime<Utc>, ProtocolError> {
+    DateTime::from_timestamp(secs, nanos).ok_or_else(|| time_error("cannot read datetime"))
+}
 
-    #[cfg(feature = "std")]
-    fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
-        let secs = i64::read(heap, args)?;
-        Ok(Self::from_utc(NaiveDateTime::from_timestamp(secs, 0), Utc))
-    }
+#[cfg(feature = "chrono")]
+fn chrono_utc_into_legacy(dt: &DateTime<Utc>) -> i64 {
+    dt.timestamp()
 }
 
 #[cfg(feature = "chrono")]
-impl Incoming for Date<Utc> {
-    #[cfg(not(feature = "std"))]
-    fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let (_, days) = i32::init(args)?;
-        let d = Self::from_utc(NaiveDate::from_num_days_from_ce(days), Utc);
-        Ok((0, d))
-    }
+fn chrono_utc_from_legacy(secs: i64) -> Result<DateTime<Utc>, ProtocolError> {
+    DateTime::from_timestamp(secs, 0).ok_or_else(|| time_error("cannot read datetime"))
+}
 
-    #[cfg(feature = "std")]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let days = self.num_days_from_ce();
-        days.args(args)?;
-        Ok(())
-    }
+#[cfg(feature = "chrono")]
+time_impls!(
+    DateTime<Utc>,
+    TimestampRepr,
+    chrono_utc_into_repr,
+    chrono_utc_from_repr,
+    i64,
+    chrono_utc_into_legacy,
+    chrono_utc_from_legacy
+);
 
-    #[cfg(feature = "std")]
-    fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
-        args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        Ok(())
-    }
+#[cfg(feature = "chrono")]
+#[allow(deprecated)]
+fn chrono_date_into_repr(date: &chrono::Date<Utc>) -> i32 {
+    date.num_days_from_ce()
 }
 
 #[cfg(feature = "chrono")]
-impl Outcoming for Date<Utc> {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let days = self.num_days_from_ce();
-        days.args(args)?;
-        Ok(())
-    }
+#[allow(deprecated)]
+fn chrono_date_from_repr(days: i32) -> Result<chrono::Date<Utc>, ProtocolError> {
+    let date = chrono_naive_date_from_repr(days)?;
+    Ok(chrono::Date::from_utc(date, Utc))
+}
 
-    #[cfg(feature = "std")]
-    fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
-        let days = i32::read(heap, args)?;
-        Ok(Self::from_utc(NaiveDate::from_num_days_from_ce(days), Utc))
-    }
+#[cfg(feature = "chrono")]
+repr_impls!(
+    #[allow(deprecated)]
+    chrono::Date<Utc>,
+    i32,
+    chrono_date_into_repr,
+    chrono_date_from_repr
+);
+
+/**
+NaiveDate: days from the common era (0001-01-01 is day 1) as i32
+*/
+#[cfg(feature = "chrono")]
+fn chrono_naive_date_into_repr(date: &NaiveDate) -> i32 {
+    date.num_days_from_ce()
 }
 
-// #[cfg(feature = "time")]
-impl Incoming for time::Duration {
-    #[cfg(not(feature = "std"))]
-    fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let (_, e) = i64::init(args)?;
-        let duration = time::Duration::seconds(e);
-        Ok((0, duration))
-    }
+#[cfg(feature = "chrono")]
+fn chrono_naive_date_from_repr(days: i32) -> Result<NaiveDate, ProtocolError> {
+    NaiveDate::from_num_days_from_ce_opt(days).ok_or_else(|| time_error("cannot read date"))
+}
 
-    #[cfg(feature = "std")]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let e = self.whole_seconds();
-        e.args(args)?;
-        Ok(())
-    }
+#[cfg(feature = "chrono")]
+repr_impls!(
+    NaiveDate,
+    i32,
+    chrono_naive_date_into_repr,
+    chrono_naive_date_from_repr
+);
 
-    #[cfg(feature = "std")]
-    fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
-        args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        Ok(())
-    }
+/**
+NaiveTime: seconds from midnight and nanos (nanos >= 1 sec mean a leap second)
+*/
+#[cfg(feature = "chrono")]
+fn chrono_naive_time_into_repr(time: &NaiveTime) -> (u32, u32) {
+    (time.num_seconds_from_midnight(), time.nanosecond())
 }
 
-// #[cfg(feature = "time")]
-impl Outcoming for time::Duration {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let e = self.whole_seconds();
-        e.args(args)?;
-        Ok(())
-    }
+#[cfg(feature = "chrono")]
+fn chrono_naive_time_from_repr((secs, nanos): (u32, u32)) -> Result<NaiveTime, ProtocolError> {
+    NaiveTime::from_num_seconds_from_midnight_opt(secs, nanos)
+        .ok_or_else(|| time_error("cannot read time"))
+}
 
-    #[cfg(feature = "std")]
-    fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
-        let e = i64::read(heap, args)?;
-        Ok(time::Duration::seconds(e))
-    }
+#[cfg(feature = "chrono")]
+repr_impls!(
+    NaiveTime,
+    (u32, u32),
+    chrono_naive_time_into_repr,
+    chrono_naive_time_from_repr
+);
+
+/**
+NaiveDateTime: seconds and nanos as if it was a unix timestamp in UTC
+*/
+#[cfg(feature = "chrono")]
+fn chrono_naive_date_time_into_repr(dt: &NaiveDateTime) -> (i64, u32) {
+    let dt = dt.and_utc();
+    (dt.timestamp(), dt.timestamp_subsec_nanos())
 }
 
-// #[cfg(feature = "time")]
-impl Incoming for time::OffsetDateTime {
-    #[cfg(not(feature = "std"))]
-    fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let (_, secs) = i64::init(args)?;
-        let dt = Self::from_unix_timestamp(secs);
-        match dt {
-            Ok(dt) => Ok((0, dt)),
-            Err(_) => Err(ProtocolError(TIME_PARSE_ERROR)),
-        }
-    }
+#[cfg(feature = "chrono")]
+fn chrono_naive_date_time_from_repr(
+    (secs, nanos): (i64, u32),
+) -> Result<NaiveDateTime, ProtocolError> {
+    DateTime::from_timestamp(secs, nanos)
+        .map(|dt| dt.naive_utc())
+        .ok_or_else(|| time_error("cannot read datetime"))
+}
 
-    #[cfg(feature = "std")]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let secs = self.unix_timestamp();
-        secs.args(args)?;
-        Ok(())
-    }
+#[cfg(feature = "chrono")]
+repr_impls!(
+    NaiveDateTime,
+    (i64, u32),
+    chrono_naive_date_time_into_repr,
+    chrono_naive_date_time_from_repr
+);
 
-    #[cfg(feature = "std")]
-    fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
-        args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        Ok(())
-    }
+/**
+DateTime<FixedOffset>: unix seconds, nanos and the offset east of UTC in seconds
+*/
+#[cfg(feature = "chrono")]
+fn chrono_fixed_into_repr(dt: &DateTime<FixedOffset>) -> (i64, u32, i32) {
+    (
+        dt.timestamp(),
+        dt.timestamp_subsec_nanos(),
+        dt.offset().local_minus_utc(),
+    )
 }
 
-// #[cfg(feature = "time")]
-impl Outcoming for time::OffsetDateTime {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let secs = self.unix_timestamp();
-        secs.args(args)?;
-        Ok(())
-    }
+#[cfg(feature = "chrono")]
+fn chrono_fixed_from_repr(
+    (secs, nanos, offset): (i64, u32, i32),
+) -> Result<DateTime<FixedOffset>, ProtocolError> {
+    let offset = FixedOffset::east_opt(offset).ok_or_else(|| time_error("cannot read offset"))?;
+    let dt =
+        DateTime::from_timestamp(secs, nanos).ok_or_else(|| time_error("cannot read datetime"))?;
+    Ok(dt.with_timezone(&offset))
+}
 
-    #[cfg(feature = "std")]
-    fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
-        let secs = i64::read(heap, args)?;
-        Self::from_unix_timestamp(secs).map_err(|_| ProtocolError::from("cannot read datetime"))
+#[cfg(feature = "chrono")]
+repr_impls!(
+    DateTime<FixedOffset>,
+    (i64, u32, i32),
+    chrono_fixed_into_repr,
+    chrono_fixed_from_repr
+);
+
+/**
+Weekday: days from monday (monday is 0)
+*/
+#[cfg(feature = "chrono")]
+fn chrono_weekday_into_repr(weekday: &Weekday) -> u32 {
+    weekday.num_days_from_monday()
+}
+
+#[cfg(feature = "chrono")]
+fn chrono_weekday_from_repr(days: u32) -> Result<Weekday, ProtocolError> {
+    u8::try_from(days)
+        .ok()
+        .and_then(|days| Weekday::try_from(days).ok())
+        .ok_or_else(|| time_error("cannot read weekday"))
+}
+
+#[cfg(feature = "chrono")]
+repr_impls!(
+    Weekday,
+    u32,
+    chrono_weekday_into_repr,
+    chrono_weekday_from_repr
+);
+
+/**
+Month: number of the month (january is 1)
+*/
+#[cfg(feature = "chrono")]
+fn chrono_month_into_repr(month: &Month) -> u32 {
+    month.number_from_month()
+}
+
+#[cfg(feature = "chrono")]
+fn chrono_month_from_repr(number: u32) -> Result<Month, ProtocolError> {
+    u8::try_from(number)
+        .ok()
+        .and_then(|number| Month::try_from(number).ok())
+        .ok_or_else(|| time_error("cannot read month"))
+}
+
+#[cfg(feature = "chrono")]
+repr_impls!(Month, u32, chrono_month_into_repr, chrono_month_from_repr);
+
+#[cfg(feature = "time")]
+fn time_duration_into_repr(duration: &time::Duration) -> DurationRepr {
+    (duration.whole_seconds(), duration.subsec_nanoseconds())
+}
+
+#[cfg(feature = "time")]
+fn time_duration_from_repr((secs, nanos): DurationRepr) -> Result<time::Duration, ProtocolError> {
+    check_subsec_nanos(nanos)?;
+    // |nanos| < 1 sec, so normalization in Duration::new can't overflow
+    Ok(time::Duration::new(secs, nanos))
+}
+
+#[cfg(feature = "time")]
+fn time_duration_into_legacy(duration: &time::Duration) -> i64 {
+    duration.whole_seconds()
+}
+
+#[cfg(feature = "time")]
+fn time_duration_from_legacy(secs: i64) -> Result<time::Duration, ProtocolError> {
+    Ok(time::Duration::seconds(secs))
+}
+
+#[cfg(feature = "time")]
+time_impls!(
+    time::Duration,
+    DurationRepr,
+    time_duration_into_repr,
+    time_duration_from_repr,
+    i64,
+    time_duration_into_legacy,
+    time_duration_from_legacy
+);
+
+/**
+Wire representation of time::OffsetDateTime: unix seconds, subsecond nanos
+and the UTC offset in whole seconds.
+With the `legacy_time` flag only unix seconds are sent and the offset is lost (UTC on read).
+*/
+#[cfg(feature = "time")]
+type OffsetDateTimeRepr = (i64, u32, i32);
+
+#[cfg(feature = "time")]
+fn offset_date_time_into_repr(dt: &time::OffsetDateTime) -> OffsetDateTimeRepr {
+    (
+        dt.unix_timestamp(),
+        dt.nanosecond(),
+        dt.offset().whole_seconds(),
+    )
+}
+
+#[cfg(feature = "time")]
+fn offset_date_time_from_repr(
+    (secs, nanos, offset): OffsetDateTimeRepr,
+) -> Result<time::OffsetDateTime, ProtocolError> {
+    let offset = time::UtcOffset::from_whole_seconds(offset)
+        .map_err(|_| time_error("cannot read utc offset"))?;
+    time::OffsetDateTime::from_unix_timestamp(secs)
+        .and_then(|dt| dt.replace_nanosecond(nanos))
+        .ok()
+        .and_then(|dt| dt.checked_to_offset(offset))
+        .ok_or_else(|| time_error("cannot read datetime"))
+}
+
+#[cfg(feature = "time")]
+fn offset_date_time_into_legacy(dt: &time::OffsetDateTime) -> i64 {
+    dt.unix_timestamp()
+}
+
+#[cfg(feature = "time")]
+fn offset_date_time_from_legacy(secs: i64) -> Result<time::OffsetDateTime, ProtocolError> {
+    time::OffsetDateTime::from_unix_timestamp(secs).map_err(|_| time_error("cannot read datetime"))
+}
+
+#[cfg(feature = "time")]
+time_impls!(
+    time::OffsetDateTime,
+    OffsetDateTimeRepr,
+    offset_date_time_into_repr,
+    offset_date_time_from_repr,
+    i64,
+    offset_date_time_into_legacy,
+    offset_date_time_from_legacy
+);
+
+/**
+time::Date: julian day as i32
+*/
+#[cfg(feature = "time")]
+fn date_into_repr(date: &time::Date) -> i32 {
+    date.to_julian_day()
+}
+
+#[cfg(feature = "time")]
+fn date_from_repr(days: i32) -> Result<time::Date, ProtocolError> {
+    time::Date::from_julian_day(days).map_err(|_| time_error("cannot read date"))
+}
+
+#[cfg(feature = "time")]
+repr_impls!(time::Date, i32, date_into_repr, date_from_repr);
+
+/**
+convert u32 to time::Time
+*/
+#[cfg(feature = "time")]
+pub const fn time_from_u32(u: u32) -> Result<time::Time, time::error::ComponentRange> {
+    let bytes: [u8; 4] = u.to_le_bytes();
+    let hour = bytes[0];
+    let minute = bytes[1];
+    let second = bytes[2];
+    time::Time::from_hms(hour, minute, second)
+}
+
+/**
+convert time::Time to u32
+*/
+#[cfg(feature = "time")]
+pub const fn time_into_u32(time: &time::Time) -> u32 {
+    let (h, m, s) = time.as_hms();
+    u32::from_le_bytes([h, m, s, 0])
+}
+
+/**
+Wire representation of time::Time: h/m/s packed by time_into_u32 and nanoseconds.
+With the `legacy_time` flag only the packed h/m/s word is sent.
+*/
+#[cfg(feature = "time")]
+type TimeRepr = (u32, u32);
+
+#[cfg(feature = "time")]
+fn time_into_repr(time: &time::Time) -> TimeRepr {
+    (time_into_u32(time), time.nanosecond())
+}
+
+#[cfg(feature = "time")]
+fn time_from_repr((u, nanos): TimeRepr) -> Result<time::Time, ProtocolError> {
+    time_from_u32(u)
+        .and_then(|time| time.replace_nanosecond(nanos))
+        .map_err(|_| time_error("cannot read time"))
+}
+
+#[cfg(feature = "time")]
+fn time_into_legacy(time: &time::Time) -> u32 {
+    time_into_u32(time)
+}
+
+#[cfg(feature = "time")]
+fn time_from_legacy(u: u32) -> Result<time::Time, ProtocolError> {
+    time_from_u32(u).map_err(|_| time_error("cannot read time"))
+}
+
+#[cfg(feature = "time")]
+time_impls!(
+    time::Time,
+    TimeRepr,
+    time_into_repr,
+    time_from_repr,
+    u32,
+    time_into_legacy,
+    time_from_legacy
+);
+
+/**
+Both layouts of every time type with nanos.
+*/
+#[cfg(feature = "alloc")]
+pub(crate) const TIME_LAYOUTS: &[(&TypeSchema, &TypeSchema)] = &[
+    #[cfg(feature = "chrono")]
+    (
+        <Duration as TimeLayout>::NANOS,
+        <Duration as TimeLayout>::LEGACY,
+    ),
+    #[cfg(feature = "chrono")]
+    (
+        <DateTime<Utc> as TimeLayout>::NANOS,
+        <DateTime<Utc> as TimeLayout>::LEGACY,
+    ),
+    #[cfg(feature = "time")]
+    (
+        <time::Duration as TimeLayout>::NANOS,
+        <time::Duration as TimeLayout>::LEGACY,
+    ),
+    #[cfg(feature = "time")]
+    (
+        <time::OffsetDateTime as TimeLayout>::NANOS,
+        <time::OffsetDateTime as TimeLayout>::LEGACY,
+    ),
+    #[cfg(feature = "time")]
+    (
+        <time::Time as TimeLayout>::NANOS,
+        <time::Time as TimeLayout>::LEGACY,
+    ),
+];
+
+/**
+time::PrimitiveDateTime: julian day, h/m/s packed by time_into_u32 and nanoseconds
+*/
+#[cfg(feature = "time")]
+fn primitive_date_time_into_repr(dt: &time::PrimitiveDateTime) -> (i32, u32, u32) {
+    (
+        dt.date().to_julian_day(),
+        time_into_u32(&dt.time()),
+        dt.nanosecond(),
+    )
+}
+
+#[cfg(feature = "time")]
+fn primitive_date_time_from_repr(
+    (days, u, nanos): (i32, u32, u32),
+) -> Result<time::PrimitiveDateTime, ProtocolError> {
+    let date = date_from_repr(days)?;
+    let time = time_from_u32(u)
+        .and_then(|time| time.replace_nanosecond(nanos))
+        .map_err(|_| time_error("cannot read time"))?;
+    Ok(time::PrimitiveDateTime::new(date, time))
+}
+
+#[cfg(feature = "time")]
+repr_impls!(
+    time::PrimitiveDateTime,
+    (i32, u32, u32),
+    primitive_date_time_into_repr,
+    primitive_date_time_from_repr
+);
+
+/**
+time::UtcOffset: offset from UTC in whole seconds
+*/
+#[cfg(feature = "time")]
+fn utc_offset_into_repr(offset: &time::UtcOffset) -> i32 {
+    offset.whole_seconds()
+}
+
+#[cfg(feature = "time")]
+fn utc_offset_from_repr(secs: i32) -> Result<time::UtcOffset, ProtocolError> {
+    time::UtcOffset::from_whole_seconds(secs).map_err(|_| time_error("cannot read utc offset"))
+}
+
+#[cfg(feature = "time")]
+repr_impls!(
+    time::UtcOffset,
+    i32,
+    utc_offset_into_repr,
+    utc_offset_from_repr
+);
+
+/**
+time::Weekday: days from monday (monday is 0)
+*/
+#[cfg(feature = "time")]
+fn weekday_into_repr(weekday: &time::Weekday) -> u32 {
+    weekday.number_days_from_monday() as u32
+}
+
+#[cfg(feature = "time")]
+fn weekday_from_repr(days: u32) -> Result<time::Weekday, ProtocolError> {
+    use time::Weekday::*;
+    match days {
+        0 => Ok(Monday),
+        1 => Ok(Tuesday),
+        2 => Ok(Wednesday),
+        3 => Ok(Thursday),
+        4 => Ok(Friday),
+        5 => Ok(Saturday),
+        6 => Ok(Sunday),
+        _ => Err(time_error("cannot read weekday")),
     }
 }
 
-// #[cfg(feature = "time")]
-impl Incoming for time::Date {
-    #[cfg(not(feature = "std"))]
+#[cfg(feature = "time")]
+repr_impls!(time::Weekday, u32, weekday_into_repr, weekday_from_repr);
+
+/**
+time::Month: number of the month (january is 1)
+*/
+#[cfg(feature = "time")]
+fn month_into_repr(month: &time::Month) -> u32 {
+    *month as u32
+}
+
+#[cfg(feature = "time")]
+fn month_from_repr(number: u32) -> Result<time::Month, ProtocolError> {
+    u8::try_from(number)
+        .ok()
+        .and_then(|number| time::Month::try_from(number).ok())
+        .ok_or_else(|| time_error("cannot read month"))
+}
+
+#[cfg(feature = "time")]
+repr_impls!(time::Month, u32, month_into_repr, month_from_repr);
+
+/**
+core::time::Duration: whole seconds as u64 (2 words) and subsecond nanos as u32 (1 word)
+*/
+impl Incoming for core::time::Duration {
+    const FIXED_WORDS: Option<usize> = Some(3);
+
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let (_, days) = i32::init(args)?;
-        let d = Self::from_julian_day(days);
-        match d {
-            Ok(d) => Ok((0, d)),
-            Err(_) => Err(ProtocolError(TIME_PARSE_ERROR)),
+        let (_, secs) = u64::init(args)?;
+        let (_, nanos) = u32::init(args)?;
+        if nanos >= NANOS_PER_SEC {
+            return Err(time_error("duration nanos out of range"));
         }
+        Ok((0, core::time::Duration::new(secs, nanos)))
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let days = self.to_julian_day();
-        days.args(args)?;
+        Incoming::args(&self.as_secs(), args)?;
+        Incoming::args(&self.subsec_nanos(), args)?;
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
-        args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+        for _ in 0..3 {
+            args.next()
+                .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
+        }
         Ok(())
     }
 }
 
-// #[cfg(feature = "time")]
-impl Outcoming for time::Date {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let days = self.to_julian_day();
-        days.args(args)?;
+impl Outcoming for core::time::Duration {
+    const FIXED_WORDS: Option<usize> = Some(3);
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        Outcoming::args(&self.as_secs(), args)?;
+        Outcoming::args(&self.subsec_nanos(), args)?;
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
-        let days = i32::read(heap, args)?;
-        Self::from_julian_day(days).map_err(|_| ProtocolError::from("cannot read datetime"))
+        let secs = u64::read(heap, args)?;
+        let nanos = u32::read(heap, args)?;
+        if nanos >= NANOS_PER_SEC {
+            return Err(time_error("duration nanos out of range"));
+        }
+        Ok(core::time::Duration::new(secs, nanos))
     }
 }
 
 /**
-convert u32 to time::Time
+std::time::SystemTime: signed seconds since UNIX_EPOCH as i64 (2 words)
+and nanos as u32 (1 word), so times before the epoch are encoded with negative seconds.
+Nanos are always counted forward, e.g. 0.5 sec before the epoch is (-1, 500_000_000).
 */
-// #[cfg(feature = "time")]
-pub const fn time_from_u32(u: u32) -> Result<time::Time, time::error::ComponentRange> {
-    let bytes: [u8; 4] = u.to_le_bytes();
-    let hour = bytes[0];
-    let minute = bytes[1];
-    let second = bytes[2];
-    time::Time::from_hms(hour, minute, second)
+#[cfg(feature = "std")]
+pub fn system_time_into_parts(time: &std::time::SystemTime) -> Result<(i64, u32), ProtocolError> {
+    match time.duration_since(std::time::UNIX_EPOCH) {
+        Ok(after) => {
+            let secs: i64 = after
+                .as_secs()
+                .try_into()
+                .map_err(|_| ProtocolError::from("system time out of range"))?;
+            Ok((secs, after.subsec_nanos()))
+        }
+        Err(err) => {
+            let before = err.duration();
+            let secs: i64 = before
+                .as_secs()
+                .try_into()
+                .map_err(|_| ProtocolError::from("system time out of range"))?;
+            let nanos = before.subsec_nanos();
+            if nanos == 0 {
+                Ok((-secs, 0))
+            } else {
+                Ok((-secs - 1, NANOS_PER_SEC - nanos))
+            }
+        }
+    }
 }
 
 /**
-convert time::Time to u32
+convert parts from system_time_into_parts back to std::time::SystemTime
 */
-// #[cfg(feature = "time")]
-pub const fn time_into_u32(time: &time::Time) -> u32 {
-    let (h, m, s) = time.as_hms();
-    u32::from_le_bytes([h, m, s, 0])
+#[cfg(feature = "std")]
+pub fn system_time_from_parts(
+    secs: i64,
+    nanos: u32,
+) -> Result<std::time::SystemTime, ProtocolError> {
+    if nanos >= NANOS_PER_SEC {
+        return Err(ProtocolError::from("system time nanos out of range"));
+    }
+    let time = if secs >= 0 {
+        std::time::UNIX_EPOCH.checked_add(core::time::Duration::new(secs as u64, nanos))
+    } else {
+        std::time::UNIX_EPOCH
+            .checked_sub(core::time::Duration::new(secs.unsigned_abs(), 0))
+            .and_then(|t| t.checked_add(core::time::Duration::new(0, nanos)))
+    };
+    time.ok_or_else(|| ProtocolError::from("system time out of range"))
 }
 
-// #[cfg(feature = "time")]
-impl Incoming for time::Time {
-    #[cfg(not(feature = "std"))]
+#[cfg(feature = "std")]
+impl Incoming for std::time::SystemTime {
+    const FIXED_WORDS: Option<usize> = Some(3);
+
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let (_, u) = u32::init(args)?;
-        let time = time_from_u32(u).map_err(|_| ProtocolError(TIME_PARSE_ERROR))?;
-        Ok((0, time))
+        let (_, secs) = i64::init(args)?;
+        let (_, nanos) = u32::init(args)?;
+        Ok((0, system_time_from_parts(secs, nanos)?))
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let u = time_into_u32(self);
-        u.args(args)?;
+        let (secs, nanos) = system_time_into_parts(self)?;
+        Incoming::args(&secs, args)?;
+        Incoming::args(&nanos, args)?;
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
-        args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+        for _ in 0..3 {
+            args.next()
+                .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
+        }
         Ok(())
     }
 }
 
-// #[cfg(feature = "time")]
-impl Outcoming for time::Time {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let u = time_into_u32(self);
-        u.args(args)?;
+#[cfg(feature = "std")]
+impl Outcoming for std::time::SystemTime {
+    const FIXED_WORDS: Option<usize> = Some(3);
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        let (secs, nanos) = system_time_into_parts(self)?;
+        Outcoming::args(&secs, args)?;
+        Outcoming::args(&nanos, args)?;
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
-        let u = u32::read(heap, args)?;
-        let time = time_from_u32(u)?;
-        Ok(time)
+        let secs = i64::read(heap, args)?;
+        let nanos = u32::read(heap, args)?;
+        system_time_from_parts(secs, nanos)
     }
 }
 
 // TODO: used?
+#[cfg(feature = "alloc")]
 #[derive(PartialEq, Clone, Default)]
 pub struct Bytes(Vec<u8>);
 
+#[cfg(feature = "alloc")]
+impl From<Vec<u8>> for Bytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Bytes(bytes)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<Bytes> for Vec<u8> {
+    fn from(bytes: Bytes) -> Self {
+        bytes.0
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl AsRef<[u8]> for Bytes {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
 // TODO: for wasm64 other logic
+#[cfg(feature = "alloc")]
 impl Incoming for Bytes {
-    #[cfg(not(feature = "std"))]
+    fn size_hint(&self) -> usize {
+        1 + self.0.len().div_ceil(4)
+    }
+
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let len = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))? as usize;
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
         let quot = len / 4;
         let rem = len % 4;
         let is_divided = rem == 0;
         let mut vec: Vec<u8> = Vec::with_capacity(len);
 
         for _ in 0..quot {
-            let u = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
+            let u = *args
+                .next()
+                .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
             let bytes: [u8; 4] = u.to_le_bytes();
             for byte in &bytes {
                 vec.push(*byte);
@@ -1015,11 +1911,15 @@ impl Incoming for Bytes {
         }
 
         if !is_divided {
-            let u = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
+            let u = *args
+                .next()
+                .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
             let bytes: [u8; 4] = u.to_le_bytes();
             let mut iter = bytes.iter();
             for _ in 0..rem {
-                let byte = *iter.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
+                let byte = *iter
+                    .next()
+                    .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
                 vec.push(byte);
             }
         }
@@ -1027,58 +1927,15 @@ impl Incoming for Bytes {
         Ok((0, Bytes(vec)))
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let len = self.0.len();
-        args.push(len as u32);
-
-        let quot = len / 4;
-        let rem = len % 4;
-        let is_divided = rem == 0;
-        let count = if is_divided { quot } else { quot + 1 };
-        let mut vec: Vec<u32> = Vec::with_capacity(count);
-        let mut iter = self.0.iter();
-
-        for _ in 0..quot {
-            let bytes: [u8; 4] = [
-                *iter
-                    .next()
-                    .ok_or_else(|| ProtocolError("args is end".to_string()))?,
-                *iter
-                    .next()
-                    .ok_or_else(|| ProtocolError("args is end".to_string()))?,
-                *iter
-                    .next()
-                    .ok_or_else(|| ProtocolError("args is end".to_string()))?,
-                *iter
-                    .next()
-                    .ok_or_else(|| ProtocolError("args is end".to_string()))?,
-            ];
-
-            let u = u32::from_le_bytes(bytes);
-            vec.push(u);
-        }
-
-        if !is_divided {
-            let b1 = *iter
-                .next()
-                .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-            let b2 = *iter.next().unwrap_or(&0);
-            let b3 = *iter.next().unwrap_or(&0);
-            let b4 = *iter.next().unwrap_or(&0);
-            let u = u32::from_le_bytes([b1, b2, b3, b4]);
-            vec.push(u);
-        }
-
-        args.append(&mut vec);
-
-        Ok(())
+        bytes_args(self.0.as_slice(), args)
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
         args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?; // len
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?; // len
         let len = self.0.len();
         let quot = len / 4;
         let rem = len % 4;
@@ -1087,59 +1944,30 @@ impl Incoming for Bytes {
 
         for _ in 0..count {
             args.next()
-                .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+                .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         }
         Ok(())
     }
 }
 
 // TODO: for wasm64 other logic
+#[cfg(feature = "alloc")]
 impl Outcoming for Bytes {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let len = self.0.len();
-        args.push(len as u32);
-
-        // TODO: for wasm64 other logic
-        let quot = len / 4;
-        let rem = len % 4;
-        let is_divided = rem == 0;
-        let count = if is_divided { quot } else { quot + 1 };
-        let mut vec: Vec<u32> = Vec::with_capacity(count);
-        let mut iter = self.0.iter();
-
-        for _ in 0..quot {
-            let bytes: [u8; 4] = [
-                *iter.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?,
-                *iter.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?,
-                *iter.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?,
-                *iter.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?,
-            ];
-
-            let u = u32::from_le_bytes(bytes);
-            vec.push(u);
-        }
-
-        if !is_divided {
-            let b1 = *iter.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
-            let b2 = *iter.next().unwrap_or(&0);
-            let b3 = *iter.next().unwrap_or(&0);
-            let b4 = *iter.next().unwrap_or(&0);
-            let u = u32::from_le_bytes([b1, b2, b3, b4]);
-            vec.push(u);
-        }
-
-        args.append(&mut vec);
+    fn size_hint(&self) -> usize {
+        1 + self.0.len().div_ceil(4)
+    }
 
-        Ok(())
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        bytes_args(self.0.as_slice(), args)
     }
 
     // TODO: https://stackoverflow.com/questions/49690459/converting-a-vecu32-to-vecu8-in-place-and-with-minimal-overhead
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(_: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
         let len = *args
             .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))? as usize;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
         let quot = len / 4;
         let rem = len % 4;
         let is_divided = rem == 0;
@@ -1148,7 +1976,7 @@ impl Outcoming for Bytes {
         for _ in 0..quot {
             let u = *args
                 .next()
-                .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+                .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
             let bytes: [u8; 4] = u.to_le_bytes();
             for byte in &bytes {
                 vec.push(*byte);
@@ -1158,28 +1986,38 @@ impl Outcoming for Bytes {
         if !is_divided {
             let u = *args
                 .next()
-                .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+                .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
             let bytes: [u8; 4] = u.to_le_bytes();
             let mut iter = bytes.iter();
             for _ in 0..rem {
                 let byte = *iter
                     .next()
-                    .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+                    .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
                 vec.push(byte);
             }
         }
 
         Ok(Bytes(vec))
     }
+
+    #[cfg(feature = "host")]
+    fn validate(_: &[u8], args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        bytes_words(args).map(drop)
+    }
+
+    #[cfg(feature = "host")]
+    fn skip(args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        bytes_words(args).map(drop)
+    }
 }
 
 /*impl Incoming for String {
     const IS_NEED_INIT_FILL: bool = true;
 
-    #[cfg(not(feature = "std"))]
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
         // узнаем длину строки
-        let arg = args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
+        let arg = args.next().ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         let len = *arg as usize;
         // создаем массив байт указанной длины состоящий из нулевых байт
         let vec = vec![0u8; len];
@@ -1190,17 +2028,17 @@ impl Outcoming for Bytes {
         Ok((ptr, string)) // why ptr?
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        args.push(self.len() as u32);
+        args.push(usize_arg(self.len())?);
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, heap: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
         let ptr: usize = *args
             .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?
             as usize; // its pointer to string
         let mut pointer = ptr;
         for byte in self.as_bytes() {
@@ -1214,37 +2052,46 @@ impl Outcoming for Bytes {
 /*impl Outcoming for String {
     const IS_NEED_READ: bool = true;
 
-    #[cfg(not(feature = "std"))]
+    #[cfg(feature = "guest")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        args.push(self.len() as u32);
+        args.push(usize_arg(self.len())?);
         args.push(self.as_ptr() as u32);
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
         let len = *args
             .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))? as usize;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
         let ptr = *args
             .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))? as usize;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
         let bytes = &heap[ptr..ptr + len];
         Ok(String::from_utf8(bytes.to_vec())?)
     }
 }*/
 
+#[cfg(feature = "alloc")]
 impl Incoming for String {
-    #[cfg(not(feature = "std"))]
+    fn size_hint(&self) -> usize {
+        1 + self.len().div_ceil(4)
+    }
+
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let len = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))? as usize;
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
         let quot = len / 4;
         let rem = len % 4;
         let is_divided = rem == 0;
         let mut vec: Vec<u8> = Vec::with_capacity(len);
 
         for _ in 0..quot {
-            let u = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
+            let u = *args
+                .next()
+                .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
             let bytes: [u8; 4] = u.to_le_bytes();
             for byte in &bytes {
                 vec.push(*byte);
@@ -1252,11 +2099,15 @@ impl Incoming for String {
         }
 
         if !is_divided {
-            let u = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
+            let u = *args
+                .next()
+                .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
             let bytes: [u8; 4] = u.to_le_bytes();
             let mut iter = bytes.iter();
             for _ in 0..rem {
-                let byte = *iter.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
+                let byte = *iter
+                    .next()
+                    .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
                 vec.push(byte);
             }
         }
@@ -1266,117 +2117,45 @@ impl Incoming for String {
         Ok((0, s))
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let len = self.len();
-        args.push(len as u32);
-
-        let quot = len / 4;
-        let rem = len % 4;
-        let is_divided = rem == 0;
-        let count = if is_divided { quot } else { quot + 1 };
-        let mut vec: Vec<u32> = Vec::with_capacity(count);
-        let mut iter = self.as_bytes().iter();
-
-        for _ in 0..quot {
-            let bytes: [u8; 4] = [
-                *iter
-                    .next()
-                    .ok_or_else(|| ProtocolError("args is end".to_string()))?,
-                *iter
-                    .next()
-                    .ok_or_else(|| ProtocolError("args is end".to_string()))?,
-                *iter
-                    .next()
-                    .ok_or_else(|| ProtocolError("args is end".to_string()))?,
-                *iter
-                    .next()
-                    .ok_or_else(|| ProtocolError("args is end".to_string()))?,
-            ];
-
-            let u = u32::from_le_bytes(bytes);
-            vec.push(u);
-        }
-
-        if !is_divided {
-            let b1 = *iter
-                .next()
-                .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-            let b2 = *iter.next().unwrap_or(&0);
-            let b3 = *iter.next().unwrap_or(&0);
-            let b4 = *iter.next().unwrap_or(&0);
-            let u = u32::from_le_bytes([b1, b2, b3, b4]);
-            vec.push(u);
-        }
-
-        args.append(&mut vec);
-
-        Ok(())
+        bytes_args(self.as_bytes(), args)
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
         args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?; // len
-        let len = self.len();
-        let quot = len / 4;
-        let rem = len % 4;
-        let is_divided = rem == 0;
-        let count = if is_divided { quot } else { quot + 1 };
-
-        for _ in 0..count {
-            args.next()
-                .ok_or_else(|| ProtocolError("args is end".to_string()))?;
-        }
-        Ok(())
-    }
-}
-
-impl Outcoming for String {
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?; // len
         let len = self.len();
-        args.push(len as u32);
-
-        // TODO: for wasm64 other logic
         let quot = len / 4;
         let rem = len % 4;
         let is_divided = rem == 0;
-        let count = if is_divided { quot } else { quot + 1 };
-        let mut vec: Vec<u32> = Vec::with_capacity(count);
-        let mut iter = self.as_bytes().iter();
-
-        for _ in 0..quot {
-            let bytes: [u8; 4] = [
-                *iter.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?,
-                *iter.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?,
-                *iter.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?,
-                *iter.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?,
-            ];
-
-            let u = u32::from_le_bytes(bytes);
-            vec.push(u);
-        }
+        let count = if is_divided { quot } else { quot + 1 };
 
-        if !is_divided {
-            let b1 = *iter.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
-            let b2 = *iter.next().unwrap_or(&0);
-            let b3 = *iter.next().unwrap_or(&0);
-            let b4 = *iter.next().unwrap_or(&0);
-            let u = u32::from_le_bytes([b1, b2, b3, b4]);
-            vec.push(u);
+        for _ in 0..count {
+            args.next()
+                .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         }
+        Ok(())
+    }
+}
 
-        args.append(&mut vec);
+#[cfg(feature = "alloc")]
+impl Outcoming for String {
+    fn size_hint(&self) -> usize {
+        1 + self.len().div_ceil(4)
+    }
 
-        Ok(())
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        bytes_args(self.as_bytes(), args)
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(_: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
         let len = *args
             .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))? as usize;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
         let quot = len / 4;
         let rem = len % 4;
         let is_divided = rem == 0;
@@ -1385,7 +2164,7 @@ impl Outcoming for String {
         for _ in 0..quot {
             let u = *args
                 .next()
-                .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+                .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
             let bytes: [u8; 4] = u.to_le_bytes();
             for byte in &bytes {
                 vec.push(*byte);
@@ -1395,13 +2174,13 @@ impl Outcoming for String {
         if !is_divided {
             let u = *args
                 .next()
-                .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+                .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
             let bytes: [u8; 4] = u.to_le_bytes();
             let mut iter = bytes.iter();
             for _ in 0..rem {
                 let byte = *iter
                     .next()
-                    .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+                    .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
                 vec.push(byte);
             }
         }
@@ -1410,15 +2189,36 @@ impl Outcoming for String {
 
         Ok(s)
     }
+
+    #[cfg(feature = "host")]
+    fn validate(_: &[u8], args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        let (len, words) = bytes_words(args)?;
+        check_utf8(len, words)
+    }
+
+    #[cfg(feature = "host")]
+    fn skip(args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        bytes_words(args).map(drop)
+    }
 }
 
 // TODO: other realization for bytes vec
+#[cfg(feature = "alloc")]
 impl<T: Incoming> Incoming for Vec<T> {
     const IS_NEED_INIT_FILL: bool = T::IS_NEED_INIT_FILL;
 
-    #[cfg(not(feature = "std"))]
+    fn size_hint(&self) -> usize {
+        match T::FIXED_WORDS {
+            Some(words) => 1 + words * self.len(),
+            None => 1 + self.iter().map(T::size_hint).sum::<usize>(),
+        }
+    }
+
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let arg = args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))?;
+        let arg = args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         let len = *arg as usize;
         let mut vec = Vec::with_capacity(len);
         for _ in 0..len {
@@ -1431,20 +2231,19 @@ impl<T: Incoming> Incoming for Vec<T> {
         Ok((ptr, vec))
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let len = self.len();
-        args.push(len as u32);
+        args.push(usize_arg(self.len())?);
         for item in self {
             item.args(args)?;
         }
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, heap: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
         args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?; // len
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?; // len
         for item in self {
             item.fill(heap, args)?;
         }
@@ -1452,24 +2251,32 @@ impl<T: Incoming> Incoming for Vec<T> {
     }
 }
 
+#[cfg(feature = "alloc")]
 impl<T: Outcoming> Outcoming for Vec<T> {
     const IS_NEED_READ: bool = T::IS_NEED_READ;
 
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let len = self.len() as u32;
-        args.push(len);
+    fn size_hint(&self) -> usize {
+        match T::FIXED_WORDS {
+            Some(words) => 1 + words * self.len(),
+            None => 1 + self.iter().map(T::size_hint).sum::<usize>(),
+        }
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        let len = usize_arg(self.len())?;
+        args.push(len)?;
         for item in self {
             item.args(args)?;
         }
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
         let len = *args
             .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))? as usize;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
         let mut vec: Vec<T> = Vec::with_capacity(len);
 
         for _ in 0..len {
@@ -1478,14 +2285,37 @@ impl<T: Outcoming> Outcoming for Vec<T> {
         }
         Ok(vec)
     }
+
+    #[cfg(feature = "host")]
+    fn validate(heap: &[u8], args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+        validate_items::<T>(len, heap, args)
+    }
+
+    #[cfg(feature = "host")]
+    fn skip(args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+        skip_items::<T>(len, args)
+    }
 }
 
 impl<T: Incoming> Incoming for Option<T> {
     const IS_NEED_INIT_FILL: bool = T::IS_NEED_INIT_FILL;
 
-    #[cfg(not(feature = "std"))]
+    fn size_hint(&self) -> usize {
+        1 + self.as_ref().map_or(0, T::size_hint)
+    }
+
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let is_some: bool = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))? != 0;
+        let is_some: bool = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?
+            != 0;
 
         if !is_some {
             Ok((0, None))
@@ -1496,7 +2326,7 @@ impl<T: Incoming> Incoming for Option<T> {
         }
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
         match self {
             None => {
@@ -1510,10 +2340,10 @@ impl<T: Incoming> Incoming for Option<T> {
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, heap: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
         args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         if let Some(item) = self {
             item.fill(heap, args)?;
         }
@@ -1524,25 +2354,29 @@ impl<T: Incoming> Incoming for Option<T> {
 impl<T: Outcoming> Outcoming for Option<T> {
     const IS_NEED_READ: bool = T::IS_NEED_READ;
 
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
+    fn size_hint(&self) -> usize {
+        1 + self.as_ref().map_or(0, T::size_hint)
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
         match self {
             None => {
-                args.push(0);
+                args.push(0)?;
             }
             Some(item) => {
-                args.push(1);
+                args.push(1)?;
                 item.args(args)?;
             }
         }
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
         let is_some = *args
             .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?
             != 0;
 
         if !is_some {
@@ -1551,6 +2385,28 @@ impl<T: Outcoming> Outcoming for Option<T> {
             Ok(Some(T::read(heap, args)?))
         }
     }
+
+    #[cfg(feature = "host")]
+    fn validate(heap: &[u8], args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        match *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?
+        {
+            0 => Ok(()),
+            _ => T::validate(heap, args),
+        }
+    }
+
+    #[cfg(feature = "host")]
+    fn skip(args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        match *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?
+        {
+            0 => Ok(()),
+            _ => T::skip(args),
+        }
+    }
 }
 
 #[cfg(any(feature = "std", feature = "hashmap"))]
@@ -1560,23 +2416,32 @@ where
 {
     const IS_NEED_INIT_FILL: bool = K::IS_NEED_INIT_FILL || V::IS_NEED_INIT_FILL;
 
-    #[cfg(not(feature = "std"))]
+    fn size_hint(&self) -> usize {
+        1 + self
+            .iter()
+            .map(|(key, value)| key.size_hint() + value.size_hint())
+            .sum::<usize>()
+    }
+
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let len = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))? as usize;
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
         let mut map: HashMap<K, V> = HashMap::with_capacity(len);
         for _ in 0..len {
-            let (key_ptr, key) = K::init(args)?;
-            let (value_ptr, value) = V::init(args)?;
-            map.insert(key, value)
-                .ok_or(ProtocolError(MAP_INSERT_ERROR))?;
+            let (_, key) = K::init(args)?;
+            let (_, value) = V::init(args)?;
+            if map.insert(key, value).is_some() {
+                return Err(ProtocolError::from(MAP_INSERT_ERROR));
+            }
         }
         Ok((0, map))
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let len = self.len();
-        args.push(len as u32);
+        args.push(usize_arg(self.len())?);
         for (key, value) in self {
             key.args(args)?;
             value.args(args)?;
@@ -1584,10 +2449,10 @@ where
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, heap: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
         args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?; // len
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?; // len
         for (key, value) in self {
             key.fill(heap, args)?;
             value.fill(heap, args)?;
@@ -1603,10 +2468,17 @@ where
 {
     const IS_NEED_READ: bool = K::IS_NEED_READ || V::IS_NEED_READ;
 
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let len = self.len() as u32;
-        args.push(len);
+    fn size_hint(&self) -> usize {
+        1 + self
+            .iter()
+            .map(|(key, value)| key.size_hint() + value.size_hint())
+            .sum::<usize>()
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        let len = usize_arg(self.len())?;
+        args.push(len)?;
         for (key, value) in self {
             key.args(args)?;
             value.args(args)?;
@@ -1614,45 +2486,72 @@ where
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
         let len = *args
             .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))? as usize;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
         let mut map: HashMap<K, V> = HashMap::with_capacity(len);
         for _ in 0..len {
             let key: K = K::read(heap, args)?;
             let value: V = V::read(heap, args)?;
-            map.insert(key, value)
-                .ok_or_else(|| ProtocolError("map already have item".to_string()))?;
+            if map.insert(key, value).is_some() {
+                return Err(ProtocolError::from(MAP_INSERT_ERROR));
+            }
         }
         Ok(map)
     }
+
+    #[cfg(feature = "host")]
+    fn validate(heap: &[u8], args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+        validate_items::<(K, V)>(len, heap, args)
+    }
+
+    #[cfg(feature = "host")]
+    fn skip(args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+        skip_items::<(K, V)>(len, args)
+    }
 }
 
+#[cfg(feature = "alloc")]
 impl<K: Incoming, V: Incoming> Incoming for BTreeMap<K, V>
 where
     K: Ord,
 {
     const IS_NEED_INIT_FILL: bool = K::IS_NEED_INIT_FILL || V::IS_NEED_INIT_FILL;
 
-    #[cfg(not(feature = "std"))]
+    fn size_hint(&self) -> usize {
+        1 + self
+            .iter()
+            .map(|(key, value)| key.size_hint() + value.size_hint())
+            .sum::<usize>()
+    }
+
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let len = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))? as usize;
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
         let mut map: BTreeMap<K, V> = BTreeMap::new();
         for _ in 0..len {
             let (_, key) = K::init(args)?;
             let (_, value) = V::init(args)?;
-            map.insert(key, value)
-                .ok_or(ProtocolError(MAP_INSERT_ERROR))?;
+            if map.insert(key, value).is_some() {
+                return Err(ProtocolError::from(MAP_INSERT_ERROR));
+            }
         }
         Ok((0, map))
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let len = self.len();
-        args.push(len as u32);
+        args.push(usize_arg(self.len())?);
         for (key, value) in self {
             key.args(args)?;
             value.args(args)?;
@@ -1660,10 +2559,10 @@ where
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, heap: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
         args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?; // len
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?; // len
         for (key, value) in self {
             key.fill(heap, args)?;
             value.fill(heap, args)?;
@@ -1672,16 +2571,24 @@ where
     }
 }
 
+#[cfg(feature = "alloc")]
 impl<K: Outcoming, V: Outcoming> Outcoming for BTreeMap<K, V>
 where
     K: Ord,
 {
     const IS_NEED_READ: bool = K::IS_NEED_READ || V::IS_NEED_READ;
 
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let len = self.len() as u32;
-        args.push(len);
+    fn size_hint(&self) -> usize {
+        1 + self
+            .iter()
+            .map(|(key, value)| key.size_hint() + value.size_hint())
+            .sum::<usize>()
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        let len = usize_arg(self.len())?;
+        args.push(len)?;
         for (key, value) in self {
             key.args(args)?;
             value.args(args)?;
@@ -1689,20 +2596,37 @@ where
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
         let len = *args
             .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))? as usize;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
         let mut map: BTreeMap<K, V> = BTreeMap::new();
         for _ in 0..len {
             let key: K = K::read(heap, args)?;
             let value: V = V::read(heap, args)?;
-            map.insert(key, value)
-                .ok_or_else(|| ProtocolError("map already have item".to_string()))?;
+            if map.insert(key, value).is_some() {
+                return Err(ProtocolError::from(MAP_INSERT_ERROR));
+            }
         }
         Ok(map)
     }
+
+    #[cfg(feature = "host")]
+    fn validate(heap: &[u8], args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+        validate_items::<(K, V)>(len, heap, args)
+    }
+
+    #[cfg(feature = "host")]
+    fn skip(args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+        skip_items::<(K, V)>(len, args)
+    }
 }
 
 #[cfg(all(any(feature = "hashmap", feature = "std"), feature = "map"))]
@@ -1712,24 +2636,33 @@ where
 {
     const IS_NEED_INIT_FILL: bool = K::IS_NEED_INIT_FILL || V::IS_NEED_INIT_FILL;
 
-    #[cfg(not(feature = "std"))]
+    fn size_hint(&self) -> usize {
+        1 + self
+            .iter()
+            .map(|(key, value)| key.size_hint() + value.size_hint())
+            .sum::<usize>()
+    }
+
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let len = *args.next().ok_or(ProtocolError(ARGS_NEXT_ERROR))? as usize;
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
         let mut map: FxHashMap<K, V> =
             FxHashMap::with_capacity_and_hasher(len, FxBuildHasher::default());
         for _ in 0..len {
-            let (key_ptr, key) = K::init(args)?;
-            let (value_ptr, value) = V::init(args)?;
-            map.insert(key, value)
-                .ok_or(ProtocolError(MAP_INSERT_ERROR))?;
+            let (_, key) = K::init(args)?;
+            let (_, value) = V::init(args)?;
+            if map.insert(key, value).is_some() {
+                return Err(ProtocolError::from(MAP_INSERT_ERROR));
+            }
         }
         Ok((0, map))
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let len = self.len();
-        args.push(len as u32);
+        args.push(usize_arg(self.len())?);
         for (key, value) in self {
             key.args(args)?;
             value.args(args)?;
@@ -1737,10 +2670,10 @@ where
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, heap: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
         args.next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))?;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
         for (key, value) in self {
             key.fill(heap, args)?;
             value.fill(heap, args)?;
@@ -1756,10 +2689,17 @@ where
 {
     const IS_NEED_READ: bool = K::IS_NEED_READ || V::IS_NEED_READ;
 
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        let len = self.len() as u32;
-        args.push(len);
+    fn size_hint(&self) -> usize {
+        1 + self
+            .iter()
+            .map(|(key, value)| key.size_hint() + value.size_hint())
+            .sum::<usize>()
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        let len = usize_arg(self.len())?;
+        args.push(len)?;
         for (key, value) in self {
             key.args(args)?;
             value.args(args)?;
@@ -1767,207 +2707,503 @@ where
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
         let len = *args
             .next()
-            .ok_or_else(|| ProtocolError("args is end".to_string()))? as usize;
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
         let mut map: FxHashMap<K, V> =
             FxHashMap::with_capacity_and_hasher(len, FxBuildHasher::default());
         for _ in 0..len {
             let key: K = K::read(heap, args)?;
             let value: V = V::read(heap, args)?;
-            map.insert(key, value)
-                .ok_or_else(|| ProtocolError("map already have item".to_string()))?;
+            if map.insert(key, value).is_some() {
+                return Err(ProtocolError::from(MAP_INSERT_ERROR));
+            }
         }
         Ok(map)
     }
+
+    #[cfg(feature = "host")]
+    fn validate(heap: &[u8], args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+        validate_items::<(K, V)>(len, heap, args)
+    }
+
+    #[cfg(feature = "host")]
+    fn skip(args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+        skip_items::<(K, V)>(len, args)
+    }
 }
 
-impl<T1: Incoming, T2: Incoming> Incoming for (T1, T2) {
-    const IS_NEED_INIT_FILL: bool = T1::IS_NEED_INIT_FILL || T2::IS_NEED_INIT_FILL;
+/**
+Implements Incoming and Outcoming for a tuple: elements are encoded one after another,
+without any length prefix.
+*/
+macro_rules! tuple_impls {
+    ($($name:ident $var:ident $idx:tt),*) => {
+        impl<$($name: Incoming),*> Incoming for ($($name,)*) {
+            const IS_NEED_INIT_FILL: bool = false $(|| $name::IS_NEED_INIT_FILL)*;
+
+            const FIXED_WORDS: Option<usize> = {
+                let words = Some(0);
+                $(let words = add_fixed_words(words, $name::FIXED_WORDS);)*
+                words
+            };
+
+            fn size_hint(&self) -> usize {
+                0 $(+ self.$idx.size_hint())*
+            }
+
+            #[cfg(feature = "guest")]
+            #[allow(unused_variables)]
+            fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
+                $(let (_, $var) = $name::init(args)?;)*
+                Ok((0, ($($var,)*)))
+            }
+
+            #[cfg(feature = "host")]
+            #[allow(unused_variables)]
+            fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
+                $(self.$idx.args(args)?;)*
+                Ok(())
+            }
+
+            #[cfg(feature = "host")]
+            #[allow(unused_variables)]
+            fn fill(&self, heap: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+                $(self.$idx.fill(heap, args)?;)*
+                Ok(())
+            }
+        }
+
+        impl<$($name: Outcoming),*> Outcoming for ($($name,)*) {
+            const IS_NEED_READ: bool = false $(|| $name::IS_NEED_READ)*;
+
+            const FIXED_WORDS: Option<usize> = {
+                let words = Some(0);
+                $(let words = add_fixed_words(words, $name::FIXED_WORDS);)*
+                words
+            };
+
+            fn size_hint(&self) -> usize {
+                0 $(+ self.$idx.size_hint())*
+            }
+
+            #[cfg(feature = "guest")]
+            #[allow(unused_variables)]
+            fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+                $(self.$idx.args(args)?;)*
+                Ok(())
+            }
+
+            #[cfg(feature = "host")]
+            #[allow(unused_variables)]
+            fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
+                $(let $var = $name::read(heap, args)?;)*
+                Ok(($($var,)*))
+            }
+
+            #[cfg(feature = "host")]
+            #[allow(unused_variables)]
+            fn validate(heap: &[u8], args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+                $($name::validate(heap, args)?;)*
+                Ok(())
+            }
+
+            #[cfg(feature = "host")]
+            #[allow(unused_variables)]
+            fn skip(args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+                $($name::skip(args)?;)*
+                Ok(())
+            }
+        }
+    };
+}
+
+tuple_impls!();
+tuple_impls!(T1 t1 0);
+tuple_impls!(T1 t1 0, T2 t2 1);
+tuple_impls!(T1 t1 0, T2 t2 1, T3 t3 2);
+tuple_impls!(T1 t1 0, T2 t2 1, T3 t3 2, T4 t4 3);
+tuple_impls!(T1 t1 0, T2 t2 1, T3 t3 2, T4 t4 3, T5 t5 4);
+tuple_impls!(T1 t1 0, T2 t2 1, T3 t3 2, T4 t4 3, T5 t5 4, T6 t6 5);
+tuple_impls!(T1 t1 0, T2 t2 1, T3 t3 2, T4 t4 3, T5 t5 4, T6 t6 5, T7 t7 6);
+tuple_impls!(T1 t1 0, T2 t2 1, T3 t3 2, T4 t4 3, T5 t5 4, T6 t6 5, T7 t7 6, T8 t8 7);
+tuple_impls!(T1 t1 0, T2 t2 1, T3 t3 2, T4 t4 3, T5 t5 4, T6 t6 5, T7 t7 6, T8 t8 7, T9 t9 8);
+tuple_impls!(T1 t1 0, T2 t2 1, T3 t3 2, T4 t4 3, T5 t5 4, T6 t6 5, T7 t7 6, T8 t8 7, T9 t9 8, T10 t10 9);
+tuple_impls!(T1 t1 0, T2 t2 1, T3 t3 2, T4 t4 3, T5 t5 4, T6 t6 5, T7 t7 6, T8 t8 7, T9 t9 8, T10 t10 9, T11 t11 10);
+tuple_impls!(T1 t1 0, T2 t2 1, T3 t3 2, T4 t4 3, T5 t5 4, T6 t6 5, T7 t7 6, T8 t8 7, T9 t9 8, T10 t10 9, T11 t11 10, T12 t12 11);
+
+#[cfg(feature = "alloc")]
+impl<T: Incoming> Incoming for Box<T> {
+    const IS_NEED_INIT_FILL: bool = T::IS_NEED_INIT_FILL;
+
+    const FIXED_WORDS: Option<usize> = T::FIXED_WORDS;
 
-    #[cfg(not(feature = "std"))]
+    fn size_hint(&self) -> usize {
+        self.as_ref().size_hint()
+    }
+
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let (_, t1) = T1::init(args)?;
-        let (_, t2) = T2::init(args)?;
-        Ok((0, (t1, t2)))
+        let (_, t) = T::init(args)?;
+        Ok((0, Box::new(t)))
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        self.0.args(args)?;
-        self.1.args(args)?;
+        self.as_ref().args(args)?;
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, heap: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
-        self.0.fill(heap, args)?;
-        self.1.fill(heap, args)?;
+        self.as_ref().fill(heap, args)?;
         Ok(())
     }
 }
 
-impl<T1: Outcoming, T2: Outcoming> Outcoming for (T1, T2) {
-    const IS_NEED_READ: bool = T1::IS_NEED_READ || T2::IS_NEED_READ;
+#[cfg(feature = "alloc")]
+impl<T: Outcoming> Outcoming for Box<T> {
+    const IS_NEED_READ: bool = T::IS_NEED_READ;
+
+    const FIXED_WORDS: Option<usize> = T::FIXED_WORDS;
 
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        self.0.args(args)?;
-        self.1.args(args)?;
+    fn size_hint(&self) -> usize {
+        self.as_ref().size_hint()
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        self.as_ref().args(args)?;
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
-        let t1 = T1::read(heap, args)?;
-        let t2 = T2::read(heap, args)?;
-        Ok((t1, t2))
+        let t = T::read(heap, args)?;
+        Ok(Box::new(t))
+    }
+
+    #[cfg(feature = "host")]
+    fn validate(heap: &[u8], args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        T::validate(heap, args)
+    }
+
+    #[cfg(feature = "host")]
+    fn skip(args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        T::skip(args)
+    }
+}
+
+/**
+Array of N items decoded one by one, stops on the first error.
+*/
+#[cfg(any(feature = "guest", feature = "host"))]
+pub(crate) fn array_try_from_fn<T, const N: usize>(
+    mut f: impl FnMut() -> Result<T, ProtocolError>,
+) -> Result<[T; N], ProtocolError> {
+    let mut error = None;
+    let items: [Option<T>; N] = core::array::from_fn(|_| {
+        if error.is_some() {
+            return None;
+        }
+        match f() {
+            Ok(item) => Some(item),
+            Err(e) => {
+                error = Some(e);
+                None
+            }
+        }
+    });
+    if let Some(e) = error {
+        return Err(e);
+    }
+    // all items are Some without an error
+    Ok(items.map(|item| item.unwrap_or_else(|| unreachable!())))
+}
+
+/**
+Bytes of a byte string from its args, 4 bytes per arg in little endian.
+*/
+#[cfg(all(
+    any(feature = "heapless", all(feature = "serde", feature = "alloc")),
+    any(feature = "guest", feature = "host")
+))]
+pub(crate) fn bytes_from_args(
+    len: usize,
+    mut next: impl FnMut() -> Option<u32>,
+    mut push: impl FnMut(u8) -> Result<(), ProtocolError>,
+) -> Result<(), ProtocolError> {
+    let mut rem = len;
+    while rem > 0 {
+        let u = next().ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
+        let count = rem.min(4);
+        for byte in &u.to_le_bytes()[..count] {
+            push(*byte)?;
+        }
+        rem -= count;
     }
+    Ok(())
 }
 
-impl<T1: Incoming, T2: Incoming, T3: Incoming> Incoming for (T1, T2, T3) {
-    const IS_NEED_INIT_FILL: bool =
-        T1::IS_NEED_INIT_FILL || T2::IS_NEED_INIT_FILL || T3::IS_NEED_INIT_FILL;
+/// Arrays have a fixed length, so only the items are in the args.
+impl<T: Incoming, const N: usize> Incoming for [T; N] {
+    const IS_NEED_INIT_FILL: bool = T::IS_NEED_INIT_FILL;
+
+    const FIXED_WORDS: Option<usize> = mul_fixed_words(T::FIXED_WORDS, N);
+
+    fn size_hint(&self) -> usize {
+        self.iter().map(T::size_hint).sum()
+    }
 
-    #[cfg(not(feature = "std"))]
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let (_, t1) = T1::init(args)?;
-        let (_, t2) = T2::init(args)?;
-        let (_, t3) = T3::init(args)?;
-        Ok((0, (t1, t2, t3)))
+        let arr = array_try_from_fn(|| Ok(T::init(args)?.1))?;
+        Ok((0, arr))
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        self.0.args(args)?;
-        self.1.args(args)?;
-        self.2.args(args)?;
+        for item in self {
+            item.args(args)?;
+        }
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, heap: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
-        self.0.fill(heap, args)?;
-        self.1.fill(heap, args)?;
-        self.2.fill(heap, args)?;
+        for item in self {
+            item.fill(heap, args)?;
+        }
         Ok(())
     }
 }
 
-impl<T1: Outcoming, T2: Outcoming, T3: Outcoming> Outcoming for (T1, T2, T3) {
-    const IS_NEED_READ: bool = T1::IS_NEED_READ || T2::IS_NEED_READ || T3::IS_NEED_READ;
+impl<T: Outcoming, const N: usize> Outcoming for [T; N] {
+    const IS_NEED_READ: bool = T::IS_NEED_READ;
+
+    const FIXED_WORDS: Option<usize> = mul_fixed_words(T::FIXED_WORDS, N);
 
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        self.0.args(args)?;
-        self.1.args(args)?;
-        self.2.args(args)?;
+    fn size_hint(&self) -> usize {
+        self.iter().map(T::size_hint).sum()
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        for item in self {
+            item.args(args)?;
+        }
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
-        let t1 = T1::read(heap, args)?;
-        let t2 = T2::read(heap, args)?;
-        let t3 = T3::read(heap, args)?;
-        Ok((t1, t2, t3))
+        array_try_from_fn(|| T::read(heap, args))
     }
-}
 
-impl<T1: Incoming, T2: Incoming, T3: Incoming, T4: Incoming> Incoming for (T1, T2, T3, T4) {
-    const IS_NEED_INIT_FILL: bool = T1::IS_NEED_INIT_FILL
-        || T2::IS_NEED_INIT_FILL
-        || T3::IS_NEED_INIT_FILL
-        || T4::IS_NEED_INIT_FILL;
+    #[cfg(feature = "host")]
+    fn validate(heap: &[u8], args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        validate_items::<T>(N, heap, args)
+    }
 
-    #[cfg(not(feature = "std"))]
-    fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let (_, t1) = T1::init(args)?;
-        let (_, t2) = T2::init(args)?;
-        let (_, t3) = T3::init(args)?;
-        let (_, t4) = T4::init(args)?;
-        Ok((0, (t1, t2, t3, t4)))
+    #[cfg(feature = "host")]
+    fn skip(args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        skip_items::<T>(N, args)
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> Incoming for heapless::String<N> {
+    fn size_hint(&self) -> usize {
+        1 + self.len().div_ceil(4)
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "guest")]
+    fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+        let mut vec: heapless::Vec<u8, N> = heapless::Vec::new();
+        bytes_from_args(
+            len,
+            || args.next().map(|u| *u),
+            |byte| {
+                vec.push(byte)
+                    .map_err(|_| ProtocolError::from(CAPACITY_ERROR))
+            },
+        )?;
+        Ok((0, heapless::String::from_utf8(vec)?))
+    }
+
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        self.0.args(args)?;
-        self.1.args(args)?;
-        self.2.args(args)?;
-        self.3.args(args)?;
-        Ok(())
+        bytes_args(self.as_bytes(), args)
     }
 
-    #[cfg(feature = "std")]
-    fn fill(&self, heap: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
-        self.0.fill(heap, args)?;
-        self.1.fill(heap, args)?;
-        self.2.fill(heap, args)?;
-        self.3.fill(heap, args)?;
+    #[cfg(feature = "host")]
+    fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        args.next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?; // len
+        for _ in 0..self.len().div_ceil(4) {
+            args.next()
+                .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
+        }
         Ok(())
     }
 }
 
-impl<T1: Outcoming, T2: Outcoming, T3: Outcoming, T4: Outcoming> Outcoming for (T1, T2, T3, T4) {
-    const IS_NEED_READ: bool =
-        T1::IS_NEED_READ || T2::IS_NEED_READ || T3::IS_NEED_READ || T4::IS_NEED_READ;
+#[cfg(feature = "heapless")]
+impl<const N: usize> Outcoming for heapless::String<N> {
+    fn size_hint(&self) -> usize {
+        1 + self.len().div_ceil(4)
+    }
 
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        self.0.args(args)?;
-        self.1.args(args)?;
-        self.2.args(args)?;
-        self.3.args(args)?;
-        Ok(())
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        bytes_args(self.as_bytes(), args)
     }
 
-    #[cfg(feature = "std")]
-    fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
-        let t1 = T1::read(heap, args)?;
-        let t2 = T2::read(heap, args)?;
-        let t3 = T3::read(heap, args)?;
-        let t4 = T4::read(heap, args)?;
-        Ok((t1, t2, t3, t4))
+    #[cfg(feature = "host")]
+    fn read(_: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+        let mut vec: heapless::Vec<u8, N> = heapless::Vec::new();
+        bytes_from_args(
+            len,
+            || args.next().copied(),
+            |byte| {
+                vec.push(byte)
+                    .map_err(|_| ProtocolError::from(CAPACITY_ERROR))
+            },
+        )?;
+        Ok(heapless::String::from_utf8(vec)?)
+    }
+
+    #[cfg(feature = "host")]
+    fn validate(_: &[u8], args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        let (len, words) = bytes_words(args)?;
+        if len > N {
+            return Err(ProtocolError::from(CAPACITY_ERROR));
+        }
+        check_utf8(len, words)
+    }
+
+    #[cfg(feature = "host")]
+    fn skip(args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        bytes_words(args).map(drop)
     }
 }
 
-impl<T: Incoming> Incoming for Box<T> {
+#[cfg(feature = "heapless")]
+impl<T: Incoming, const N: usize> Incoming for heapless::Vec<T, N> {
     const IS_NEED_INIT_FILL: bool = T::IS_NEED_INIT_FILL;
 
-    #[cfg(not(feature = "std"))]
+    fn size_hint(&self) -> usize {
+        match T::FIXED_WORDS {
+            Some(words) => 1 + words * self.len(),
+            None => 1 + self.iter().map(T::size_hint).sum::<usize>(),
+        }
+    }
+
+    #[cfg(feature = "guest")]
     fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
-        let (_, t) = T::init(args)?;
-        Ok((0, Box::new(t)))
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+        if len > N {
+            return Err(ProtocolError::from(CAPACITY_ERROR));
+        }
+        let mut vec = heapless::Vec::new();
+        for _ in 0..len {
+            let item: T = T::init(args)?.1;
+            vec.push(item)
+                .map_err(|_| ProtocolError::from(CAPACITY_ERROR))?;
+        }
+        Ok((0, vec))
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        self.as_ref().args(args)?;
+        args.push(usize_arg(self.len())?);
+        for item in self {
+            item.args(args)?;
+        }
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn fill(&self, heap: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
-        self.as_ref().fill(heap, args)?;
+        args.next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?; // len
+        for item in self {
+            item.fill(heap, args)?;
+        }
         Ok(())
     }
 }
 
-impl<T: Outcoming> Outcoming for Box<T> {
+#[cfg(feature = "heapless")]
+impl<T: Outcoming, const N: usize> Outcoming for heapless::Vec<T, N> {
     const IS_NEED_READ: bool = T::IS_NEED_READ;
 
-    #[cfg(not(feature = "std"))]
-    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
-        self.as_ref().args(args)?;
+    fn size_hint(&self) -> usize {
+        match T::FIXED_WORDS {
+            Some(words) => 1 + words * self.len(),
+            None => 1 + self.iter().map(T::size_hint).sum::<usize>(),
+        }
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        args.push(usize_arg(self.len())?)?;
+        for item in self {
+            item.args(args)?;
+        }
         Ok(())
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "host")]
     fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
-        let t = T::read(heap, args)?;
-        Ok(Box::new(t))
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+        if len > N {
+            return Err(ProtocolError::from(CAPACITY_ERROR));
+        }
+        let mut vec = heapless::Vec::new();
+        for _ in 0..len {
+            let item: T = T::read(heap, args)?;
+            vec.push(item)
+                .map_err(|_| ProtocolError::from(CAPACITY_ERROR))?;
+        }
+        Ok(vec)
+    }
+
+    #[cfg(feature = "host")]
+    fn validate(heap: &[u8], args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+        if len > N {
+            return Err(ProtocolError::from(CAPACITY_ERROR));
+        }
+        validate_items::<T>(len, heap, args)
+    }
+
+    #[cfg(feature = "host")]
+    fn skip(args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+        skip_items::<T>(len, args)
     }
 }
 
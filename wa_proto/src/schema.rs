@@ -0,0 +1,1219 @@
+//! Schema of the types: the args carry no type information, so the host and the guest
+//! compare the schemas of their types (or only the hashes) when the module is instantiated.
+//!
+//! The schema is sent as args: a tag of the node, the names as len and 4 bytes per arg,
+//! then the counts and the nested nodes. The hash is FNV-1a 64 of these args,
+//! so `Schema::SCHEMA_HASH` is computed at compile time and matches `schema_args_hash`.
+
+#[cfg(feature = "alloc")]
+use crate::SCHEMA_MISMATCH_ERROR;
+use crate::{usize_arg, ArgsWriter, ProtocolError};
+#[cfg(feature = "alloc")]
+use crate::{ARGS_NEXT_ERROR, TRAILING_ARGS_ERROR};
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+#[cfg(feature = "alloc")]
+use core::fmt;
+#[cfg(all(not(feature = "std"), feature = "hashmap"))]
+use hashbrown::HashMap;
+#[cfg(feature = "std")]
+use std::collections::{BTreeMap, HashMap};
+
+/**
+Node of the type tree. Types with the same layout on the wire may have different names,
+so a `u8` on one side and a `u32` on the other is a mismatch too.
+*/
+#[derive(Debug, PartialEq, Eq)]
+pub enum TypeSchema {
+    /// Primitive or opaque type: integers, strings, bytes, time
+    Primitive(&'static str),
+    /// Named type sent as the value of another type, like the time types of chrono
+    Alias(&'static str, &'static TypeSchema),
+    Option(&'static TypeSchema),
+    /// Vec and heapless::Vec: len and the items
+    Seq(&'static TypeSchema),
+    /// [T; N]: the items without a len
+    Array(&'static TypeSchema, usize),
+    Map(&'static TypeSchema, &'static TypeSchema),
+    Tuple(&'static [&'static TypeSchema]),
+    /// `Serde<T, C>`: bytes of the value encoded by the codec, with the name of the codec
+    Payload(&'static str, &'static TypeSchema),
+    Struct {
+        name: &'static str,
+        packed: bool,
+        framed: bool,
+        fields: &'static [FieldSchema],
+    },
+    Enum {
+        name: &'static str,
+        framed: bool,
+        variants: &'static [VariantSchema],
+    },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct FieldSchema {
+    pub name: &'static str,
+    /// Version of `#[coming(since = N)]`, 0 if the field is always present
+    pub since: u32,
+    /// Bits of the field in a packed struct, 0 if it takes whole args
+    pub bits: u32,
+    pub schema: &'static TypeSchema,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct VariantSchema {
+    pub name: &'static str,
+    /// `#[coming(other)]` variant, read for the unknown tags
+    pub other: bool,
+    pub schema: Option<&'static TypeSchema>,
+}
+
+/**
+Version of the bytes format and the args layout.
+*/
+pub const FORMAT_VERSION: u16 = 1;
+
+/**
+Version of the bytes format for args of `#[coming(packed)]` structs,
+the unpacked layout keeps FORMAT_VERSION.
+*/
+pub const PACKED_FORMAT_VERSION: u16 = 2;
+
+/**
+Type with a schema, implemented for the built-in types and by `#[derive(Schema)]`.
+Recursive types aren't supported: their schema would be infinite.
+*/
+pub trait Schema {
+    const SCHEMA: &'static TypeSchema;
+
+    const SCHEMA_HASH: u64 = schema_hash(Self::SCHEMA);
+
+    /// Version of the args layout, PACKED_FORMAT_VERSION with a packed struct inside
+    const FORMAT_VERSION: u16 = format_version(Self::SCHEMA);
+}
+
+/**
+Nesting limit of the schemas read from the args of the other side.
+*/
+pub const SCHEMA_MAX_DEPTH: usize = 64;
+
+const TAG_PRIMITIVE: u32 = 0;
+const TAG_ALIAS: u32 = 1;
+const TAG_OPTION: u32 = 2;
+const TAG_SEQ: u32 = 3;
+const TAG_ARRAY: u32 = 4;
+const TAG_MAP: u32 = 5;
+const TAG_TUPLE: u32 = 6;
+const TAG_STRUCT: u32 = 7;
+const TAG_ENUM: u32 = 8;
+const TAG_PAYLOAD: u32 = 9;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+const fn hash_word(mut hash: u64, word: u32) -> u64 {
+    let bytes = word.to_le_bytes();
+    let mut i = 0;
+    while i < 4 {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+        i += 1;
+    }
+    hash
+}
+
+/**
+Arg with the bytes of a name from `start`, zero padded.
+*/
+const fn name_word(bytes: &[u8], start: usize) -> u32 {
+    let mut word = [0_u8; 4];
+    let mut i = 0;
+    while i < 4 && start + i < bytes.len() {
+        word[i] = bytes[start + i];
+        i += 1;
+    }
+    u32::from_le_bytes(word)
+}
+
+const fn hash_name(hash: u64, name: &str) -> u64 {
+    let bytes = name.as_bytes();
+    let mut hash = hash_word(hash, bytes.len() as u32);
+    let mut start = 0;
+    while start < bytes.len() {
+        hash = hash_word(hash, name_word(bytes, start));
+        start += 4;
+    }
+    hash
+}
+
+const fn hash_node(hash: u64, schema: &TypeSchema) -> u64 {
+    match schema {
+        TypeSchema::Primitive(name) => hash_name(hash_word(hash, TAG_PRIMITIVE), name),
+        TypeSchema::Alias(name, inner) => {
+            hash_node(hash_name(hash_word(hash, TAG_ALIAS), name), inner)
+        }
+        TypeSchema::Option(inner) => hash_node(hash_word(hash, TAG_OPTION), inner),
+        TypeSchema::Seq(inner) => hash_node(hash_word(hash, TAG_SEQ), inner),
+        TypeSchema::Array(inner, len) => {
+            hash_node(hash_word(hash_word(hash, TAG_ARRAY), *len as u32), inner)
+        }
+        TypeSchema::Map(key, value) => hash_node(hash_node(hash_word(hash, TAG_MAP), key), value),
+        TypeSchema::Tuple(items) => {
+            let mut hash = hash_word(hash_word(hash, TAG_TUPLE), items.len() as u32);
+            let mut i = 0;
+            while i < items.len() {
+                hash = hash_node(hash, items[i]);
+                i += 1;
+            }
+            hash
+        }
+        TypeSchema::Payload(codec, inner) => {
+            hash_node(hash_name(hash_word(hash, TAG_PAYLOAD), codec), inner)
+        }
+        TypeSchema::Struct {
+            name,
+            packed,
+            framed,
+            fields,
+        } => {
+            let mut hash = hash_name(hash_word(hash, TAG_STRUCT), name);
+            hash = hash_word(hash_word(hash, *packed as u32), *framed as u32);
+            hash = hash_word(hash, fields.len() as u32);
+            let mut i = 0;
+            while i < fields.len() {
+                hash = hash_word(hash_name(hash, fields[i].name), fields[i].since);
+                hash = hash_node(hash_word(hash, fields[i].bits), fields[i].schema);
+                i += 1;
+            }
+            hash
+        }
+        TypeSchema::Enum {
+            name,
+            framed,
+            variants,
+        } => {
+            let mut hash = hash_name(hash_word(hash, TAG_ENUM), name);
+            hash = hash_word(hash_word(hash, *framed as u32), variants.len() as u32);
+            let mut i = 0;
+            while i < variants.len() {
+                hash = hash_word(hash_name(hash, variants[i].name), variants[i].other as u32);
+                hash = match variants[i].schema {
+                    Some(schema) => hash_node(hash_word(hash, 1), schema),
+                    None => hash_word(hash, 0),
+                };
+                i += 1;
+            }
+            hash
+        }
+    }
+}
+
+const fn has_packed(schema: &TypeSchema) -> bool {
+    match schema {
+        // the payload isn't in the args layout
+        TypeSchema::Primitive(_) | TypeSchema::Payload(_, _) => false,
+        TypeSchema::Alias(_, inner)
+        | TypeSchema::Option(inner)
+        | TypeSchema::Seq(inner)
+        | TypeSchema::Array(inner, _) => has_packed(inner),
+        TypeSchema::Map(key, value) => has_packed(key) || has_packed(value),
+        TypeSchema::Tuple(items) => {
+            let mut i = 0;
+            while i < items.len() {
+                if has_packed(items[i]) {
+                    return true;
+                }
+                i += 1;
+            }
+            false
+        }
+        TypeSchema::Struct { packed, fields, .. } => {
+            let mut i = 0;
+            while !*packed && i < fields.len() {
+                if has_packed(fields[i].schema) {
+                    return true;
+                }
+                i += 1;
+            }
+            *packed
+        }
+        TypeSchema::Enum { variants, .. } => {
+            let mut i = 0;
+            while i < variants.len() {
+                if let Some(schema) = variants[i].schema {
+                    if has_packed(schema) {
+                        return true;
+                    }
+                }
+                i += 1;
+            }
+            false
+        }
+    }
+}
+
+/**
+Version of the args layout of the type, for the header of `to_bytes_of`:
+PACKED_FORMAT_VERSION if a `#[coming(packed)]` struct is anywhere in the tree.
+*/
+pub const fn format_version(schema: &TypeSchema) -> u16 {
+    if has_packed(schema) {
+        PACKED_FORMAT_VERSION
+    } else {
+        FORMAT_VERSION
+    }
+}
+
+/**
+FNV-1a 64 hash of the schema args, computed at compile time.
+*/
+pub const fn schema_hash(schema: &TypeSchema) -> u64 {
+    hash_node(FNV_OFFSET, schema)
+}
+
+/**
+FNV-1a 64 hash of the schema args received from the other side.
+*/
+pub fn schema_args_hash(args: &[u32]) -> u64 {
+    args.iter()
+        .fold(FNV_OFFSET, |hash, word| hash_word(hash, *word))
+}
+
+pub(crate) fn name_args<W: ArgsWriter>(name: &str, args: &mut W) -> Result<(), ProtocolError> {
+    let bytes = name.as_bytes();
+    args.push(usize_arg(bytes.len())?)?;
+    for start in (0..bytes.len()).step_by(4) {
+        args.push(name_word(bytes, start))?;
+    }
+    Ok(())
+}
+
+/**
+Write the schema as args, to send it to the other side.
+*/
+pub fn schema_args<W: ArgsWriter>(schema: &TypeSchema, args: &mut W) -> Result<(), ProtocolError> {
+    match schema {
+        TypeSchema::Primitive(name) => {
+            args.push(TAG_PRIMITIVE)?;
+            name_args(name, args)
+        }
+        TypeSchema::Alias(name, inner) => {
+            args.push(TAG_ALIAS)?;
+            name_args(name, args)?;
+            schema_args(inner, args)
+        }
+        TypeSchema::Option(inner) => {
+            args.push(TAG_OPTION)?;
+            schema_args(inner, args)
+        }
+        TypeSchema::Payload(codec, inner) => {
+            args.push(TAG_PAYLOAD)?;
+            name_args(codec, args)?;
+            schema_args(inner, args)
+        }
+        TypeSchema::Seq(inner) => {
+            args.push(TAG_SEQ)?;
+            schema_args(inner, args)
+        }
+        TypeSchema::Array(inner, len) => {
+            args.push(TAG_ARRAY)?;
+            args.push(usize_arg(*len)?)?;
+            schema_args(inner, args)
+        }
+        TypeSchema::Map(key, value) => {
+            args.push(TAG_MAP)?;
+            schema_args(key, args)?;
+            schema_args(value, args)
+        }
+        TypeSchema::Tuple(items) => {
+            args.push(TAG_TUPLE)?;
+            args.push(usize_arg(items.len())?)?;
+            for item in items.iter() {
+                schema_args(item, args)?;
+            }
+            Ok(())
+        }
+        TypeSchema::Struct {
+            name,
+            packed,
+            framed,
+            fields,
+        } => {
+            args.push(TAG_STRUCT)?;
+            name_args(name, args)?;
+            args.push(*packed as u32)?;
+            args.push(*framed as u32)?;
+            args.push(usize_arg(fields.len())?)?;
+            for field in fields.iter() {
+                name_args(field.name, args)?;
+                args.push(field.since)?;
+                args.push(field.bits)?;
+                schema_args(field.schema, args)?;
+            }
+            Ok(())
+        }
+        TypeSchema::Enum {
+            name,
+            framed,
+            variants,
+        } => {
+            args.push(TAG_ENUM)?;
+            name_args(name, args)?;
+            args.push(*framed as u32)?;
+            args.push(usize_arg(variants.len())?)?;
+            for variant in variants.iter() {
+                name_args(variant.name, args)?;
+                args.push(variant.other as u32)?;
+                match variant.schema {
+                    Some(schema) => {
+                        args.push(1)?;
+                        schema_args(schema, args)?;
+                    }
+                    None => args.push(0)?,
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/**
+Owned type tree, decoded from the schema args of the other side.
+*/
+#[cfg(feature = "alloc")]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SchemaTree {
+    Primitive(String),
+    Alias(String, Box<SchemaTree>),
+    Option(Box<SchemaTree>),
+    Seq(Box<SchemaTree>),
+    Array(Box<SchemaTree>, usize),
+    Map(Box<SchemaTree>, Box<SchemaTree>),
+    Tuple(Vec<SchemaTree>),
+    /// The codec and the schema of the value in the payload
+    Payload(String, Box<SchemaTree>),
+    Struct {
+        name: String,
+        packed: bool,
+        framed: bool,
+        fields: Vec<FieldTree>,
+    },
+    Enum {
+        name: String,
+        framed: bool,
+        /// name, other and schema of the variants
+        variants: Vec<(String, bool, Option<SchemaTree>)>,
+    },
+}
+
+/**
+Field of a struct in the SchemaTree, the owned FieldSchema.
+*/
+#[cfg(feature = "alloc")]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FieldTree {
+    pub name: String,
+    pub since: u32,
+    pub bits: u32,
+    pub schema: SchemaTree,
+}
+
+#[cfg(feature = "alloc")]
+impl From<&TypeSchema> for SchemaTree {
+    fn from(schema: &TypeSchema) -> Self {
+        match schema {
+            TypeSchema::Primitive(name) => SchemaTree::Primitive(name.to_string()),
+            TypeSchema::Alias(name, inner) => {
+                SchemaTree::Alias(name.to_string(), Box::new(SchemaTree::from(*inner)))
+            }
+            TypeSchema::Option(inner) => SchemaTree::Option(Box::new(SchemaTree::from(*inner))),
+            TypeSchema::Payload(codec, inner) => {
+                SchemaTree::Payload(codec.to_string(), Box::new(SchemaTree::from(*inner)))
+            }
+            TypeSchema::Seq(inner) => SchemaTree::Seq(Box::new(SchemaTree::from(*inner))),
+            TypeSchema::Array(inner, len) => {
+                SchemaTree::Array(Box::new(SchemaTree::from(*inner)), *len)
+            }
+            TypeSchema::Map(key, value) => SchemaTree::Map(
+                Box::new(SchemaTree::from(*key)),
+                Box::new(SchemaTree::from(*value)),
+            ),
+            TypeSchema::Tuple(items) => {
+                SchemaTree::Tuple(items.iter().map(|item| SchemaTree::from(*item)).collect())
+            }
+            TypeSchema::Struct {
+                name,
+                packed,
+                framed,
+                fields,
+            } => SchemaTree::Struct {
+                name: name.to_string(),
+                packed: *packed,
+                framed: *framed,
+                fields: fields
+                    .iter()
+                    .map(|field| FieldTree {
+                        name: field.name.to_string(),
+                        since: field.since,
+                        bits: field.bits,
+                        schema: SchemaTree::from(field.schema),
+                    })
+                    .collect(),
+            },
+            TypeSchema::Enum {
+                name,
+                framed,
+                variants,
+            } => SchemaTree::Enum {
+                name: name.to_string(),
+                framed: *framed,
+                variants: variants
+                    .iter()
+                    .map(|variant| {
+                        (
+                            variant.name.to_string(),
+                            variant.other,
+                            variant.schema.map(SchemaTree::from),
+                        )
+                    })
+                    .collect(),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl SchemaTree {
+    /**
+    Decode the schema args written by `schema_args`, all args must be read.
+    */
+    pub fn from_args(args: &[u32]) -> Result<Self, ProtocolError> {
+        let mut iter = args.iter();
+        let tree = SchemaTree::read(&mut iter)?;
+        if iter.len() != 0 {
+            return Err(ProtocolError::from(TRAILING_ARGS_ERROR));
+        }
+        Ok(tree)
+    }
+
+    pub(crate) fn read(args: &mut core::slice::Iter<u32>) -> Result<Self, ProtocolError> {
+        SchemaTree::read_node(args, 0)
+    }
+
+    /**
+    Node at the depth, the nesting of the other side is limited by SCHEMA_MAX_DEPTH
+    and the counts of the items by the args left, so broken args can't exhaust the stack or the memory.
+    */
+    fn read_node(args: &mut core::slice::Iter<u32>, depth: usize) -> Result<Self, ProtocolError> {
+        if depth >= SCHEMA_MAX_DEPTH {
+            return Err(ProtocolError::from(SCHEMA_MISMATCH_ERROR));
+        }
+        let inner = |args: &mut core::slice::Iter<u32>| SchemaTree::read_node(args, depth + 1);
+        let tree = match next(args)? {
+            TAG_PRIMITIVE => SchemaTree::Primitive(read_name(args)?),
+            TAG_ALIAS => SchemaTree::Alias(read_name(args)?, Box::new(inner(args)?)),
+            TAG_OPTION => SchemaTree::Option(Box::new(inner(args)?)),
+            TAG_PAYLOAD => SchemaTree::Payload(read_name(args)?, Box::new(inner(args)?)),
+            TAG_SEQ => SchemaTree::Seq(Box::new(inner(args)?)),
+            TAG_ARRAY => {
+                let len = next(args)? as usize;
+                SchemaTree::Array(Box::new(inner(args)?), len)
+            }
+            TAG_MAP => SchemaTree::Map(Box::new(inner(args)?), Box::new(inner(args)?)),
+            TAG_TUPLE => {
+                let len = count(args)?;
+                SchemaTree::Tuple((0..len).map(|_| inner(args)).collect::<Result<_, _>>()?)
+            }
+            TAG_STRUCT => {
+                let name = read_name(args)?;
+                let packed = next(args)? != 0;
+                let framed = next(args)? != 0;
+                let len = count(args)?;
+                let fields = (0..len)
+                    .map(|_| {
+                        Ok(FieldTree {
+                            name: read_name(args)?,
+                            since: next(args)?,
+                            bits: next(args)?,
+                            schema: inner(args)?,
+                        })
+                    })
+                    .collect::<Result<_, ProtocolError>>()?;
+                SchemaTree::Struct {
+                    name,
+                    packed,
+                    framed,
+                    fields,
+                }
+            }
+            TAG_ENUM => {
+                let name = read_name(args)?;
+                let framed = next(args)? != 0;
+                let len = count(args)?;
+                let variants = (0..len)
+                    .map(|_| {
+                        let name = read_name(args)?;
+                        let other = next(args)? != 0;
+                        let schema = match next(args)? {
+                            0 => None,
+                            _ => Some(inner(args)?),
+                        };
+                        Ok((name, other, schema))
+                    })
+                    .collect::<Result<_, ProtocolError>>()?;
+                SchemaTree::Enum {
+                    name,
+                    framed,
+                    variants,
+                }
+            }
+            _ => return Err(ProtocolError::from(SCHEMA_MISMATCH_ERROR)),
+        };
+        Ok(tree)
+    }
+
+    /**
+    Write the schema as args, the same as `schema_args` of the TypeSchema.
+    */
+    pub fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        match self {
+            SchemaTree::Primitive(name) => {
+                args.push(TAG_PRIMITIVE)?;
+                name_args(name, args)
+            }
+            SchemaTree::Alias(name, inner) => {
+                args.push(TAG_ALIAS)?;
+                name_args(name, args)?;
+                inner.args(args)
+            }
+            SchemaTree::Option(inner) => {
+                args.push(TAG_OPTION)?;
+                inner.args(args)
+            }
+            SchemaTree::Payload(codec, inner) => {
+                args.push(TAG_PAYLOAD)?;
+                name_args(codec, args)?;
+                inner.args(args)
+            }
+            SchemaTree::Seq(inner) => {
+                args.push(TAG_SEQ)?;
+                inner.args(args)
+            }
+            SchemaTree::Array(inner, len) => {
+                args.push(TAG_ARRAY)?;
+                args.push(usize_arg(*len)?)?;
+                inner.args(args)
+            }
+            SchemaTree::Map(key, value) => {
+                args.push(TAG_MAP)?;
+                key.args(args)?;
+                value.args(args)
+            }
+            SchemaTree::Tuple(items) => {
+                args.push(TAG_TUPLE)?;
+                args.push(usize_arg(items.len())?)?;
+                for item in items {
+                    item.args(args)?;
+                }
+                Ok(())
+            }
+            SchemaTree::Struct {
+                name,
+                packed,
+                framed,
+                fields,
+            } => {
+                args.push(TAG_STRUCT)?;
+                name_args(name, args)?;
+                args.push(*packed as u32)?;
+                args.push(*framed as u32)?;
+                args.push(usize_arg(fields.len())?)?;
+                for field in fields {
+                    name_args(&field.name, args)?;
+                    args.push(field.since)?;
+                    args.push(field.bits)?;
+                    field.schema.args(args)?;
+                }
+                Ok(())
+            }
+            SchemaTree::Enum {
+                name,
+                framed,
+                variants,
+            } => {
+                args.push(TAG_ENUM)?;
+                name_args(name, args)?;
+                args.push(*framed as u32)?;
+                args.push(usize_arg(variants.len())?)?;
+                for (name, other, schema) in variants {
+                    name_args(name, args)?;
+                    args.push(*other as u32)?;
+                    match schema {
+                        Some(schema) => {
+                            args.push(1)?;
+                            schema.args(args)?;
+                        }
+                        None => args.push(0)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /**
+    The same hash as `Schema::SCHEMA_HASH` of the type.
+    */
+    pub fn hash(&self) -> u64 {
+        let mut args: Vec<u32> = Vec::new();
+        // writing into a Vec never fails
+        let _ = self.args(&mut args);
+        schema_args_hash(&args)
+    }
+
+    /**
+    Short name of the node for the diff.
+    */
+    fn kind(&self) -> String {
+        match self {
+            SchemaTree::Primitive(name) | SchemaTree::Alias(name, _) => name.clone(),
+            SchemaTree::Option(_) => "Option".to_string(),
+            SchemaTree::Payload(codec, _) => format!("Serde<{}>", codec),
+            SchemaTree::Seq(_) => "Vec".to_string(),
+            SchemaTree::Array(_, len) => format!("array of {}", len),
+            SchemaTree::Map(_, _) => "map".to_string(),
+            SchemaTree::Tuple(items) => format!("tuple of {}", items.len()),
+            SchemaTree::Struct { name, .. } => format!("struct {}", name),
+            SchemaTree::Enum { name, .. } => format!("enum {}", name),
+        }
+    }
+}
+
+/**
+Bits of the fields packed without `#[coming(pack)]` in a packed struct.
+*/
+#[cfg(feature = "alloc")]
+pub(crate) fn default_bits(schema: &SchemaTree) -> u32 {
+    match schema {
+        SchemaTree::Primitive(name) => match name.as_str() {
+            "bool" => 1,
+            "u8" | "i8" => 8,
+            "u16" => 16,
+            _ => 0,
+        },
+        _ => 0,
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for SchemaTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaTree::Primitive(name) => write!(f, "{}", name),
+            SchemaTree::Alias(name, inner) => write!(f, "{} as {}", name, inner),
+            SchemaTree::Option(inner) => write!(f, "Option<{}>", inner),
+            SchemaTree::Payload(codec, inner) => write!(f, "Serde<{}, {}>", codec, inner),
+            SchemaTree::Seq(inner) => write!(f, "Vec<{}>", inner),
+            SchemaTree::Array(inner, len) => write!(f, "[{}; {}]", inner, len),
+            SchemaTree::Map(key, value) => write!(f, "Map<{}, {}>", key, value),
+            SchemaTree::Tuple(items) => {
+                write!(f, "(")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, ")")
+            }
+            SchemaTree::Struct {
+                name,
+                packed,
+                framed,
+                fields,
+            } => {
+                if *packed {
+                    write!(f, "#[coming(packed)] ")?;
+                }
+                if *framed {
+                    write!(f, "#[coming(framed)] ")?;
+                }
+                write!(f, "{} {{", name)?;
+                for (index, field) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    if field.since > 0 {
+                        write!(f, " #[coming(since = {})]", field.since)?;
+                    }
+                    if field.bits > 0 && field.bits != default_bits(&field.schema) {
+                        write!(f, " #[coming(pack)]")?;
+                    }
+                    write!(f, " {}: {}", field.name, field.schema)?;
+                }
+                write!(f, " }}")
+            }
+            SchemaTree::Enum {
+                name,
+                framed,
+                variants,
+            } => {
+                if *framed {
+                    write!(f, "#[coming(framed)] ")?;
+                }
+                write!(f, "{} {{", name)?;
+                for (index, (name, other, schema)) in variants.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    if *other {
+                        write!(f, " #[coming(other)]")?;
+                    }
+                    match schema {
+                        Some(schema) => write!(f, " {}({})", name, schema)?,
+                        None => write!(f, " {}", name)?,
+                    }
+                }
+                write!(f, " }}")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub(crate) fn next(args: &mut core::slice::Iter<u32>) -> Result<u32, ProtocolError> {
+    args.next()
+        .copied()
+        .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))
+}
+
+/**
+Args of a value wider than an arg, the low word first.
+*/
+#[cfg(feature = "alloc")]
+pub(crate) fn words<const N: usize>(
+    args: &mut core::slice::Iter<u32>,
+) -> Result<[u32; N], ProtocolError> {
+    let mut words = [0u32; N];
+    for word in &mut words {
+        *word = next(args)?;
+    }
+    Ok(words)
+}
+
+/**
+Count of the items of a node, each item takes at least an arg.
+*/
+#[cfg(feature = "alloc")]
+pub(crate) fn count(args: &mut core::slice::Iter<u32>) -> Result<u32, ProtocolError> {
+    let len = next(args)?;
+    if len as usize > args.len() {
+        return Err(ProtocolError::from(ARGS_NEXT_ERROR));
+    }
+    Ok(len)
+}
+
+/**
+Len of a list or a map of a value, its items take at least `min` args each:
+a len beyond what the args left can hold is refused, items without args may have any len.
+*/
+#[cfg(feature = "alloc")]
+pub(crate) fn items_len(
+    args: &mut core::slice::Iter<u32>,
+    min: usize,
+) -> Result<u32, ProtocolError> {
+    let len = next(args)?;
+    if (len as usize).saturating_mul(min) > args.len() {
+        return Err(ProtocolError::from(ARGS_NEXT_ERROR));
+    }
+    Ok(len)
+}
+
+/**
+Least count of args of a value of the schema, 0 for the types unknown to this version.
+*/
+#[cfg(feature = "alloc")]
+pub(crate) fn min_words(schema: &SchemaTree) -> usize {
+    match schema {
+        SchemaTree::Primitive(name) => match name.as_str() {
+            "bool" | "u8" | "u16" | "u32" | "usize" | "i8" | "i32" | "isize" | "f32" => 1,
+            "String" | "Bytes" => 1,
+            "u64" | "i64" | "f64" | "&str" | "&Bytes" => 2,
+            "Duration" | "SystemTime" => 3,
+            "u128" | "i128" => 4,
+            _ => 0,
+        },
+        SchemaTree::Alias(_, inner) => min_words(inner),
+        SchemaTree::Option(_) | SchemaTree::Seq(_) | SchemaTree::Map(_, _) => 1,
+        SchemaTree::Payload(_, _) | SchemaTree::Enum { .. } => 1,
+        SchemaTree::Array(inner, len) => min_words(inner).saturating_mul(*len),
+        SchemaTree::Tuple(items) => items.iter().map(min_words).sum(),
+        // the fields of a newer version may be missing from the frame
+        SchemaTree::Struct { framed: true, .. } => 1,
+        SchemaTree::Struct { packed, fields, .. } => {
+            let bits = fields.iter().any(|field| *packed && field.bits > 0) as usize;
+            fields
+                .iter()
+                .filter(|field| !*packed || field.bits == 0)
+                .map(|field| min_words(&field.schema))
+                .sum::<usize>()
+                + bits
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub(crate) fn read_name(args: &mut core::slice::Iter<u32>) -> Result<String, ProtocolError> {
+    let len = next(args)? as usize;
+    if len.div_ceil(4) > args.len() {
+        return Err(ProtocolError::from(ARGS_NEXT_ERROR));
+    }
+    let mut bytes: Vec<u8> = Vec::with_capacity(len);
+    while bytes.len() < len {
+        let word = next(args)?.to_le_bytes();
+        let count = (len - bytes.len()).min(4);
+        bytes.extend_from_slice(&word[..count]);
+    }
+    Ok(String::from_utf8(bytes)?)
+}
+
+/**
+Readable differences between the schemas, one line per difference, empty if they match.
+*/
+#[cfg(feature = "alloc")]
+pub fn schema_diff(host: &SchemaTree, guest: &SchemaTree) -> Vec<String> {
+    let mut diff: Vec<String> = Vec::new();
+    diff_node(String::new(), host, guest, &mut diff);
+    diff
+}
+
+#[cfg(feature = "alloc")]
+fn diff_node(path: String, host: &SchemaTree, guest: &SchemaTree, diff: &mut Vec<String>) {
+    let at = if path.is_empty() { "<root>" } else { &path };
+    match (host, guest) {
+        (SchemaTree::Primitive(a), SchemaTree::Primitive(b)) if a == b => {}
+        (SchemaTree::Alias(a, host), SchemaTree::Alias(b, guest)) if a == b => {
+            diff_node(path, host, guest, diff)
+        }
+        (SchemaTree::Payload(a, host), SchemaTree::Payload(b, guest)) if a == b => {
+            diff_node(path, host, guest, diff)
+        }
+        (SchemaTree::Option(host), SchemaTree::Option(guest)) => {
+            diff_node(format!("{}?", path), host, guest, diff)
+        }
+        (SchemaTree::Seq(host), SchemaTree::Seq(guest)) => {
+            diff_node(format!("{}[]", path), host, guest, diff)
+        }
+        (SchemaTree::Array(host, a), SchemaTree::Array(guest, b)) if a == b => {
+            diff_node(format!("{}[]", path), host, guest, diff)
+        }
+        (SchemaTree::Map(host_key, host_value), SchemaTree::Map(guest_key, guest_value)) => {
+            diff_node(format!("{}.key", path), host_key, guest_key, diff);
+            diff_node(format!("{}.value", path), host_value, guest_value, diff);
+        }
+        (SchemaTree::Tuple(host), SchemaTree::Tuple(guest)) if host.len() == guest.len() => {
+            for (index, (host, guest)) in host.iter().zip(guest).enumerate() {
+                diff_node(format!("{}.{}", path, index), host, guest, diff);
+            }
+        }
+        (
+            SchemaTree::Struct {
+                name: host_name,
+                packed: host_packed,
+                framed: host_framed,
+                fields: host_fields,
+            },
+            SchemaTree::Struct {
+                name: guest_name,
+                packed: guest_packed,
+                framed: guest_framed,
+                fields: guest_fields,
+            },
+        ) if host_name == guest_name => {
+            if host_packed != guest_packed {
+                diff.push(format!(
+                    "{}: struct {} is packed only on the {}",
+                    at,
+                    host_name,
+                    if *host_packed { "host" } else { "guest" }
+                ));
+            }
+            if host_framed != guest_framed {
+                diff.push(format!(
+                    "{}: struct {} is framed only on the {}",
+                    at,
+                    host_name,
+                    if *host_framed { "host" } else { "guest" }
+                ));
+            }
+            let path = if path.is_empty() {
+                host_name.clone()
+            } else {
+                path
+            };
+            // trailing since fields of framed structs are skipped or defaulted by the other side
+            let framed = *host_framed && *guest_framed;
+            diff_fields(&path, framed, host_fields, guest_fields, diff);
+        }
+        (
+            SchemaTree::Enum {
+                name: host_name,
+                framed: host_framed,
+                variants: host_variants,
+            },
+            SchemaTree::Enum {
+                name: guest_name,
+                framed: guest_framed,
+                variants: guest_variants,
+            },
+        ) if host_name == guest_name => {
+            if host_framed != guest_framed {
+                diff.push(format!(
+                    "{}: enum {} is framed only on the {}",
+                    at,
+                    host_name,
+                    if *host_framed { "host" } else { "guest" }
+                ));
+            }
+            let path = if path.is_empty() {
+                host_name.clone()
+            } else {
+                path
+            };
+            // unknown variants are read as the other variant
+            let host_other = host_variants.iter().any(|(_, other, _)| *other);
+            let guest_other = guest_variants.iter().any(|(_, other, _)| *other);
+            for index in 0..host_variants.len().max(guest_variants.len()) {
+                match (host_variants.get(index), guest_variants.get(index)) {
+                    (Some((host_name, _, host)), Some((guest_name, _, guest))) => {
+                        if host_name != guest_name {
+                            diff.push(format!(
+                                "{}: variant {} is {} on the host and {} on the guest",
+                                path, index, host_name, guest_name
+                            ));
+                            continue;
+                        }
+                        let path = format!("{}::{}", path, host_name);
+                        match (host, guest) {
+                            (Some(host), Some(guest)) => diff_node(path, host, guest, diff),
+                            (None, None) => {}
+                            (Some(host), None) => diff.push(format!(
+                                "{}: host {}, guest without a value",
+                                path,
+                                host.kind()
+                            )),
+                            (None, Some(guest)) => diff.push(format!(
+                                "{}: host without a value, guest {}",
+                                path,
+                                guest.kind()
+                            )),
+                        }
+                    }
+                    (Some((name, _, _)), None) if !guest_other => {
+                        diff.push(format!("{}::{}: only on the host", path, name))
+                    }
+                    (None, Some((name, _, _))) if !host_other => {
+                        diff.push(format!("{}::{}: only on the guest", path, name))
+                    }
+                    (Some(_), None) | (None, Some(_)) => {}
+                    (None, None) => {}
+                }
+            }
+        }
+        (host, guest) => diff.push(format!(
+            "{}: host {}, guest {}",
+            at,
+            host.kind(),
+            guest.kind()
+        )),
+    }
+}
+
+#[cfg(feature = "alloc")]
+fn diff_fields(
+    path: &str,
+    framed: bool,
+    host: &[FieldTree],
+    guest: &[FieldTree],
+    diff: &mut Vec<String>,
+) {
+    for index in 0..host.len().max(guest.len()) {
+        match (host.get(index), guest.get(index)) {
+            (Some(host), Some(guest)) => {
+                if host.name != guest.name {
+                    diff.push(format!(
+                        "{}: field {} is {} on the host and {} on the guest",
+                        path, index, host.name, guest.name
+                    ));
+                    continue;
+                }
+                let path = format!("{}.{}", path, host.name);
+                if host.since != guest.since {
+                    diff.push(format!(
+                        "{}: host since {}, guest since {}",
+                        path, host.since, guest.since
+                    ));
+                }
+                if host.bits != guest.bits {
+                    diff.push(format!(
+                        "{}: host {} bits, guest {} bits",
+                        path, host.bits, guest.bits
+                    ));
+                }
+                diff_node(path, &host.schema, &guest.schema, diff);
+            }
+            (Some(field), None) | (None, Some(field)) if framed && field.since > 0 => {}
+            (Some(field), None) => diff.push(format!(
+                "{}.{}: field {} only on the host",
+                path,
+                field.name,
+                field.schema.kind()
+            )),
+            (None, Some(field)) => diff.push(format!(
+                "{}.{}: field {} only on the guest",
+                path,
+                field.name,
+                field.schema.kind()
+            )),
+            (None, None) => {}
+        }
+    }
+}
+
+/**
+Compare the schema of the host type with the schema args of the guest,
+the error has the diff with std.
+Schemas with other hashes are compatible if they differ only in the trailing since fields
+of framed structs or in the variants read as the other variant.
+*/
+#[cfg(feature = "alloc")]
+pub fn check_schema(host: &TypeSchema, guest_args: &[u32]) -> Result<(), ProtocolError> {
+    if schema_hash(host) == schema_args_hash(guest_args) {
+        return Ok(());
+    }
+    let guest = SchemaTree::from_args(guest_args)?;
+    let diff = schema_diff(&SchemaTree::from(host), &guest);
+    if diff.is_empty() {
+        return Ok(());
+    }
+    Err(schema_mismatch_error(&diff))
+}
+
+#[cfg(feature = "std")]
+fn schema_mismatch_error(diff: &[String]) -> ProtocolError {
+    ProtocolError(format!("schema mismatch: {}", diff.join("; ")))
+}
+
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+fn schema_mismatch_error(_: &[String]) -> ProtocolError {
+    ProtocolError(SCHEMA_MISMATCH_ERROR)
+}
+
+macro_rules! primitive_schema {
+    ($($typ:ty),*) => {
+        $(
+            impl Schema for $typ {
+                const SCHEMA: &'static TypeSchema = &TypeSchema::Primitive(stringify!($typ));
+            }
+        )*
+    };
+}
+
+primitive_schema!(bool, u8, i8, u16, i32, u32, u64, i64, u128, i128, usize, isize, f32, f64);
+
+impl Schema for core::time::Duration {
+    const SCHEMA: &'static TypeSchema = &TypeSchema::Primitive("Duration");
+}
+
+#[cfg(feature = "std")]
+impl Schema for std::time::SystemTime {
+    const SCHEMA: &'static TypeSchema = &TypeSchema::Primitive("SystemTime");
+}
+
+#[cfg(feature = "alloc")]
+impl Schema for String {
+    const SCHEMA: &'static TypeSchema = &TypeSchema::Primitive("String");
+}
+
+#[cfg(feature = "alloc")]
+impl Schema for crate::Bytes {
+    const SCHEMA: &'static TypeSchema = &TypeSchema::Primitive("Bytes");
+}
+
+/**
+Borrowed bytes are the len and the pointer, not the bytes like `String` and `Bytes`.
+*/
+impl Schema for &str {
+    const SCHEMA: &'static TypeSchema = &TypeSchema::Primitive("&str");
+}
+
+impl Schema for &[u8] {
+    const SCHEMA: &'static TypeSchema = &TypeSchema::Primitive("&Bytes");
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> Schema for heapless::String<N> {
+    const SCHEMA: &'static TypeSchema = &TypeSchema::Primitive("String");
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Schema> Schema for Vec<T> {
+    const SCHEMA: &'static TypeSchema = &TypeSchema::Seq(T::SCHEMA);
+}
+
+#[cfg(feature = "heapless")]
+impl<T: Schema, const N: usize> Schema for heapless::Vec<T, N> {
+    const SCHEMA: &'static TypeSchema = &TypeSchema::Seq(T::SCHEMA);
+}
+
+impl<T: Schema> Schema for Option<T> {
+    const SCHEMA: &'static TypeSchema = &TypeSchema::Option(T::SCHEMA);
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Schema> Schema for Box<T> {
+    const SCHEMA: &'static TypeSchema = T::SCHEMA;
+}
+
+impl<T: Schema, const N: usize> Schema for [T; N] {
+    const SCHEMA: &'static TypeSchema = &TypeSchema::Array(T::SCHEMA, N);
+}
+
+#[cfg(any(feature = "std", feature = "hashmap"))]
+impl<K: Schema, V: Schema, S> Schema for HashMap<K, V, S> {
+    const SCHEMA: &'static TypeSchema = &TypeSchema::Map(K::SCHEMA, V::SCHEMA);
+}
+
+#[cfg(feature = "alloc")]
+impl<K: Schema, V: Schema> Schema for BTreeMap<K, V> {
+    const SCHEMA: &'static TypeSchema = &TypeSchema::Map(K::SCHEMA, V::SCHEMA);
+}
+
+macro_rules! tuple_schema {
+    ($($name:ident),*) => {
+        impl<$($name: Schema),*> Schema for ($($name,)*) {
+            const SCHEMA: &'static TypeSchema = &TypeSchema::Tuple(&[$($name::SCHEMA),*]);
+        }
+    };
+}
+
+tuple_schema!();
+tuple_schema!(T1);
+tuple_schema!(T1, T2);
+tuple_schema!(T1, T2, T3);
+tuple_schema!(T1, T2, T3, T4);
+tuple_schema!(T1, T2, T3, T4, T5);
+tuple_schema!(T1, T2, T3, T4, T5, T6);
+tuple_schema!(T1, T2, T3, T4, T5, T6, T7);
+tuple_schema!(T1, T2, T3, T4, T5, T6, T7, T8);
+tuple_schema!(T1, T2, T3, T4, T5, T6, T7, T8, T9);
+tuple_schema!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10);
+tuple_schema!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11);
+tuple_schema!(T1, T2, T3, T4, T5, T6, T7, T8, T9, T10, T11, T12);
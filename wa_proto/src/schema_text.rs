@@ -0,0 +1,284 @@
+//! Parser of the schema text printed by the Display of SchemaTree,
+//! like `#[coming(framed)] Order { id: u64, items: Vec<Item { price: u32 }> }`,
+//! for the tools that get the schema from a user instead of a Rust type.
+
+#[cfg(not(feature = "std"))]
+use crate::SCHEMA_MISMATCH_ERROR;
+use crate::{default_bits, enum_bits, FieldTree, ProtocolError, SchemaTree};
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::str::FromStr;
+
+impl FromStr for SchemaTree {
+    type Err = ProtocolError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { text, pos: 0 };
+        let tree = parser.node()?;
+        parser.skip_space();
+        if parser.pos < text.len() {
+            return Err(parser.error("end of the schema"));
+        }
+        Ok(tree)
+    }
+}
+
+/**
+Attributes `#[coming(...)]` before a type, a field or a variant.
+*/
+#[derive(Default)]
+struct Attrs {
+    packed: bool,
+    framed: bool,
+    pack: bool,
+    other: bool,
+    since: u32,
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    #[cfg(feature = "std")]
+    fn error(&self, expected: &str) -> ProtocolError {
+        ProtocolError(format!(
+            "schema text: expected {} at {}: {:?}",
+            expected,
+            self.pos,
+            &self.text[self.pos..]
+        ))
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn error(&self, _: &str) -> ProtocolError {
+        ProtocolError(SCHEMA_MISMATCH_ERROR)
+    }
+
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+
+    fn skip_space(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /**
+    Skip the token if it is next.
+    */
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_space();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), ProtocolError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(token))
+        }
+    }
+
+    fn number(&mut self) -> Result<usize, ProtocolError> {
+        self.skip_space();
+        let len = self
+            .rest()
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(self.rest().len());
+        let number = self.rest()[..len]
+            .parse()
+            .map_err(|_| self.error("a number"))?;
+        self.pos += len;
+        Ok(number)
+    }
+
+    /**
+    Name of a field or of a variant.
+    */
+    fn ident(&mut self) -> Result<String, ProtocolError> {
+        self.skip_space();
+        let len = self
+            .rest()
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(self.rest().len());
+        if len == 0 {
+            return Err(self.error("a name"));
+        }
+        let ident = self.rest()[..len].to_string();
+        self.pos += len;
+        Ok(ident)
+    }
+
+    /**
+    Name of a type, may have spaces and generics like `DateTime < Utc >`.
+    Ends before ` as ` of an alias or before a delimiter.
+    */
+    fn type_name(&mut self) -> Result<(String, bool), ProtocolError> {
+        self.skip_space();
+        let text = self.text;
+        let start = self.pos;
+        let mut depth = 0;
+        for (index, c) in text[start..].char_indices() {
+            let name = &text[start..start + index];
+            if let Some(name) = name.strip_suffix(" as ") {
+                self.pos = start + index;
+                return Ok((name.trim().to_string(), true));
+            }
+            match c {
+                '<' => depth += 1,
+                '>' if depth > 0 => depth -= 1,
+                '{' | '}' | '(' | ')' | '[' | ']' | ',' | ';' | '>' => break,
+                _ => {}
+            }
+            self.pos = start + index + c.len_utf8();
+        }
+        let name = text[start..self.pos].trim_end();
+        // the spaces before a delimiter aren't a part of the name
+        self.pos = start + name.len();
+        if name.is_empty() {
+            return Err(self.error("a type"));
+        }
+        Ok((name.to_string(), false))
+    }
+
+    fn attrs(&mut self) -> Result<Attrs, ProtocolError> {
+        let mut attrs = Attrs::default();
+        while self.eat("#[coming(") {
+            let name = self.ident()?;
+            match name.as_str() {
+                "packed" => attrs.packed = true,
+                "framed" => attrs.framed = true,
+                "pack" => attrs.pack = true,
+                "other" => attrs.other = true,
+                "since" => {
+                    self.expect("=")?;
+                    attrs.since = self.number()? as u32;
+                }
+                _ => return Err(self.error("packed, framed, pack, other or since")),
+            }
+            self.expect(")]")?;
+        }
+        Ok(attrs)
+    }
+
+    fn node(&mut self) -> Result<SchemaTree, ProtocolError> {
+        let attrs = self.attrs()?;
+        if self.eat("Option<") {
+            let inner = self.node()?;
+            self.expect(">")?;
+            return Ok(SchemaTree::Option(Box::new(inner)));
+        }
+        if self.eat("Serde<") {
+            let codec = self.ident()?;
+            self.expect(",")?;
+            let inner = self.node()?;
+            self.expect(">")?;
+            return Ok(SchemaTree::Payload(codec, Box::new(inner)));
+        }
+        if self.eat("Vec<") {
+            let inner = self.node()?;
+            self.expect(">")?;
+            return Ok(SchemaTree::Seq(Box::new(inner)));
+        }
+        if self.eat("Map<") {
+            let key = self.node()?;
+            self.expect(",")?;
+            let value = self.node()?;
+            self.expect(">")?;
+            return Ok(SchemaTree::Map(Box::new(key), Box::new(value)));
+        }
+        if self.eat("[") {
+            let inner = self.node()?;
+            self.expect(";")?;
+            let len = self.number()?;
+            self.expect("]")?;
+            return Ok(SchemaTree::Array(Box::new(inner), len));
+        }
+        if self.eat("(") {
+            let mut items: Vec<SchemaTree> = Vec::new();
+            while !self.eat(")") {
+                if !items.is_empty() {
+                    self.expect(",")?;
+                }
+                items.push(self.node()?);
+            }
+            return Ok(SchemaTree::Tuple(items));
+        }
+        let (name, is_alias) = self.type_name()?;
+        if is_alias {
+            return Ok(SchemaTree::Alias(name, Box::new(self.node()?)));
+        }
+        if self.eat("{") {
+            return self.body(name, attrs);
+        }
+        Ok(SchemaTree::Primitive(name))
+    }
+
+    /**
+    Fields of a struct or variants of an enum, a struct if the first item has a type.
+    */
+    fn body(&mut self, name: String, attrs: Attrs) -> Result<SchemaTree, ProtocolError> {
+        let mut fields: Vec<FieldTree> = Vec::new();
+        let mut variants: Vec<(String, bool, Option<SchemaTree>)> = Vec::new();
+        while !self.eat("}") {
+            if !fields.is_empty() || !variants.is_empty() {
+                self.expect(",")?;
+            }
+            let item = self.attrs()?;
+            let item_name = self.ident()?;
+            if variants.is_empty() && self.eat(":") {
+                let schema = self.node()?;
+                let bits = match (attrs.packed, item.pack, &schema) {
+                    (false, _, _) => 0,
+                    (true, false, schema) => default_bits(schema),
+                    (true, true, SchemaTree::Enum { variants, .. }) => {
+                        enum_bits(variants.len() as u32)
+                    }
+                    (true, true, _) => return Err(self.error("an enum for #[coming(pack)]")),
+                };
+                fields.push(FieldTree {
+                    name: item_name,
+                    since: item.since,
+                    bits,
+                    schema,
+                });
+            } else if fields.is_empty() {
+                let schema = if self.eat("(") {
+                    let schema = self.node()?;
+                    self.expect(")")?;
+                    Some(schema)
+                } else {
+                    None
+                };
+                variants.push((item_name, item.other, schema));
+            } else {
+                return Err(self.error(":"));
+            }
+        }
+        if variants.is_empty() {
+            Ok(SchemaTree::Struct {
+                name,
+                packed: attrs.packed,
+                framed: attrs.framed,
+                fields,
+            })
+        } else {
+            Ok(SchemaTree::Enum {
+                name,
+                framed: attrs.framed,
+                variants,
+            })
+        }
+    }
+}
@@ -0,0 +1,400 @@
+//! serde Serializer into the args, with the same layout as `Incoming::args` and `Outcoming::args`:
+//! - bool, integers up to 32 bits, f32 and char - one arg
+//! - i64, u64 and f64 - two args, i128 and u128 - four args, low word first
+//! - str and bytes - len and 4 bytes per arg
+//! - Option - 0 or 1 and the value
+//! - seq and map - len and the items, tuples and structs - only the fields
+//! - enum variant - index of the variant and the fields
+
+use crate::{
+    bytes_args, u128_into_args, u64_into_args, usize_arg, ArgsWriter, ProtocolError,
+    SERDE_CUSTOM_ERROR, SERDE_UNSUPPORTED_ERROR,
+};
+use core::fmt::{self, Display, Write};
+use serde::ser::{self, Serialize};
+
+#[cfg(feature = "std")]
+impl ser::Error for ProtocolError {
+    fn custom<T: Display>(msg: T) -> Self {
+        ProtocolError(msg.to_string())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl ser::Error for ProtocolError {
+    fn custom<T: Display>(_: T) -> Self {
+        ProtocolError::from(SERDE_CUSTOM_ERROR)
+    }
+}
+
+/**
+Serialize the value into the args.
+*/
+pub fn to_args<T, W>(value: &T, args: &mut W) -> Result<(), ProtocolError>
+where
+    T: Serialize + ?Sized,
+    W: ArgsWriter,
+{
+    value.serialize(&mut Serializer::new(args))
+}
+
+pub struct Serializer<'a, W: ArgsWriter> {
+    args: &'a mut W,
+}
+
+impl<'a, W: ArgsWriter> Serializer<'a, W> {
+    pub fn new(args: &'a mut W) -> Self {
+        Serializer { args }
+    }
+
+    fn push_words(&mut self, words: &[u32]) -> Result<(), ProtocolError> {
+        for word in words {
+            self.args.push(*word)?;
+        }
+        Ok(())
+    }
+}
+
+/**
+Counts the bytes of a formatted value.
+*/
+struct LenCounter(usize);
+
+impl Write for LenCounter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+/**
+Packs the bytes of a formatted value into the args, 4 bytes per arg.
+*/
+struct BytesPacker<'a, W: ArgsWriter> {
+    args: &'a mut W,
+    word: [u8; 4],
+    filled: usize,
+    error: Option<ProtocolError>,
+}
+
+impl<W: ArgsWriter> BytesPacker<'_, W> {
+    fn finish(mut self) -> Result<(), ProtocolError> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        if self.filled > 0 {
+            self.word[self.filled..].fill(0);
+            self.args.push(u32::from_le_bytes(self.word))?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: ArgsWriter> Write for BytesPacker<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.word[self.filled] = byte;
+            self.filled += 1;
+            if self.filled == 4 {
+                self.filled = 0;
+                if let Err(e) = self.args.push(u32::from_le_bytes(self.word)) {
+                    self.error = Some(e);
+                    return Err(fmt::Error);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W: ArgsWriter> ser::Serializer for &mut Serializer<'_, W> {
+    type Ok = ();
+    type Error = ProtocolError;
+
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<(), ProtocolError> {
+        self.args.push(if v { 1 } else { 0 })
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), ProtocolError> {
+        self.serialize_i32(v as i32)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), ProtocolError> {
+        self.serialize_i32(v as i32)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), ProtocolError> {
+        self.args.push(v as u32)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), ProtocolError> {
+        self.push_words(&u64_into_args(v as u64))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), ProtocolError> {
+        self.serialize_u128(v as u128)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), ProtocolError> {
+        self.args.push(v as u32)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), ProtocolError> {
+        self.args.push(v as u32)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), ProtocolError> {
+        self.args.push(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), ProtocolError> {
+        self.push_words(&u64_into_args(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), ProtocolError> {
+        self.push_words(&u128_into_args(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), ProtocolError> {
+        self.args.push(v.to_bits())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), ProtocolError> {
+        self.push_words(&u64_into_args(v.to_bits()))
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), ProtocolError> {
+        self.args.push(v as u32)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), ProtocolError> {
+        bytes_args(v.as_bytes(), self.args)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), ProtocolError> {
+        bytes_args(v, self.args)
+    }
+
+    fn serialize_none(self) -> Result<(), ProtocolError> {
+        self.args.push(0)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), ProtocolError> {
+        self.args.push(1)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        variant_index: u32,
+        _: &'static str,
+    ) -> Result<(), ProtocolError> {
+        self.args.push(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), ProtocolError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        variant_index: u32,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), ProtocolError> {
+        self.args.push(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self, ProtocolError> {
+        let len = len.ok_or_else(|| ProtocolError::from(SERDE_UNSUPPORTED_ERROR))?;
+        self.args.push(usize_arg(len)?)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self, ProtocolError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self, ProtocolError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        variant_index: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self, ProtocolError> {
+        self.args.push(variant_index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self, ProtocolError> {
+        self.serialize_seq(len)
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, ProtocolError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        variant_index: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self, ProtocolError> {
+        self.args.push(variant_index)?;
+        Ok(self)
+    }
+
+    /// Formats the value twice: for the len and for the bytes, so no allocation is needed.
+    fn collect_str<T: Display + ?Sized>(self, value: &T) -> Result<(), ProtocolError> {
+        let mut counter = LenCounter(0);
+        write!(counter, "{}", value).map_err(|_| ProtocolError::from(SERDE_CUSTOM_ERROR))?;
+        self.args.push(usize_arg(counter.0)?)?;
+        let mut packer = BytesPacker {
+            args: &mut *self.args,
+            word: [0; 4],
+            filled: 0,
+            error: None,
+        };
+        if write!(packer, "{}", value).is_err() {
+            return Err(packer
+                .error
+                .unwrap_or_else(|| ProtocolError::from(SERDE_CUSTOM_ERROR)));
+        }
+        packer.finish()
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl<W: ArgsWriter> ser::SerializeSeq for &mut Serializer<'_, W> {
+    type Ok = ();
+    type Error = ProtocolError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ProtocolError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+}
+
+impl<W: ArgsWriter> ser::SerializeTuple for &mut Serializer<'_, W> {
+    type Ok = ();
+    type Error = ProtocolError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ProtocolError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+}
+
+impl<W: ArgsWriter> ser::SerializeTupleStruct for &mut Serializer<'_, W> {
+    type Ok = ();
+    type Error = ProtocolError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ProtocolError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+}
+
+impl<W: ArgsWriter> ser::SerializeTupleVariant for &mut Serializer<'_, W> {
+    type Ok = ();
+    type Error = ProtocolError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ProtocolError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+}
+
+impl<W: ArgsWriter> ser::SerializeMap for &mut Serializer<'_, W> {
+    type Ok = ();
+    type Error = ProtocolError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), ProtocolError> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), ProtocolError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+}
+
+impl<W: ArgsWriter> ser::SerializeStruct for &mut Serializer<'_, W> {
+    type Ok = ();
+    type Error = ProtocolError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), ProtocolError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+}
+
+impl<W: ArgsWriter> ser::SerializeStructVariant for &mut Serializer<'_, W> {
+    type Ok = ();
+    type Error = ProtocolError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), ProtocolError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), ProtocolError> {
+        Ok(())
+    }
+}
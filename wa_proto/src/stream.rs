@@ -0,0 +1,485 @@
+//! Streams of chunks for payloads larger than one call: the sender splits the payload into
+//! `Chunk<T>` messages with the id of the stream and the number of the chunk, one per call,
+//! and the receiver answers every call with a `Flow` to take the next chunk, to get the same
+//! chunk again later, or to stop the stream.
+//!
+//! A chunk is sent as args: the stream id, the sequence number, 1 for the last chunk, then the data.
+//! A Flow is one arg: 0 continue, 1 wait, 2 stop.
+//! The numbers don't wrap: a stream has at most `u32::MAX` chunks, numbered from 0,
+//! and both sides refuse the number `u32::MAX` with STREAM_ORDER_ERROR.
+
+#[cfg(feature = "host")]
+use crate::skip_args;
+#[cfg(feature = "guest")]
+use crate::ArgsWriter;
+use crate::{
+    add_fixed_words, FieldSchema, Incoming, Outcoming, ProtocolError, Schema, TypeSchema,
+    VariantSchema, STREAM_ORDER_ERROR,
+};
+#[cfg(any(feature = "guest", feature = "host"))]
+use crate::{ARGS_NEXT_ERROR, ENUM_FROM_U32_ERROR};
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::vec::Vec;
+#[cfg(feature = "guest")]
+use core::slice::IterMut;
+#[cfg(feature = "host")]
+use core::{cell::RefMut, slice::Iter};
+
+/**
+Part of a stream, a message like any other.
+*/
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Chunk<T> {
+    pub stream: u32,
+    /// Number of the chunk in the stream, from 0
+    pub seq: u32,
+    /// No chunks after this one
+    pub last: bool,
+    pub data: T,
+}
+
+impl<T: Incoming> Incoming for Chunk<T> {
+    const IS_NEED_INIT_FILL: bool = T::IS_NEED_INIT_FILL;
+
+    const FIXED_WORDS: Option<usize> = add_fixed_words(Some(3), T::FIXED_WORDS);
+
+    fn size_hint(&self) -> usize {
+        3 + self.data.size_hint()
+    }
+
+    #[cfg(feature = "guest")]
+    fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
+        let (_, stream) = u32::init(args)?;
+        let (_, seq) = u32::init(args)?;
+        let (_, last) = bool::init(args)?;
+        let (_, data) = T::init(args)?;
+        Ok((
+            0,
+            Chunk {
+                stream,
+                seq,
+                last,
+                data,
+            },
+        ))
+    }
+
+    #[cfg(feature = "host")]
+    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
+        Incoming::args(&self.stream, args)?;
+        Incoming::args(&self.seq, args)?;
+        Incoming::args(&self.last, args)?;
+        Incoming::args(&self.data, args)
+    }
+
+    #[cfg(feature = "host")]
+    fn fill(&self, heap: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        Incoming::fill(&self.stream, heap, args)?;
+        Incoming::fill(&self.seq, heap, args)?;
+        Incoming::fill(&self.last, heap, args)?;
+        Incoming::fill(&self.data, heap, args)
+    }
+}
+
+impl<T: Outcoming> Outcoming for Chunk<T> {
+    const IS_NEED_READ: bool = T::IS_NEED_READ;
+
+    const FIXED_WORDS: Option<usize> = add_fixed_words(Some(3), T::FIXED_WORDS);
+
+    fn size_hint(&self) -> usize {
+        3 + self.data.size_hint()
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        Outcoming::args(&self.stream, args)?;
+        Outcoming::args(&self.seq, args)?;
+        Outcoming::args(&self.last, args)?;
+        Outcoming::args(&self.data, args)
+    }
+
+    #[cfg(feature = "host")]
+    fn read(heap: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
+        Ok(Chunk {
+            stream: u32::read(heap, args)?,
+            seq: u32::read(heap, args)?,
+            last: bool::read(heap, args)?,
+            data: T::read(heap, args)?,
+        })
+    }
+
+    #[cfg(feature = "host")]
+    fn validate(heap: &[u8], args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        skip_args(args, 3)?;
+        T::validate(heap, args)
+    }
+
+    #[cfg(feature = "host")]
+    fn skip(args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        skip_args(args, 3)?;
+        T::skip(args)
+    }
+}
+
+impl<T: Schema> Schema for Chunk<T> {
+    const SCHEMA: &'static TypeSchema = &TypeSchema::Struct {
+        name: "Chunk",
+        packed: false,
+        framed: false,
+        fields: &[
+            FieldSchema {
+                name: "stream",
+                since: 0,
+                bits: 0,
+                schema: u32::SCHEMA,
+            },
+            FieldSchema {
+                name: "seq",
+                since: 0,
+                bits: 0,
+                schema: u32::SCHEMA,
+            },
+            FieldSchema {
+                name: "last",
+                since: 0,
+                bits: 0,
+                schema: bool::SCHEMA,
+            },
+            FieldSchema {
+                name: "data",
+                since: 0,
+                bits: 0,
+                schema: T::SCHEMA,
+            },
+        ],
+    };
+}
+
+/**
+Answer of the receiver to a chunk, the backpressure of a stream.
+*/
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Flow {
+    /// The chunk is taken, send the next one
+    Continue,
+    /// The receiver is full and didn't take the chunk, send the same chunk again later
+    Wait,
+    /// The receiver doesn't want more chunks of the stream
+    Stop,
+}
+
+impl Flow {
+    pub const fn code(self) -> u32 {
+        match self {
+            Flow::Continue => 0,
+            Flow::Wait => 1,
+            Flow::Stop => 2,
+        }
+    }
+
+    #[cfg(any(feature = "guest", feature = "host"))]
+    fn from_code(code: u32) -> Result<Self, ProtocolError> {
+        match code {
+            0 => Ok(Flow::Continue),
+            1 => Ok(Flow::Wait),
+            2 => Ok(Flow::Stop),
+            _ => Err(ProtocolError::from(ENUM_FROM_U32_ERROR)),
+        }
+    }
+}
+
+impl Incoming for Flow {
+    const FIXED_WORDS: Option<usize> = Some(1);
+
+    #[cfg(feature = "guest")]
+    fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
+        let code = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
+        Ok((code, Flow::from_code(code)?))
+    }
+
+    #[cfg(feature = "host")]
+    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
+        args.push(self.code());
+        Ok(())
+    }
+
+    #[cfg(feature = "host")]
+    fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        args.next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
+        Ok(())
+    }
+}
+
+impl Outcoming for Flow {
+    const FIXED_WORDS: Option<usize> = Some(1);
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        args.push(self.code())
+    }
+
+    #[cfg(feature = "host")]
+    fn read(_: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
+        let code = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))?;
+        Flow::from_code(code)
+    }
+}
+
+impl Schema for Flow {
+    const SCHEMA: &'static TypeSchema = &TypeSchema::Enum {
+        name: "Flow",
+        framed: false,
+        variants: &[
+            VariantSchema {
+                name: "Continue",
+                other: false,
+                schema: None,
+            },
+            VariantSchema {
+                name: "Wait",
+                other: false,
+                schema: None,
+            },
+            VariantSchema {
+                name: "Stop",
+                other: false,
+                schema: None,
+            },
+        ],
+    };
+}
+
+/**
+Sender side of a stream: numbers the chunks and follows the answers of the receiver.
+*/
+#[derive(Debug, Clone)]
+pub struct StreamWriter {
+    stream: u32,
+    seq: u32,
+    /// The last chunk is sent or the receiver stopped the stream
+    finished: bool,
+    stopped: bool,
+    /// A chunk is sent and its Flow isn't known yet
+    pending: bool,
+}
+
+impl StreamWriter {
+    pub const fn new(stream: u32) -> Self {
+        StreamWriter {
+            stream,
+            seq: 0,
+            finished: false,
+            stopped: false,
+            pending: false,
+        }
+    }
+
+    /**
+    Writer of a stream that already sent `seq` chunks, the next chunk has the number `seq`.
+    */
+    pub const fn resume(stream: u32, seq: u32) -> Self {
+        StreamWriter {
+            seq,
+            ..StreamWriter::new(stream)
+        }
+    }
+
+    pub fn stream(&self) -> u32 {
+        self.stream
+    }
+
+    /**
+    Next chunk of the stream with the data, an error after the last chunk or a Stop,
+    or when the numbers of the chunks run out.
+    */
+    pub fn chunk<T>(&mut self, data: T, last: bool) -> Result<Chunk<T>, ProtocolError> {
+        if self.finished {
+            return Err(ProtocolError::from(STREAM_ORDER_ERROR));
+        }
+        let next_seq = next_seq(self.seq)?;
+        let chunk = Chunk {
+            stream: self.stream,
+            seq: self.seq,
+            last,
+            data,
+        };
+        self.seq = next_seq;
+        self.finished = last;
+        self.pending = true;
+        Ok(chunk)
+    }
+
+    /**
+    Answer of the receiver to the last chunk: after a Wait the next `chunk`
+    has the same number, so the sender passes the same data again.
+    */
+    pub fn flow(&mut self, flow: Flow) {
+        if !self.pending {
+            return;
+        }
+        self.pending = false;
+        match flow {
+            Flow::Continue => {}
+            Flow::Wait => {
+                // the pending chunk took a number, so seq is above 0
+                self.seq -= 1;
+                self.finished = false;
+            }
+            Flow::Stop => {
+                self.finished = true;
+                self.stopped = true;
+            }
+        }
+    }
+
+    /**
+    No more chunks: the last one is taken or the receiver stopped the stream.
+    */
+    pub fn is_finished(&self) -> bool {
+        self.finished && !self.pending
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+}
+
+/**
+Receiver side of a stream: takes the chunks in order and ignores the ones sent again.
+The receiver answers Wait before `receive` when it can't take the chunk yet.
+*/
+#[derive(Debug, Clone)]
+pub struct StreamReader {
+    stream: u32,
+    next_seq: u32,
+    finished: bool,
+}
+
+impl StreamReader {
+    pub const fn new(stream: u32) -> Self {
+        StreamReader {
+            stream,
+            next_seq: 0,
+            finished: false,
+        }
+    }
+
+    /**
+    Reader of a stream that already took `seq` chunks, the next chunk has the number `seq`.
+    */
+    pub const fn resume(stream: u32, seq: u32) -> Self {
+        StreamReader {
+            next_seq: seq,
+            ..StreamReader::new(stream)
+        }
+    }
+
+    pub fn stream(&self) -> u32 {
+        self.stream
+    }
+
+    /**
+    Data of the next chunk, None for a chunk that was already taken,
+    an error for another stream, a missed chunk or a chunk after the last one.
+    */
+    pub fn receive<T>(&mut self, chunk: Chunk<T>) -> Result<Option<T>, ProtocolError> {
+        if chunk.stream != self.stream {
+            return Err(ProtocolError::from(STREAM_ORDER_ERROR));
+        }
+        if chunk.seq < self.next_seq {
+            return Ok(None);
+        }
+        if chunk.seq != self.next_seq || self.finished {
+            return Err(ProtocolError::from(STREAM_ORDER_ERROR));
+        }
+        self.next_seq = next_seq(self.next_seq)?;
+        self.finished = chunk.last;
+        Ok(Some(chunk.data))
+    }
+
+    /**
+    The last chunk is taken.
+    */
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /**
+    Count of the chunks taken.
+    */
+    pub fn received(&self) -> u32 {
+        self.next_seq
+    }
+}
+
+/**
+Number after `seq`: the numbers don't wrap, so a chunk numbered `u32::MAX` is an error
+on both sides instead of starting the stream again from 0.
+*/
+fn next_seq(seq: u32) -> Result<u32, ProtocolError> {
+    seq.checked_add(1)
+        .ok_or_else(|| ProtocolError::from(STREAM_ORDER_ERROR))
+}
+
+/**
+Receiver that joins the bytes of the chunks into the whole payload, up to `limit` bytes.
+*/
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct Reassembler {
+    reader: StreamReader,
+    bytes: Vec<u8>,
+    limit: usize,
+    stopped: bool,
+}
+
+#[cfg(feature = "alloc")]
+impl Reassembler {
+    pub fn new(stream: u32, limit: usize) -> Self {
+        Reassembler {
+            reader: StreamReader::new(stream),
+            bytes: Vec::new(),
+            limit,
+            stopped: false,
+        }
+    }
+
+    /**
+    Take a chunk and answer the sender: Stop once the payload exceeds the limit.
+    */
+    pub fn push<T: AsRef<[u8]>>(&mut self, chunk: Chunk<T>) -> Result<Flow, ProtocolError> {
+        if self.stopped {
+            return Ok(Flow::Stop);
+        }
+        if let Some(data) = self.reader.receive(chunk)? {
+            let data = data.as_ref();
+            if self.bytes.len() + data.len() > self.limit {
+                self.stopped = true;
+                self.bytes = Vec::new();
+                return Ok(Flow::Stop);
+            }
+            self.bytes.extend_from_slice(data);
+        }
+        Ok(Flow::Continue)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.reader.is_finished()
+    }
+
+    /**
+    The whole payload after the last chunk.
+    */
+    pub fn finish(self) -> Option<Vec<u8>> {
+        if self.reader.is_finished() && !self.stopped {
+            Some(self.bytes)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,536 @@
+//! Dynamic value decoded from the args with a runtime schema, for the tooling
+//! (debuggers, proxies, logging) that doesn't compile against the types of the guest.
+//!
+//! Enum tags are the indexes of the variants in the schema, like the derived enums
+//! with the default discriminants.
+//!
+//! Values are checked more strictly than by the typed read, like in the dump:
+//! bools and Option tags are 0 or 1, integers fit their type, nanos are below a second,
+//! and a list or a map can't have more items than the args left.
+
+#[cfg(feature = "host")]
+use crate::skip_args;
+use crate::{
+    bytes_args, bytes_words, enum_bits, frame_end, items_len, mask, min_words, next,
+    skip_to_frame_end, u128_from_args, u128_into_args, u64_from_args, u64_into_args, usize_arg,
+    words, ArgsWriter, FieldTree, Incoming, Outcoming, ProtocolError, SchemaTree,
+    ENUM_FROM_U32_ERROR, NANOS_PER_SEC, SCHEMA_MISMATCH_ERROR, TIME_PARSE_ERROR,
+};
+#[cfg(all(not(feature = "std"), feature = "alloc"))]
+use alloc::{boxed::Box, string::String, vec::Vec};
+#[cfg(feature = "host")]
+use core::cell::RefMut;
+use core::slice::Iter;
+#[cfg(feature = "guest")]
+use core::slice::IterMut;
+
+/**
+Value of any type with a schema.
+Duration and SystemTime are records of `secs` and `nanos`, aliases are their inner values,
+`Serde` payloads are bytes, and the since fields absent in a framed struct are left out.
+*/
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Bool(bool),
+    /// Signed integers
+    Int(i128),
+    /// Unsigned integers
+    UInt(u128),
+    Float(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    Option(Option<Box<Value>>),
+    /// Vec, arrays and tuples
+    List(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    /// Enum variant: the tag, the name and the value
+    Variant(u32, String, Option<Box<Value>>),
+    /// Struct: the names and the values of the fields
+    Record(Vec<(String, Value)>),
+}
+
+impl Value {
+    /**
+    Read a value of the schema from the args.
+    */
+    pub fn from_args(schema: &SchemaTree, args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
+        let value = match schema {
+            SchemaTree::Primitive(name) => primitive_from_args(name, args)?,
+            SchemaTree::Alias(_, inner) => Value::from_args(inner, args)?,
+            SchemaTree::Payload(_, _) => Value::Bytes(bytes_from_args(args)?),
+            SchemaTree::Option(inner) => match next(args)? {
+                0 => Value::Option(None),
+                1 => Value::Option(Some(Box::new(Value::from_args(inner, args)?))),
+                _ => return Err(ProtocolError::from(ENUM_FROM_U32_ERROR)),
+            },
+            SchemaTree::Seq(inner) => {
+                let len = items_len(args, min_words(inner))?;
+                Value::List(
+                    (0..len)
+                        .map(|_| Value::from_args(inner, args))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            SchemaTree::Array(inner, len) => Value::List(
+                (0..*len)
+                    .map(|_| Value::from_args(inner, args))
+                    .collect::<Result<_, _>>()?,
+            ),
+            SchemaTree::Tuple(items) => Value::List(
+                items
+                    .iter()
+                    .map(|item| Value::from_args(item, args))
+                    .collect::<Result<_, _>>()?,
+            ),
+            SchemaTree::Map(key, value) => {
+                let len = items_len(args, min_words(key) + min_words(value))?;
+                Value::Map(
+                    (0..len)
+                        .map(|_| Ok((Value::from_args(key, args)?, Value::from_args(value, args)?)))
+                        .collect::<Result<_, ProtocolError>>()?,
+                )
+            }
+            SchemaTree::Struct {
+                packed,
+                framed,
+                fields,
+                ..
+            } => {
+                let end = if *framed {
+                    Some(frame_end(args)?)
+                } else {
+                    None
+                };
+                let mut record: Vec<(String, Value)> = Vec::with_capacity(fields.len());
+                if *packed {
+                    let mut word = 0;
+                    let mut used = u32::BITS;
+                    for field in fields.iter().filter(|field| field.bits > 0) {
+                        if used + field.bits > u32::BITS {
+                            word = next(args)?;
+                            used = 0;
+                        }
+                        let bits = (word >> used) & mask(field.bits);
+                        used += field.bits;
+                        record.push((field.name.clone(), packed_from_bits(field, bits)?));
+                    }
+                }
+                for field in fields.iter().filter(|field| !*packed || field.bits == 0) {
+                    if let Some(end) = end {
+                        if field.since > 0 && args.len() <= end {
+                            continue;
+                        }
+                    }
+                    record.push((field.name.clone(), Value::from_args(&field.schema, args)?));
+                }
+                // the fields in the order of the schema
+                record.sort_by_key(|(name, _)| fields.iter().position(|field| &field.name == name));
+                if let Some(end) = end {
+                    skip_to_frame_end(args, end)?;
+                }
+                Value::Record(record)
+            }
+            SchemaTree::Enum {
+                framed, variants, ..
+            } => {
+                let tag = next(args)?;
+                let end = if *framed {
+                    Some(frame_end(args)?)
+                } else {
+                    None
+                };
+                let value = match variants.get(tag as usize) {
+                    Some((name, _, schema)) => {
+                        let value = match schema {
+                            Some(schema) => Some(Box::new(Value::from_args(schema, args)?)),
+                            None => None,
+                        };
+                        Value::Variant(tag, name.clone(), value)
+                    }
+                    None => match variants.iter().position(|(_, other, _)| *other) {
+                        Some(index) => {
+                            Value::Variant(index as u32, variants[index].0.clone(), None)
+                        }
+                        None => return Err(ProtocolError::from(ENUM_FROM_U32_ERROR)),
+                    },
+                };
+                if let Some(end) = end {
+                    skip_to_frame_end(args, end)?;
+                }
+                value
+            }
+        };
+        Ok(value)
+    }
+
+    /**
+    Write the value as args of the schema, the same args as the typed value.
+    */
+    pub fn args<W: ArgsWriter>(
+        &self,
+        schema: &SchemaTree,
+        args: &mut W,
+    ) -> Result<(), ProtocolError> {
+        match (schema, self) {
+            (SchemaTree::Primitive(name), value) => primitive_args(name, value, args),
+            (SchemaTree::Alias(_, inner), value) => value.args(inner, args),
+            (SchemaTree::Payload(_, _), Value::Bytes(value)) => bytes_args(value, args),
+            (SchemaTree::Option(_), Value::Option(None)) => args.push(0),
+            (SchemaTree::Option(inner), Value::Option(Some(value))) => {
+                args.push(1)?;
+                value.args(inner, args)
+            }
+            (SchemaTree::Seq(inner), Value::List(items)) => {
+                args.push(usize_arg(items.len())?)?;
+                items.iter().try_for_each(|item| item.args(inner, args))
+            }
+            (SchemaTree::Array(inner, len), Value::List(items)) if items.len() == *len => {
+                items.iter().try_for_each(|item| item.args(inner, args))
+            }
+            (SchemaTree::Tuple(schemas), Value::List(items)) if items.len() == schemas.len() => {
+                schemas
+                    .iter()
+                    .zip(items)
+                    .try_for_each(|(schema, item)| item.args(schema, args))
+            }
+            (SchemaTree::Map(key_schema, value_schema), Value::Map(entries)) => {
+                args.push(usize_arg(entries.len())?)?;
+                for (key, value) in entries {
+                    key.args(key_schema, args)?;
+                    value.args(value_schema, args)?;
+                }
+                Ok(())
+            }
+            (
+                SchemaTree::Struct {
+                    packed,
+                    framed,
+                    fields,
+                    ..
+                },
+                Value::Record(record),
+            ) => {
+                let mut body: Vec<u32> = Vec::new();
+                if *packed {
+                    let mut word = 0;
+                    let mut used = 0;
+                    for field in fields.iter().filter(|field| field.bits > 0) {
+                        let bits = packed_into_bits(field, field_value(record, field)?)?;
+                        if used + field.bits > u32::BITS {
+                            body.push(word);
+                            word = 0;
+                            used = 0;
+                        }
+                        word |= (bits & mask(field.bits)) << used;
+                        used += field.bits;
+                    }
+                    if used > 0 {
+                        body.push(word);
+                    }
+                }
+                for field in fields.iter().filter(|field| !*packed || field.bits == 0) {
+                    match record.iter().find(|(name, _)| name == &field.name) {
+                        Some((_, value)) => value.args(&field.schema, &mut body)?,
+                        // the since fields are trailing, an older reader defaults them
+                        None if *framed && field.since > 0 => {}
+                        None => return Err(ProtocolError::from(SCHEMA_MISMATCH_ERROR)),
+                    }
+                }
+                if *framed {
+                    args.push(usize_arg(body.len())?)?;
+                }
+                body.into_iter().try_for_each(|word| args.push(word))
+            }
+            (
+                SchemaTree::Enum {
+                    framed, variants, ..
+                },
+                Value::Variant(tag, _, value),
+            ) => {
+                let schema = match variants.get(*tag as usize) {
+                    Some((_, _, schema)) => schema,
+                    None => return Err(ProtocolError::from(ENUM_FROM_U32_ERROR)),
+                };
+                let mut body: Vec<u32> = Vec::new();
+                match (schema, value) {
+                    (Some(schema), Some(value)) => value.args(schema, &mut body)?,
+                    (None, None) => {}
+                    _ => return Err(ProtocolError::from(SCHEMA_MISMATCH_ERROR)),
+                }
+                args.push(*tag)?;
+                if *framed {
+                    args.push(usize_arg(body.len())?)?;
+                }
+                body.into_iter().try_for_each(|word| args.push(word))
+            }
+            _ => Err(ProtocolError::from(SCHEMA_MISMATCH_ERROR)),
+        }
+    }
+}
+
+/**
+Value with its schema, `(SchemaTree, Value)` as a message: the args of the schema
+(`schema_args`), then the args of the value, so the other side reads it without the type.
+The strings and the bytes are always in the args, there's nothing to fill in the memory.
+*/
+#[derive(Debug, PartialEq, Clone)]
+pub struct Dynamic(pub SchemaTree, pub Value);
+
+impl Dynamic {
+    fn write<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        self.0.args(args)?;
+        self.1.args(&self.0, args)
+    }
+
+    fn from_args(args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
+        let schema = SchemaTree::read(args)?;
+        let value = Value::from_args(&schema, args)?;
+        Ok(Dynamic(schema, value))
+    }
+
+    fn words(&self) -> usize {
+        let mut args: Vec<u32> = Vec::new();
+        self.write(&mut args).map_or(0, |_| args.len())
+    }
+}
+
+impl From<(SchemaTree, Value)> for Dynamic {
+    fn from((schema, value): (SchemaTree, Value)) -> Self {
+        Dynamic(schema, value)
+    }
+}
+
+impl Incoming for Dynamic {
+    fn size_hint(&self) -> usize {
+        self.words()
+    }
+
+    #[cfg(feature = "guest")]
+    fn init(args: &mut IterMut<u32>) -> Result<(u32, Self), ProtocolError> {
+        let rest = args.as_slice();
+        let mut iter = rest.iter();
+        let value = Dynamic::from_args(&mut iter)?;
+        let read = rest.len() - iter.len();
+        if read > 0 {
+            args.nth(read - 1);
+        }
+        Ok((0, value))
+    }
+
+    #[cfg(feature = "host")]
+    fn args(&self, args: &mut Vec<u32>) -> Result<(), ProtocolError> {
+        self.write(args)
+    }
+
+    #[cfg(feature = "host")]
+    fn fill(&self, _: &mut RefMut<[u8]>, args: &mut Iter<u32>) -> Result<(), ProtocolError> {
+        skip_args(args, self.words())
+    }
+}
+
+impl Outcoming for Dynamic {
+    fn size_hint(&self) -> usize {
+        self.words()
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        self.write(args)
+    }
+
+    #[cfg(feature = "host")]
+    fn read(_: &[u8], args: &mut Iter<u32>) -> Result<Self, ProtocolError> {
+        Dynamic::from_args(args)
+    }
+}
+
+fn bytes_from_args(args: &mut Iter<u32>) -> Result<Vec<u8>, ProtocolError> {
+    let (len, words) = bytes_words(args)?;
+    Ok(words
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .take(len)
+        .collect())
+}
+
+fn time_record(secs: Value, nanos: u32) -> Value {
+    Value::Record(Vec::from([
+        (String::from("secs"), secs),
+        (String::from("nanos"), Value::UInt(nanos as u128)),
+    ]))
+}
+
+fn primitive_from_args(name: &str, args: &mut Iter<u32>) -> Result<Value, ProtocolError> {
+    let value = match name {
+        "bool" => match next(args)? {
+            0 => Value::Bool(false),
+            1 => Value::Bool(true),
+            _ => return Err(ProtocolError::from(ENUM_FROM_U32_ERROR)),
+        },
+        "u8" | "u16" | "u32" | "usize" => int_value(name, Value::UInt(next(args)? as u128))?,
+        "i8" | "i32" | "isize" => int_value(name, Value::Int(next(args)? as i32 as i128))?,
+        "u64" => Value::UInt(u64_from_args(words(args)?) as u128),
+        "i64" => Value::Int(u64_from_args(words(args)?) as i64 as i128),
+        "u128" => Value::UInt(u128_from_args(words(args)?)),
+        "i128" => Value::Int(u128_from_args(words(args)?) as i128),
+        "f32" => Value::Float(f32::from_bits(next(args)?) as f64),
+        "f64" => Value::Float(f64::from_bits(u64_from_args(words(args)?))),
+        "String" => Value::String(String::from_utf8(bytes_from_args(args)?)?),
+        "Bytes" => Value::Bytes(bytes_from_args(args)?),
+        "Duration" => {
+            let secs = u64_from_args(words(args)?);
+            time_record(Value::UInt(secs as u128), nanos(next(args)?)?)
+        }
+        "SystemTime" => {
+            let secs = u64_from_args(words(args)?) as i64;
+            time_record(Value::Int(secs as i128), nanos(next(args)?)?)
+        }
+        _ => return Err(ProtocolError::from(SCHEMA_MISMATCH_ERROR)),
+    };
+    Ok(value)
+}
+
+fn primitive_args<W: ArgsWriter>(
+    name: &str,
+    value: &Value,
+    args: &mut W,
+) -> Result<(), ProtocolError> {
+    match (name, value) {
+        ("bool", Value::Bool(value)) => args.push(*value as u32),
+        // the integers out of the range of the type would be truncated
+        (_, Value::UInt(_) | Value::Int(_)) if !int_fits(name, value) => {
+            Err(ProtocolError::from(SCHEMA_MISMATCH_ERROR))
+        }
+        ("u8" | "u16" | "u32" | "usize", Value::UInt(value)) => args.push(*value as u32),
+        ("i8" | "i32" | "isize", Value::Int(value)) => args.push(*value as u32),
+        ("u64", Value::UInt(value)) => u64_into_args(*value as u64)
+            .into_iter()
+            .try_for_each(|word| args.push(word)),
+        ("i64", Value::Int(value)) => u64_into_args(*value as u64)
+            .into_iter()
+            .try_for_each(|word| args.push(word)),
+        ("u128", Value::UInt(value)) => u128_into_args(*value)
+            .into_iter()
+            .try_for_each(|word| args.push(word)),
+        ("i128", Value::Int(value)) => u128_into_args(*value as u128)
+            .into_iter()
+            .try_for_each(|word| args.push(word)),
+        ("f32", Value::Float(value)) => args.push((*value as f32).to_bits()),
+        ("f64", Value::Float(value)) => u64_into_args(value.to_bits())
+            .into_iter()
+            .try_for_each(|word| args.push(word)),
+        ("String", Value::String(value)) => bytes_args(value.as_bytes(), args),
+        ("Bytes", Value::Bytes(value)) => bytes_args(value, args),
+        ("Duration" | "SystemTime", Value::Record(record)) => {
+            let secs = match record.iter().find(|(name, _)| name == "secs") {
+                Some((_, secs @ Value::UInt(value)))
+                    if name == "Duration" && int_fits("u64", secs) =>
+                {
+                    *value as u64
+                }
+                Some((_, secs @ Value::Int(value)))
+                    if name == "SystemTime" && int_fits("i64", secs) =>
+                {
+                    *value as u64
+                }
+                _ => return Err(ProtocolError::from(SCHEMA_MISMATCH_ERROR)),
+            };
+            let nanos = match record.iter().find(|(name, _)| name == "nanos") {
+                Some((_, Value::UInt(value))) if *value < NANOS_PER_SEC as u128 => *value as u32,
+                Some((_, Value::UInt(_))) => return Err(ProtocolError::from(TIME_PARSE_ERROR)),
+                _ => return Err(ProtocolError::from(SCHEMA_MISMATCH_ERROR)),
+            };
+            u64_into_args(secs)
+                .into_iter()
+                .try_for_each(|word| args.push(word))?;
+            args.push(nanos)
+        }
+        _ => Err(ProtocolError::from(SCHEMA_MISMATCH_ERROR)),
+    }
+}
+
+/**
+Whether an integer value is in the range of the primitive, the value of another type doesn't fit.
+*/
+pub(crate) fn int_fits(name: &str, value: &Value) -> bool {
+    match value {
+        Value::UInt(value) => match name {
+            "u8" => u8::try_from(*value).is_ok(),
+            "u16" => u16::try_from(*value).is_ok(),
+            "u32" | "usize" => u32::try_from(*value).is_ok(),
+            "u64" => u64::try_from(*value).is_ok(),
+            "u128" => true,
+            _ => false,
+        },
+        Value::Int(value) => match name {
+            "i8" => i8::try_from(*value).is_ok(),
+            "i32" | "isize" => i32::try_from(*value).is_ok(),
+            "i64" => i64::try_from(*value).is_ok(),
+            "i128" => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn int_value(name: &str, value: Value) -> Result<Value, ProtocolError> {
+    if !int_fits(name, &value) {
+        return Err(ProtocolError::from(SCHEMA_MISMATCH_ERROR));
+    }
+    Ok(value)
+}
+
+fn nanos(nanos: u32) -> Result<u32, ProtocolError> {
+    if nanos >= NANOS_PER_SEC {
+        return Err(ProtocolError::from(TIME_PARSE_ERROR));
+    }
+    Ok(nanos)
+}
+
+fn packed_from_bits(field: &FieldTree, bits: u32) -> Result<Value, ProtocolError> {
+    let value = match &field.schema {
+        SchemaTree::Primitive(name) if name == "bool" => Value::Bool(bits == 1),
+        SchemaTree::Primitive(name) if name == "i8" => Value::Int(bits as u8 as i8 as i128),
+        SchemaTree::Primitive(_) => Value::UInt(bits as u128),
+        SchemaTree::Enum { variants, .. } => match variants.get(bits as usize) {
+            Some((name, _, None)) => Value::Variant(bits, name.clone(), None),
+            _ => return Err(ProtocolError::from(ENUM_FROM_U32_ERROR)),
+        },
+        _ => return Err(ProtocolError::from(SCHEMA_MISMATCH_ERROR)),
+    };
+    Ok(value)
+}
+
+fn packed_into_bits(field: &FieldTree, value: &Value) -> Result<u32, ProtocolError> {
+    let bits = match (&field.schema, value) {
+        (SchemaTree::Primitive(name), Value::Bool(value)) if name == "bool" => *value as u32,
+        (SchemaTree::Primitive(name), Value::UInt(bits)) if int_fits(name, value) => *bits as u32,
+        (SchemaTree::Primitive(name), Value::Int(bits))
+            if name == "i8" && int_fits(name, value) =>
+        {
+            *bits as u8 as u32
+        }
+        (SchemaTree::Enum { variants, .. }, Value::Variant(tag, _, None))
+            if field.bits == enum_bits(variants.len() as u32) =>
+        {
+            // the mask would turn another tag into a valid variant
+            if *tag as usize >= variants.len() {
+                return Err(ProtocolError::from(ENUM_FROM_U32_ERROR));
+            }
+            *tag
+        }
+        _ => return Err(ProtocolError::from(SCHEMA_MISMATCH_ERROR)),
+    };
+    Ok(bits)
+}
+
+fn field_value<'a>(
+    record: &'a [(String, Value)],
+    field: &FieldTree,
+) -> Result<&'a Value, ProtocolError> {
+    record
+        .iter()
+        .find(|(name, _)| name == &field.name)
+        .map(|(_, value)| value)
+        .ok_or_else(|| ProtocolError::from(SCHEMA_MISMATCH_ERROR))
+}
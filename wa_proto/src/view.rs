@@ -0,0 +1,305 @@
+//! Lazy views of the collections in the args: the host reads `SeqView<T>` and `MapView<K, V>`
+//! from the same args as `Vec<T>` and the maps, and decodes an item only when it gets to it.
+//! Items of the types with `FIXED_WORDS` are found by their index without reading the others.
+
+#[cfg(any(feature = "guest", feature = "host"))]
+use crate::ProtocolError;
+#[cfg(feature = "host")]
+use crate::{skip_args, skip_items, ARGS_NEXT_ERROR};
+#[cfg(feature = "guest")]
+use crate::{usize_arg, ArgsWriter};
+use crate::{Outcoming, OutcomingRef, Schema, TypeSchema};
+#[cfg(feature = "host")]
+use core::borrow::Borrow;
+use core::fmt;
+use core::marker::PhantomData;
+#[cfg(feature = "host")]
+use core::slice::Iter;
+
+/**
+Items of a `Vec<T>` left in the args, read one by one on access.
+Only the number of the args is checked when the view is read,
+`T::validate` over the whole `Vec<T>` checks the items up front without allocating.
+*/
+pub struct SeqView<'a, T> {
+    // only the host decodes the items
+    #[cfg_attr(not(feature = "host"), allow(dead_code))]
+    heap: &'a [u8],
+    /// Args of the items, without the len
+    items: &'a [u32],
+    len: usize,
+    item: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for SeqView<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for SeqView<'_, T> {}
+
+/**
+Only the len and the args, the items aren't decoded for the output.
+*/
+impl<T> fmt::Debug for SeqView<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SeqView")
+            .field("len", &self.len)
+            .field("args", &self.items.len())
+            .finish()
+    }
+}
+
+impl<'a, T> SeqView<'a, T> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /**
+    Args of the items, for passing them on without decoding.
+    */
+    pub fn item_args(&self) -> &'a [u32] {
+        self.items
+    }
+}
+
+#[cfg(feature = "host")]
+impl<'a, T: Outcoming> SeqView<'a, T> {
+    /**
+    Item at the index: the args of a fixed width item are found by a multiplication,
+    otherwise the items before it are skipped without decoding them.
+    */
+    pub fn get(&self, index: usize) -> Option<Result<T, ProtocolError>> {
+        if index >= self.len {
+            return None;
+        }
+        let mut args = self.items.iter();
+        let skipped = match T::FIXED_WORDS {
+            Some(words) => skip_args(&mut args, index * words),
+            None => (0..index).try_for_each(|_| T::skip(&mut args)),
+        };
+        Some(skipped.and_then(|_| T::read(self.heap, &mut args)))
+    }
+
+    pub fn first(&self) -> Option<Result<T, ProtocolError>> {
+        self.get(0)
+    }
+
+    pub fn iter(&self) -> SeqIter<'a, T> {
+        SeqIter {
+            heap: self.heap,
+            args: self.items.iter(),
+            left: self.len,
+            item: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "host")]
+impl<'a, T: Outcoming> IntoIterator for &SeqView<'a, T> {
+    type Item = Result<T, ProtocolError>;
+    type IntoIter = SeqIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T: Outcoming> OutcomingRef<'a, 'a> for SeqView<'a, T> {
+    fn size_hint(&self) -> usize {
+        1 + self.items.len()
+    }
+
+    /**
+    The same args as the Vec the view was read from.
+    */
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        args.push(usize_arg(self.len)?)?;
+        self.items.iter().try_for_each(|word| args.push(*word))
+    }
+
+    #[cfg(feature = "host")]
+    fn read_ref(heap: &'a [u8], args: &mut Iter<'a, u32>) -> Result<Self, ProtocolError> {
+        let len = *args
+            .next()
+            .ok_or_else(|| ProtocolError::from(ARGS_NEXT_ERROR))? as usize;
+        let rest = args.as_slice();
+        skip_items::<T>(len, args)?;
+        Ok(SeqView {
+            heap,
+            items: &rest[..rest.len() - args.len()],
+            len,
+            item: PhantomData,
+        })
+    }
+}
+
+impl<T: Schema> Schema for SeqView<'_, T> {
+    const SCHEMA: &'static TypeSchema = &TypeSchema::Seq(T::SCHEMA);
+}
+
+/**
+Iterator over the items of a SeqView, decoded in `next`.
+The args after a broken item can't be found, so it ends after the first error.
+*/
+#[cfg(feature = "host")]
+pub struct SeqIter<'a, T> {
+    heap: &'a [u8],
+    args: Iter<'a, u32>,
+    left: usize,
+    item: PhantomData<fn() -> T>,
+}
+
+#[cfg(feature = "host")]
+impl<T: Outcoming> Iterator for SeqIter<'_, T> {
+    type Item = Result<T, ProtocolError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.left == 0 {
+            return None;
+        }
+        let item = T::read(self.heap, &mut self.args);
+        self.left = if item.is_ok() { self.left - 1 } else { 0 };
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.left.min(1), Some(self.left))
+    }
+
+    /**
+    Skip the items before the nth without decoding them.
+    */
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let n = n.min(self.left);
+        let skipped = match T::FIXED_WORDS {
+            Some(words) => skip_args(&mut self.args, n * words),
+            None => (0..n).try_for_each(|_| T::skip(&mut self.args)),
+        };
+        if let Err(error) = skipped {
+            self.left = 0;
+            return Some(Err(error));
+        }
+        self.left -= n;
+        self.next()
+    }
+}
+
+/**
+Entries of a map left in the args, read one by one on access like a `SeqView<(K, V)>`.
+Looking up a key reads the keys before it and skips their values.
+*/
+pub struct MapView<'a, K, V> {
+    entries: SeqView<'a, (K, V)>,
+}
+
+impl<K, V> Clone for MapView<'_, K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for MapView<'_, K, V> {}
+
+impl<K, V> fmt::Debug for MapView<'_, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MapView")
+            .field("len", &self.len())
+            .field("args", &self.item_args().len())
+            .finish()
+    }
+}
+
+impl<'a, K, V> MapView<'a, K, V> {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /**
+    Args of the entries, for passing them on without decoding.
+    */
+    pub fn item_args(&self) -> &'a [u32] {
+        self.entries.item_args()
+    }
+}
+
+#[cfg(feature = "host")]
+impl<'a, K: Outcoming, V: Outcoming> MapView<'a, K, V> {
+    /**
+    Value of the first entry with the key, duplicate keys aren't checked.
+    */
+    pub fn get<Q>(&self, key: &Q) -> Option<Result<V, ProtocolError>>
+    where
+        K: Borrow<Q>,
+        Q: PartialEq + ?Sized,
+    {
+        let heap = self.entries.heap;
+        let mut args = self.entries.items.iter();
+        for _ in 0..self.len() {
+            let found = match K::read(heap, &mut args) {
+                Ok(found) => found,
+                Err(error) => return Some(Err(error)),
+            };
+            if found.borrow() == key {
+                return Some(V::read(heap, &mut args));
+            }
+            if let Err(error) = V::skip(&mut args) {
+                return Some(Err(error));
+            }
+        }
+        None
+    }
+
+    /**
+    Entry at the index in the order of the args, found like `SeqView::get`.
+    */
+    pub fn get_index(&self, index: usize) -> Option<Result<(K, V), ProtocolError>> {
+        self.entries.get(index)
+    }
+
+    pub fn iter(&self) -> SeqIter<'a, (K, V)> {
+        self.entries.iter()
+    }
+}
+
+#[cfg(feature = "host")]
+impl<'a, K: Outcoming, V: Outcoming> IntoIterator for &MapView<'a, K, V> {
+    type Item = Result<(K, V), ProtocolError>;
+    type IntoIter = SeqIter<'a, (K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: Outcoming, V: Outcoming> OutcomingRef<'a, 'a> for MapView<'a, K, V> {
+    fn size_hint(&self) -> usize {
+        self.entries.size_hint()
+    }
+
+    #[cfg(feature = "guest")]
+    fn args<W: ArgsWriter>(&self, args: &mut W) -> Result<(), ProtocolError> {
+        OutcomingRef::args(&self.entries, args)
+    }
+
+    #[cfg(feature = "host")]
+    fn read_ref(heap: &'a [u8], args: &mut Iter<'a, u32>) -> Result<Self, ProtocolError> {
+        Ok(MapView {
+            entries: SeqView::read_ref(heap, args)?,
+        })
+    }
+}
+
+impl<K: Schema, V: Schema> Schema for MapView<'_, K, V> {
+    const SCHEMA: &'static TypeSchema = &TypeSchema::Map(K::SCHEMA, V::SCHEMA);
+}
@@ -0,0 +1,90 @@
+//! Guests without an allocator: `ArgsBuf` over a stack buffer and the heapless types,
+//! `cargo test --features guest,heapless`
+#![cfg(all(feature = "guest", feature = "std"))]
+
+use wa_proto::{ArgsBuf, ArgsWriter, Outcoming, ProtocolError, ARGS_BUF_FULL_ERROR};
+#[cfg(feature = "heapless")]
+use wa_proto::{Incoming, CAPACITY_ERROR};
+
+fn error(code: u32) -> ProtocolError {
+    ProtocolError::from(code)
+}
+
+#[test]
+fn args_buf_writes_into_the_slice() {
+    let mut buf = [0_u32; 8];
+    let mut args = ArgsBuf::new(&mut buf);
+    assert!(args.is_empty());
+    let value = (7_u32, u64::MAX, Some(true));
+    assert_eq!(Outcoming::size_hint(&value), 5);
+    Outcoming::args(&value, &mut args).unwrap();
+    assert_eq!(args.len(), 5);
+    assert_eq!(args.as_slice(), [7, u32::MAX, u32::MAX, 1, 1]);
+    args.as_mut_slice()[0] = 8;
+    assert_eq!(buf[..6], [8, u32::MAX, u32::MAX, 1, 1, 0]);
+}
+
+#[test]
+fn args_buf_refuses_args_beyond_the_slice() {
+    let mut buf = [0_u32; 2];
+    let mut args = ArgsBuf::new(&mut buf);
+    let full = Outcoming::args(&(1_u32, 2_u64), &mut args).unwrap_err();
+    assert_eq!(full.0, error(ARGS_BUF_FULL_ERROR).0);
+    // the args that fit stay written
+    assert_eq!(args.as_slice(), [1, 2]);
+    assert_eq!(args.push(3).unwrap_err().0, error(ARGS_BUF_FULL_ERROR).0);
+
+    let mut empty = ArgsBuf::new(&mut []);
+    assert!(Outcoming::args(&(), &mut empty).is_ok());
+    assert!(Outcoming::args(&0_u8, &mut empty).is_err());
+}
+
+#[cfg(feature = "heapless")]
+#[test]
+fn heapless_from_a_stack_buffer() {
+    let text: heapless::String<8> = heapless::String::try_from("abcde").unwrap();
+    let items: heapless::Vec<u64, 4> = heapless::Vec::from_slice(&[1, 1 << 32]).unwrap();
+    let mut buf = [0_u32; 16];
+    let mut args = ArgsBuf::new(&mut buf);
+    Outcoming::args(&(text.clone(), items.clone()), &mut args).unwrap();
+    assert_eq!(args.as_slice(), [5, 0x6463_6261, 0x65, 2, 1, 0, 0, 1]);
+
+    let (_, decoded) =
+        <(heapless::String<8>, heapless::Vec<u64, 4>)>::init(&mut args.as_mut_slice().iter_mut())
+            .unwrap();
+    assert_eq!(decoded, (text, items));
+}
+
+#[cfg(feature = "heapless")]
+#[test]
+fn heapless_capacity_errors() {
+    let capacity = error(CAPACITY_ERROR).0;
+    // 5 bytes into a string of 4
+    let mut args = [5, 0x6463_6261, 0x65];
+    let (_, fits) = heapless::String::<8>::init(&mut args.iter_mut()).unwrap();
+    assert_eq!(fits, "abcde");
+    let full = heapless::String::<4>::init(&mut args.iter_mut()).unwrap_err();
+    assert_eq!(full.0, capacity);
+    // 3 items into a vec of 2
+    let mut args = [3, 1, 2, 3];
+    let full = heapless::Vec::<u32, 2>::init(&mut args.iter_mut()).unwrap_err();
+    assert_eq!(full.0, capacity);
+
+    #[cfg(feature = "host")]
+    {
+        let args = [5, 0x6463_6261, 0x65];
+        let full = heapless::String::<4>::read(&[], &mut args.iter()).unwrap_err();
+        assert_eq!(full.0, capacity);
+        let full = heapless::String::<4>::validate(&[], &mut args.iter()).unwrap_err();
+        assert_eq!(full.0, capacity);
+        let args = [3, 1, 2, 3];
+        let full = heapless::Vec::<u32, 2>::read(&[], &mut args.iter()).unwrap_err();
+        assert_eq!(full.0, capacity);
+        let full = heapless::Vec::<u32, 2>::validate(&[], &mut args.iter()).unwrap_err();
+        assert_eq!(full.0, capacity);
+        // skip doesn't check the capacity
+        let mut iter = args.iter();
+        heapless::Vec::<u32, 2>::skip(&mut iter).unwrap();
+        assert_eq!(iter.len(), 0);
+    }
+}
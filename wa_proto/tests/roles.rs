@@ -0,0 +1,76 @@
+//! Each role against fixed args, so a build with one role is tested too:
+//! `cargo test` checks the host side, `cargo test --no-default-features --features guest,std`
+//! the guest side.
+#![cfg(feature = "std")]
+
+use wa_proto::{Incoming, Outcoming};
+
+/**
+A value with every word order: low word first for u64, i64, f64 and u128.
+*/
+type Wide = (u64, i64, f64, u128, String);
+
+fn wide() -> Wide {
+    (
+        0x0123_4567_89ab_cdef,
+        -2,
+        1.0,
+        0x0011_2233_4455_6677_8899_aabb_ccdd_eeff,
+        String::from("abcde"),
+    )
+}
+
+const WIDE_ARGS: [u32; 14] = [
+    0x89ab_cdef,
+    0x0123_4567,
+    0xffff_fffe,
+    0xffff_ffff,
+    0,
+    0x3ff0_0000,
+    0xccdd_eeff,
+    0x8899_aabb,
+    0x4455_6677,
+    0x0011_2233,
+    5,
+    0x6463_6261,
+    0x65,
+    // the len of the following Vec
+    0,
+];
+
+#[cfg(feature = "host")]
+#[test]
+fn host_writes_incoming_args() {
+    let mut args: Vec<u32> = Vec::new();
+    Incoming::args(&(wide(), Vec::<u8>::new()), &mut args).unwrap();
+    assert_eq!(args, WIDE_ARGS);
+}
+
+#[cfg(feature = "host")]
+#[test]
+fn host_reads_outcoming_args() {
+    let mut iter = WIDE_ARGS.iter();
+    let read = <(Wide, Vec<u8>)>::read(&[], &mut iter).unwrap();
+    assert_eq!(read, (wide(), Vec::new()));
+    assert_eq!(iter.len(), 0);
+    assert!(<(Wide, Vec<u8>)>::read(&[], &mut WIDE_ARGS[..13].iter()).is_err());
+}
+
+#[cfg(feature = "guest")]
+#[test]
+fn guest_writes_outcoming_args() {
+    let mut args: Vec<u32> = Vec::new();
+    Outcoming::args(&(wide(), Vec::<u8>::new()), &mut args).unwrap();
+    assert_eq!(args, WIDE_ARGS);
+}
+
+#[cfg(feature = "guest")]
+#[test]
+fn guest_inits_incoming_args() {
+    let mut args = WIDE_ARGS;
+    let mut iter = args.iter_mut();
+    let (_, init) = <(Wide, Vec<u8>)>::init(&mut iter).unwrap();
+    assert_eq!(init, (wide(), Vec::new()));
+    assert_eq!(iter.len(), 0);
+    assert!(<(Wide, Vec<u8>)>::init(&mut args[..13].iter_mut()).is_err());
+}
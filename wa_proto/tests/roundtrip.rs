@@ -0,0 +1,1348 @@
+//! host encode -> guest decode and guest encode -> host decode, needs both roles:
+//! `cargo test --features guest`, tests/roles.rs checks each role alone
+#![cfg(all(feature = "host", feature = "guest"))]
+
+use core::fmt::Debug;
+use wa_proto::{u128_into_args, u64_into_args, Incoming, Outcoming};
+
+fn host_to_guest<T: Incoming>(value: &T) -> T {
+    let mut args: Vec<u32> = Vec::new();
+    Incoming::args(value, &mut args).unwrap();
+    let mut iter = args.iter_mut();
+    let (_, decoded) = T::init(&mut iter).unwrap();
+    assert!(iter.next().is_none(), "not all args are read");
+    decoded
+}
+
+fn guest_to_host<T: Outcoming>(value: &T) -> T {
+    let mut args: Vec<u32> = Vec::new();
+    Outcoming::args(value, &mut args).unwrap();
+    let mut iter = args.iter();
+    let decoded = T::read(&[], &mut iter).unwrap();
+    assert!(iter.next().is_none(), "not all args are read");
+    decoded
+}
+
+fn assert_roundtrip<T: Incoming + Outcoming + PartialEq + Debug>(values: &[T]) {
+    for value in values {
+        assert_eq!(&host_to_guest(value), value);
+        assert_eq!(&guest_to_host(value), value);
+    }
+}
+
+fn args_of<T: Incoming + Outcoming>(value: &T) -> Vec<u32> {
+    let mut host: Vec<u32> = Vec::new();
+    Incoming::args(value, &mut host).unwrap();
+    let mut guest: Vec<u32> = Vec::new();
+    Outcoming::args(value, &mut guest).unwrap();
+    assert_eq!(host, guest);
+    assert_eq!(Incoming::size_hint(value), host.len());
+    assert_eq!(Outcoming::size_hint(value), guest.len());
+    host
+}
+
+#[test]
+fn word_order_is_low_word_first() {
+    assert_eq!(
+        args_of(&0x0123_4567_89ab_cdef_u64),
+        [0x89ab_cdef, 0x0123_4567]
+    );
+    assert_eq!(args_of(&-2_i64), [0xffff_fffe, 0xffff_ffff]);
+    assert_eq!(args_of(&1.0_f64), [0, 0x3ff0_0000]);
+    assert_eq!(
+        args_of(&0x0011_2233_4455_6677_8899_aabb_ccdd_eeff_u128),
+        [0xccdd_eeff, 0x8899_aabb, 0x4455_6677, 0x0011_2233]
+    );
+    assert_eq!(u64_into_args(1 << 32), [0, 1]);
+    assert_eq!(u128_into_args(1 << 96), [0, 0, 0, 1]);
+}
+
+#[test]
+fn integers() {
+    assert_roundtrip(&[0_u32, 1, u32::MAX]);
+    assert_roundtrip(&[0_i32, -1, i32::MIN, i32::MAX]);
+    assert_roundtrip(&[0_u64, 1, u32::MAX as u64, 1 << 32, u64::MAX - 1, u64::MAX]);
+    assert_roundtrip(&[
+        0_i64,
+        1,
+        -1,
+        0x1_0000_0000,
+        -0x1_0000_0000,
+        i64::MIN,
+        i64::MAX,
+    ]);
+    assert_roundtrip(&[0_u128, u64::MAX as u128, 1 << 64, 1 << 96, u128::MAX]);
+    assert_roundtrip(&[0_i128, -1, i64::MIN as i128, i128::MIN, i128::MAX]);
+}
+
+#[test]
+fn usize_is_one_arg() {
+    assert_roundtrip(&[0_usize, u32::MAX as usize]);
+    assert_roundtrip(&[0_isize, -1, i32::MIN as isize, i32::MAX as isize]);
+    assert_eq!(args_of(&-2_isize), [0xffff_fffe]);
+
+    #[cfg(target_pointer_width = "64")]
+    {
+        let mut args: Vec<u32> = Vec::new();
+        let error = Incoming::args(&(1_usize << 32), &mut args).unwrap_err();
+        assert_eq!(error.0, "usize beyond u32");
+        assert!(Outcoming::args(&(i32::MIN as isize - 1), &mut args).is_err());
+
+        // a len beyond u32 is refused on both sides, the items of () take no memory
+        let long = vec![(); 1 << 32];
+        let mut args: Vec<u32> = Vec::new();
+        assert_eq!(
+            Incoming::args(&long, &mut args).unwrap_err().0,
+            "usize beyond u32"
+        );
+        assert!(args.is_empty());
+        assert_eq!(
+            Outcoming::args(&long, &mut args).unwrap_err().0,
+            "usize beyond u32"
+        );
+    }
+}
+
+#[test]
+fn floats_keep_bits() {
+    let f64_bits = [
+        0_u64,
+        (-0.0_f64).to_bits(),
+        1,
+        f64::MIN_POSITIVE.to_bits(),
+        f64::MAX.to_bits(),
+        f64::INFINITY.to_bits(),
+        f64::NEG_INFINITY.to_bits(),
+        f64::NAN.to_bits(),
+        0x7ff0_0000_0000_0001,
+        0xfff8_0000_dead_beef,
+    ];
+    for bits in f64_bits {
+        let value = f64::from_bits(bits);
+        assert_eq!(host_to_guest(&value).to_bits(), bits);
+        assert_eq!(guest_to_host(&value).to_bits(), bits);
+    }
+
+    let f32_bits = [
+        0_u32,
+        (-0.0_f32).to_bits(),
+        1,
+        f32::NAN.to_bits(),
+        0x7f80_0001,
+    ];
+    for bits in f32_bits {
+        let value = f32::from_bits(bits);
+        assert_eq!(host_to_guest(&value).to_bits(), bits);
+        assert_eq!(guest_to_host(&value).to_bits(), bits);
+    }
+}
+
+#[test]
+fn nested_values() {
+    assert_roundtrip(&[(u64::MAX, -1_i64, String::from("abcde"))]);
+    assert_roundtrip(&[Some(vec![i64::MIN, i64::MAX]), None]);
+    assert_roundtrip(&[[u128::MAX, 0, 1]]);
+}
+
+#[test]
+fn size_hint_matches_args() {
+    assert_eq!(<u64 as Incoming>::FIXED_WORDS, Some(2));
+    assert_eq!(<(u32, [u128; 3], f64) as Outcoming>::FIXED_WORDS, Some(15));
+    assert_eq!(<(u32, String) as Incoming>::FIXED_WORDS, None);
+    args_of(&(1_u8, String::from("abcde"), vec![1_u64, 2, 3]));
+    args_of(&vec![
+        String::from("a"),
+        String::new(),
+        String::from("abcd"),
+    ]);
+    args_of(&Some([Some(1_i64), None]));
+    args_of(&Box::new((true, 2.5_f32)));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_matches_impls() {
+    let value = (
+        u64::MAX,
+        i64::MIN,
+        -1.5_f64,
+        u128::MAX - 7,
+        String::from("xyz"),
+    );
+    let mut args: Vec<u32> = Vec::new();
+    wa_proto::to_args(&value, &mut args).unwrap();
+    assert_eq!(args, args_of(&value));
+    let decoded: (u64, i64, f64, u128, String) = wa_proto::from_args(&args).unwrap();
+    assert_eq!(decoded, value);
+    args_of(&wa_proto::Serde::<_>::new(value));
+}
+
+#[derive(Incoming, Outcoming, wa_proto::Schema, Debug, PartialEq)]
+#[coming(packed)]
+struct Packed {
+    a: bool,
+    b: u8,
+    name: String,
+    c: i8,
+    d: u16,
+    e: bool,
+}
+
+#[test]
+fn packed_fields_share_args() {
+    let value = Packed {
+        a: true,
+        b: 0xab,
+        name: String::from("xyz"),
+        c: -1,
+        d: 0xffff,
+        e: true,
+    };
+    assert_roundtrip(&[value]);
+    let value = Packed {
+        a: true,
+        b: 0xab,
+        name: String::new(),
+        c: -1,
+        d: 0x1234,
+        e: false,
+    };
+    // a, b and c in the first arg, d and e don't fit and start the second one
+    assert_eq!(args_of(&value), [0x1_ff57, 0x1234, 0]);
+    assert_eq!(<Packed as Incoming>::FIXED_WORDS, None);
+    assert_eq!(wa_proto::packed_words(&[1, 8, 8, 16, 1]), 2);
+
+    let bytes =
+        wa_proto::to_bytes_with_version(&args_of(&value), wa_proto::PACKED_FORMAT_VERSION).unwrap();
+    assert!(wa_proto::from_bytes(&bytes).is_err());
+    let (version, args) = wa_proto::from_bytes_with_version(&bytes).unwrap();
+    assert_eq!(version, wa_proto::PACKED_FORMAT_VERSION);
+    assert_eq!(args, args_of(&value));
+
+    // the version follows the packed struct through the types around it
+    assert_eq!(
+        <Packed as wa_proto::Schema>::FORMAT_VERSION,
+        wa_proto::PACKED_FORMAT_VERSION
+    );
+    assert_eq!(
+        <Vec<(u8, Option<Packed>)> as wa_proto::Schema>::FORMAT_VERSION,
+        wa_proto::PACKED_FORMAT_VERSION
+    );
+    assert_eq!(
+        <(u8, String) as wa_proto::Schema>::FORMAT_VERSION,
+        wa_proto::FORMAT_VERSION
+    );
+    assert_eq!(wa_proto::to_bytes_of::<Packed>(&args).unwrap(), bytes);
+    assert_eq!(wa_proto::from_bytes_of::<Packed>(&bytes).unwrap(), args);
+    let unpacked = wa_proto::to_bytes(&args).unwrap();
+    assert!(wa_proto::from_bytes_of::<Packed>(&unpacked).is_err());
+    assert!(wa_proto::from_bytes_of::<(u8, String)>(&bytes).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn compact_is_smaller() {
+    let value = (
+        3_u32,
+        -2_i64,
+        u64::MAX,
+        Some(String::from("xyz")),
+        vec![1_u16, 300],
+        1.5_f32,
+        i128::MIN,
+    );
+    let bytes = wa_proto::to_compact(&value).unwrap();
+    assert_eq!(&bytes[..2], [3, 3]);
+    let decoded: (u32, i64, u64, Option<String>, Vec<u16>, f32, i128) =
+        wa_proto::from_compact(&bytes).unwrap();
+    assert_eq!(decoded, value);
+
+    let mut args: Vec<u32> = Vec::new();
+    wa_proto::to_args(&value, &mut args).unwrap();
+    assert!(bytes.len() < args.len() * 4);
+
+    let compact: wa_proto::Compact<_> = wa_proto::Compact::new(value);
+    assert_eq!(args_of(&compact)[0] as usize, bytes.len());
+    assert_eq!(host_to_guest(&compact), compact);
+    assert_eq!(guest_to_host(&compact), compact);
+
+    // 2^32 doesn't fit into a u32
+    assert!(wa_proto::from_compact::<u32>(&[0x80, 0x80, 0x80, 0x80, 0x10]).is_err());
+    assert_eq!(
+        wa_proto::from_compact::<u32>(&[0xff, 0xff, 0xff, 0xff, 0x0f]).unwrap(),
+        u32::MAX
+    );
+}
+
+#[allow(dead_code)]
+mod v1 {
+    #[derive(wa_proto::Schema)]
+    pub struct Item {
+        pub price: u32,
+    }
+
+    #[derive(wa_proto::Schema)]
+    pub struct Order {
+        pub id: u64,
+        pub items: Vec<Item>,
+    }
+
+    #[derive(
+        wa_proto::Incoming,
+        wa_proto::Outcoming,
+        wa_proto::Schema,
+        wa_proto::PackBits,
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+    )]
+    pub enum Level {
+        #[coming(other)]
+        Unknown,
+        Low,
+        High,
+    }
+
+    impl Level {
+        pub fn from_u32(val: u32) -> Option<Self> {
+            match val {
+                0 => Some(Level::Unknown),
+                1 => Some(Level::Low),
+                2 => Some(Level::High),
+                _ => None,
+            }
+        }
+    }
+
+    #[derive(wa_proto::Incoming, wa_proto::Outcoming, wa_proto::Schema, Debug, PartialEq)]
+    #[coming(framed)]
+    pub struct Event {
+        pub id: u64,
+        pub level: Level,
+    }
+}
+
+#[allow(dead_code)]
+mod v2 {
+    #[derive(wa_proto::Schema)]
+    pub struct Item {
+        pub price: u64,
+    }
+
+    #[derive(wa_proto::Schema)]
+    pub struct Order {
+        pub id: u64,
+        pub items: Vec<Item>,
+        pub note: Option<String>,
+    }
+
+    #[derive(
+        wa_proto::Incoming, wa_proto::Outcoming, wa_proto::Schema, Clone, Copy, Debug, PartialEq,
+    )]
+    pub enum Level {
+        #[coming(other)]
+        Unknown,
+        Low,
+        High,
+        Critical,
+    }
+
+    impl Level {
+        pub fn from_u32(val: u32) -> Option<Self> {
+            match val {
+                0 => Some(Level::Unknown),
+                1 => Some(Level::Low),
+                2 => Some(Level::High),
+                3 => Some(Level::Critical),
+                _ => None,
+            }
+        }
+    }
+
+    #[derive(wa_proto::Incoming, wa_proto::Outcoming, wa_proto::Schema, Debug, PartialEq)]
+    #[coming(framed)]
+    pub struct Event {
+        pub id: u64,
+        pub level: Level,
+        #[coming(since = 2)]
+        pub retries: u32,
+    }
+}
+
+#[test]
+fn schema_mismatch_has_diff() {
+    use wa_proto::{check_schema, schema_args, schema_args_hash, Schema, SchemaTree};
+
+    let mut guest: Vec<u32> = Vec::new();
+    schema_args(v1::Order::SCHEMA, &mut guest).unwrap();
+    assert_eq!(schema_args_hash(&guest), v1::Order::SCHEMA_HASH);
+    assert_ne!(v1::Order::SCHEMA_HASH, v2::Order::SCHEMA_HASH);
+    assert_eq!(
+        <(u32, [bool; 2])>::SCHEMA_HASH,
+        SchemaTree::from(<(u32, [bool; 2])>::SCHEMA).hash()
+    );
+
+    let tree = SchemaTree::from_args(&guest).unwrap();
+    assert_eq!(tree, SchemaTree::from(v1::Order::SCHEMA));
+    assert_eq!(
+        tree.to_string(),
+        "Order { id: u64, items: Vec<Item { price: u32 }> }"
+    );
+
+    assert!(check_schema(v1::Order::SCHEMA, &guest).is_ok());
+    let error = check_schema(v2::Order::SCHEMA, &guest).unwrap_err();
+    assert_eq!(
+        error.0,
+        "schema mismatch: Order.items[].price: host u64, guest u32; \
+         Order.note: field Option only on the host"
+    );
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn schema_len_beyond_u32() {
+    use wa_proto::{schema_args, Schema, SchemaTree, TypeSchema};
+
+    // the len of an array doesn't fit in an arg on either writer of the schema
+    static LONG: TypeSchema = TypeSchema::Array(<u8 as Schema>::SCHEMA, 1 << 32);
+    let mut args: Vec<u32> = Vec::new();
+    assert_eq!(
+        schema_args(&LONG, &mut args).unwrap_err().0,
+        "usize beyond u32"
+    );
+    let mut args: Vec<u32> = Vec::new();
+    assert_eq!(
+        SchemaTree::from(&LONG).args(&mut args).unwrap_err().0,
+        "usize beyond u32"
+    );
+}
+
+#[test]
+fn framed_versions_read_each_other() {
+    use wa_proto::{check_schema, schema_args, Schema};
+
+    let old = v1::Event {
+        id: 7,
+        level: v1::Level::High,
+    };
+    let new = v2::Event {
+        id: 7,
+        level: v2::Level::Critical,
+        retries: 3,
+    };
+    assert_roundtrip(core::slice::from_ref(&new));
+    // another version sends another count of args, so the size isn't fixed
+    assert_eq!(<v2::Event as Incoming>::FIXED_WORDS, None);
+    assert_eq!(<v2::Event as Outcoming>::FIXED_WORDS, None);
+
+    // the frame is the count of the args after it, new variants are read as Unknown
+    let mut args = args_of(&new);
+    assert_eq!(args, [4, 7, 0, 3, 3]);
+    let decoded = v1::Event::read(&[], &mut args.iter()).unwrap();
+    assert_eq!(decoded.level, v1::Level::Unknown);
+    let mut iter = args.iter_mut();
+    let (_, decoded) = v1::Event::init(&mut iter).unwrap();
+    assert!(iter.next().is_none(), "the unknown field is skipped");
+    assert_eq!(decoded.id, 7);
+
+    let mut args = args_of(&old);
+    let decoded = v2::Event::read(&[], &mut args.iter()).unwrap();
+    assert_eq!(decoded.retries, 0);
+    let mut iter = args.iter_mut();
+    let (_, decoded) = v2::Event::init(&mut iter).unwrap();
+    assert_eq!(decoded.level, v2::Level::High);
+
+    // a frame longer than the args
+    assert!(v1::Event::read(&[], &mut [9, 7, 0, 2].iter()).is_err());
+
+    // items of a newer version inside a Vec: skip and the views follow the frames
+    use wa_proto::{read_ref, SeqView};
+    let args = args_of(&(vec![v2::Event { retries: 1, ..new }, new], 77_u32));
+    let mut iter = args.iter();
+    <Vec<v1::Event>>::skip(&mut iter).unwrap();
+    assert_eq!(iter.as_slice(), [77]);
+    let view: SeqView<v1::Event> = read_ref(&[], &args[..args.len() - 1]).unwrap();
+    assert_eq!(view.len(), 2);
+    let decoded = view.get(1).unwrap().unwrap();
+    assert_eq!((decoded.id, decoded.level), (7, v1::Level::Unknown));
+    let decoded = <(Vec<v1::Event>, u32)>::read(&[], &mut args.iter()).unwrap();
+    assert_eq!((decoded.0.len(), decoded.1), (2, 77));
+
+    let mut guest: Vec<u32> = Vec::new();
+    schema_args(v1::Event::SCHEMA, &mut guest).unwrap();
+    assert_ne!(v1::Event::SCHEMA_HASH, v2::Event::SCHEMA_HASH);
+    assert!(check_schema(v2::Event::SCHEMA, &guest).is_ok());
+    let mut guest: Vec<u32> = Vec::new();
+    schema_args(v2::Event::SCHEMA, &mut guest).unwrap();
+    assert!(check_schema(v1::Event::SCHEMA, &guest).is_ok());
+}
+
+#[test]
+fn handshake_checks_messages() {
+    use wa_proto::{Handshake, Message, FORMAT_VERSION, LEGACY_TIME_FLAG};
+
+    static GUEST: Handshake = Handshake::new(&[
+        Message::of::<v1::Event>("event"),
+        Message::of::<v1::Order>("order"),
+        Message::of::<u32>("count"),
+    ]);
+    static HOST: Handshake = Handshake::new(&[
+        Message::of::<v2::Event>("event"),
+        Message::of::<v2::Order>("order"),
+    ]);
+    let mut guest: Vec<u32> = Vec::new();
+    GUEST.args(&mut guest).unwrap();
+    assert_eq!(guest[0], FORMAT_VERSION as u32);
+
+    // the guest speaks more messages than the host uses
+    assert_eq!(GUEST.check(&guest).unwrap(), GUEST.flags);
+    let error = HOST.check(&guest).unwrap_err();
+    assert_eq!(
+        error.0,
+        "handshake: order: Order.items[].price: host u64, guest u32; \
+         order: Order.note: field Option only on the host"
+    );
+
+    let mut other: Vec<u32> = Vec::new();
+    Handshake {
+        flags: GUEST.flags ^ LEGACY_TIME_FLAG,
+        ..Handshake::new(&[])
+    }
+    .args(&mut other)
+    .unwrap();
+    // the host adapts to the time layout of the guest, the messages still have to match
+    assert_eq!(
+        GUEST.check(&other).unwrap_err().0,
+        "handshake: event: message only on the host; order: message only on the host; \
+         count: message only on the host"
+    );
+    assert_eq!(
+        Handshake::new(&[]).check(&other).unwrap(),
+        GUEST.flags ^ LEGACY_TIME_FLAG
+    );
+
+    // flags of a newer version
+    other[1] |= 4;
+    assert!(GUEST
+        .check(&other)
+        .unwrap_err()
+        .0
+        .contains("unknown flags: 0x4"));
+
+    // a count of messages beyond the args isn't collected
+    let mut many = guest.clone();
+    many[2] = u32::MAX;
+    assert_eq!(GUEST.check(&many).unwrap_err().0, "args next error");
+
+    other[0] = FORMAT_VERSION as u32 + 1;
+    assert_eq!(
+        GUEST.check(&other).unwrap_err().0,
+        format!(
+            "handshake: format version: host {}, guest {}",
+            FORMAT_VERSION,
+            FORMAT_VERSION + 1
+        )
+    );
+}
+
+#[test]
+fn value_reencodes_args() {
+    use core::time::Duration;
+    use wa_proto::{Schema, SchemaTree, Value};
+
+    fn reencode(schema: &SchemaTree, args: &[u32]) -> Value {
+        let mut iter = args.iter();
+        let value = Value::from_args(schema, &mut iter).unwrap();
+        assert!(iter.next().is_none(), "not all args are read");
+        let mut again: Vec<u32> = Vec::new();
+        value.args(schema, &mut again).unwrap();
+        assert_eq!(again, args);
+        value
+    }
+    fn field(name: &str, value: Value) -> (String, Value) {
+        (String::from(name), value)
+    }
+
+    let value = (
+        u64::MAX,
+        Some(String::from("xyz")),
+        vec![(-3_i32, 1.5_f64)],
+        Duration::new(5, 7),
+    );
+    let schema = SchemaTree::from(<(u64, Option<String>, Vec<(i32, f64)>, Duration)>::SCHEMA);
+    assert_eq!(
+        reencode(&schema, &args_of(&value)),
+        Value::List(vec![
+            Value::UInt(u64::MAX as u128),
+            Value::Option(Some(Box::new(Value::String(String::from("xyz"))))),
+            Value::List(vec![Value::List(vec![Value::Int(-3), Value::Float(1.5)])]),
+            Value::Record(vec![
+                field("secs", Value::UInt(5)),
+                field("nanos", Value::UInt(7))
+            ]),
+        ])
+    );
+
+    let packed = Packed {
+        a: true,
+        b: 0xab,
+        name: String::from("n"),
+        c: -1,
+        d: 0x1234,
+        e: false,
+    };
+    let value = reencode(&SchemaTree::from(Packed::SCHEMA), &args_of(&packed));
+    assert_eq!(
+        value,
+        Value::Record(vec![
+            field("a", Value::Bool(true)),
+            field("b", Value::UInt(0xab)),
+            field("name", Value::String(String::from("n"))),
+            field("c", Value::Int(-1)),
+            field("d", Value::UInt(0x1234)),
+            field("e", Value::Bool(false)),
+        ])
+    );
+
+    // a newer message read with the older schema
+    let new = v2::Event {
+        id: 7,
+        level: v2::Level::Critical,
+        retries: 3,
+    };
+    let args = args_of(&new);
+    reencode(&SchemaTree::from(v2::Event::SCHEMA), &args);
+    let value = Value::from_args(&SchemaTree::from(v1::Event::SCHEMA), &mut args.iter()).unwrap();
+    assert_eq!(
+        value,
+        Value::Record(vec![
+            field("id", Value::UInt(7)),
+            field("level", Value::Variant(0, String::from("Unknown"), None)),
+        ])
+    );
+    let mut old: Vec<u32> = Vec::new();
+    value
+        .args(&SchemaTree::from(v2::Event::SCHEMA), &mut old)
+        .unwrap();
+    assert_eq!(old, [3, 7, 0, 0]);
+    assert!(value
+        .args(&SchemaTree::from(Packed::SCHEMA), &mut old)
+        .is_err());
+}
+
+#[test]
+fn value_refuses_what_the_type_would_truncate() {
+    use core::time::Duration;
+    use wa_proto::{Schema, SchemaTree, Value};
+
+    fn from_args<T: Schema>(args: &[u32]) -> Result<Value, String> {
+        Value::from_args(&SchemaTree::from(T::SCHEMA), &mut args.iter()).map_err(|error| error.0)
+    }
+    fn args<T: Schema>(value: Value) -> Result<Vec<u32>, String> {
+        let mut args: Vec<u32> = Vec::new();
+        value
+            .args(&SchemaTree::from(T::SCHEMA), &mut args)
+            .map_err(|error| error.0)?;
+        Ok(args)
+    }
+
+    assert_eq!(from_args::<u8>(&[300]).unwrap_err(), "schema mismatch");
+    assert_eq!(from_args::<u8>(&[255]).unwrap(), Value::UInt(255));
+    assert_eq!(args::<u8>(Value::UInt(300)).unwrap_err(), "schema mismatch");
+    assert_eq!(
+        args::<u16>(Value::UInt(1 << 16)).unwrap_err(),
+        "schema mismatch"
+    );
+    assert_eq!(args::<i8>(Value::Int(-129)).unwrap_err(), "schema mismatch");
+    assert_eq!(args::<i8>(Value::Int(-1)).unwrap(), [u32::MAX]);
+    assert_eq!(from_args::<i8>(&[200]).unwrap_err(), "schema mismatch");
+    assert!(args::<u32>(Value::UInt(1 << 32)).is_err());
+    assert!(args::<i64>(Value::Int(i64::MAX as i128 + 1)).is_err());
+    assert!(args::<u32>(Value::Int(1)).is_err());
+
+    // bools and Option tags other than 0 and 1, like the dump
+    assert!(from_args::<bool>(&[2]).is_err());
+    assert!(from_args::<Option<u32>>(&[2, 7]).is_err());
+
+    let nanos = Value::Record(vec![
+        (String::from("secs"), Value::UInt(1)),
+        (String::from("nanos"), Value::UInt(1_000_000_000)),
+    ]);
+    assert_eq!(args::<Duration>(nanos).unwrap_err(), "time from u32 error");
+    assert_eq!(
+        from_args::<Duration>(&[1, 0, 1_000_000_000]).unwrap_err(),
+        "time from u32 error"
+    );
+
+    // a len beyond the args left isn't collected
+    assert_eq!(
+        from_args::<Vec<u32>>(&[u32::MAX, 1]).unwrap_err(),
+        "args next error"
+    );
+
+    // the packed fields
+    let packed = Value::Record(vec![
+        (String::from("a"), Value::Bool(true)),
+        (String::from("b"), Value::UInt(0x100)),
+        (String::from("name"), Value::String(String::new())),
+        (String::from("c"), Value::Int(-1)),
+        (String::from("d"), Value::UInt(1)),
+        (String::from("e"), Value::Bool(false)),
+    ]);
+    assert_eq!(args::<Packed>(packed).unwrap_err(), "schema mismatch");
+
+    // a packed variant beyond the variants isn't masked into another one
+    let flags: SchemaTree =
+        "#[coming(packed)] Flags { on: bool, #[coming(pack)] level: Level { A, B, C } }"
+            .parse()
+            .unwrap();
+    let record = |tag, name: &str| {
+        Value::Record(vec![
+            (String::from("on"), Value::Bool(true)),
+            (
+                String::from("level"),
+                Value::Variant(tag, String::from(name), None),
+            ),
+        ])
+    };
+    let mut args: Vec<u32> = Vec::new();
+    record(2, "C").args(&flags, &mut args).unwrap();
+    assert_eq!(args, [0b101]);
+    assert_eq!(
+        record(3, "D").args(&flags, &mut args).unwrap_err().0,
+        "enum from u32 error"
+    );
+}
+
+#[test]
+fn value_reads_items_without_args() {
+    use wa_proto::{Schema, SchemaTree, Value};
+
+    #[derive(Outcoming, wa_proto::Schema, Debug, PartialEq)]
+    struct Empty {
+        none: [u32; 0],
+    }
+
+    fn from_args<T: Schema>(args: &[u32]) -> Result<Value, String> {
+        Value::from_args(&SchemaTree::from(T::SCHEMA), &mut args.iter()).map_err(|error| error.0)
+    }
+
+    // the typed read takes any len of items without args, so does Value
+    let args = [3, 7];
+    assert_eq!(
+        <(Vec<()>, u32)>::read(&[], &mut args.iter()).unwrap(),
+        (vec![(); 3], 7)
+    );
+    assert_eq!(
+        from_args::<(Vec<()>, u32)>(&args).unwrap(),
+        Value::List(vec![
+            Value::List(vec![Value::List(Vec::new()); 3]),
+            Value::UInt(7)
+        ])
+    );
+    assert_eq!(
+        from_args::<Vec<[u64; 0]>>(&[5]).unwrap(),
+        Value::List(vec![Value::List(Vec::new()); 5])
+    );
+    assert_eq!(
+        Vec::<Empty>::read(&[], &mut [2].iter()).unwrap(),
+        [Empty { none: [] }, Empty { none: [] }]
+    );
+    assert_eq!(
+        from_args::<Vec<Empty>>(&[2]).unwrap(),
+        Value::List(vec![
+            Value::Record(vec![(
+                String::from("none"),
+                Value::List(Vec::new())
+            )]);
+            2
+        ])
+    );
+
+    // items with args are still bounded by the args left, by their least width
+    assert_eq!(
+        from_args::<Vec<u64>>(&[2, 1, 0, 2]).unwrap_err(),
+        "args next error"
+    );
+    assert!(from_args::<Vec<(u32, Option<u8>)>>(&[3, 1, 0, 2, 0, 3]).is_err());
+}
+
+#[test]
+fn dynamic_carries_its_schema() {
+    use wa_proto::{Dynamic, Schema, SchemaTree, Value};
+
+    let schema = SchemaTree::from(<(u64, Option<String>)>::SCHEMA);
+    let value = Value::List(vec![
+        Value::UInt(7),
+        Value::Option(Some(Box::new(Value::String(String::from("abc"))))),
+    ]);
+    let dynamic = Dynamic::from((schema.clone(), value.clone()));
+    assert_roundtrip(core::slice::from_ref(&dynamic));
+
+    // the schema args, then the args of the value
+    let args = args_of(&dynamic);
+    let mut schema_args: Vec<u32> = Vec::new();
+    schema.args(&mut schema_args).unwrap();
+    let mut value_args: Vec<u32> = Vec::new();
+    value.args(&schema, &mut value_args).unwrap();
+    assert_eq!(value_args, args_of(&(7_u64, Some(String::from("abc")))));
+    assert_eq!(args, [schema_args, value_args].concat());
+
+    // a value that doesn't match its schema isn't written
+    let wrong = Dynamic(schema, Value::Bool(true));
+    assert!(Outcoming::args(&wrong, &mut Vec::new()).is_err());
+}
+
+#[test]
+fn dump_marks_the_mismatch() {
+    use std::collections::BTreeMap;
+    use wa_proto::{dump_args, ArgsDump, Schema, SchemaTree};
+
+    let value = (String::from("abc"), Some(7_u64));
+    let args = args_of(&value);
+    let dump = "0: len=3 | 0: \"abc\" packed 0x00636261 | 1: Some \
+                | 1?: u64 7 (lo 0x00000007, hi 0x00000000)";
+    assert_eq!(
+        format!("{:?}", ArgsDump::of::<(String, Option<u64>)>(&args)),
+        dump
+    );
+
+    let schema = SchemaTree::from(<(String, Option<u64>)>::SCHEMA);
+    assert_eq!(
+        dump_args(&schema, &args[..4]),
+        "0: len=3 | 0: \"abc\" packed 0x00636261 | 1: Some \
+         | 1?: !! expected u64, the args ended"
+    );
+    assert_eq!(
+        dump_args(&schema, &[1, 0xff, 2]),
+        "0: len=1 | 0: !! invalid utf-8 in 0x000000ff"
+    );
+    assert!(dump_args(&schema, &[0, 0, 5]).ends_with("!! 1 trailing args: 0x00000005"));
+
+    let new = v2::Event {
+        id: 7,
+        level: v2::Level::Critical,
+        retries: 3,
+    };
+    assert_eq!(
+        format!("{:?}", ArgsDump::of::<v1::Event>(&args_of(&new))),
+        "Event: frame=4 | Event.id: u64 7 (lo 0x00000007, hi 0x00000000) \
+         | Event.level: tag=3 unknown, read as Unknown | Event: skipped 1 unknown args"
+    );
+    assert_eq!(
+        format!("{:?}", ArgsDump::of::<v1::Event>(&[5, 7])),
+        "Event: !! frame=5 is longer than the 1 args left"
+    );
+
+    // values the typed read would truncate
+    assert_eq!(
+        format!("{:?}", ArgsDump::of::<(u8, u32)>(&[300, 1])),
+        "0: !! u8 300 out of range"
+    );
+    assert_eq!(
+        format!("{:?}", ArgsDump::of::<i8>(&[200])),
+        "!! i8 200 out of range"
+    );
+    assert_eq!(
+        format!("{:?}", ArgsDump::of::<i8>(&[-128_i32 as u32])),
+        "i8 -128"
+    );
+    assert_eq!(
+        format!(
+            "{:?}",
+            ArgsDump::of::<core::time::Duration>(&[1, 0, 1_000_000_000])
+        ),
+        "!! Duration 1s 1000000000ns, nanos out of range"
+    );
+    assert_eq!(
+        format!("{:?}", ArgsDump::of::<Option<u8>>(&[2, 1])),
+        "!! Option tag 2"
+    );
+
+    // a packed tag out of the variants stops the dump
+    #[derive(wa_proto::Schema)]
+    #[coming(packed)]
+    #[allow(dead_code)]
+    struct Flags {
+        on: bool,
+        #[coming(pack)]
+        level: v1::Level,
+        count: u16,
+    }
+    assert_eq!(
+        format!("{:?}", ArgsDump::of::<Flags>(&[1 | 2 << 1 | 3 << 3])),
+        "Flags: packed 0x0000001d: on true, level High, count 3"
+    );
+    assert_eq!(
+        format!("{:?}", ArgsDump::of::<Flags>(&[1 | 3 << 1 | 3 << 3])),
+        "Flags: !! packed 0x0000001f: on true, level tag=3 out of the 3 variants"
+    );
+
+    // a len beyond the args left is marked, items without args are dumped once
+    assert_eq!(
+        format!("{:?}", ArgsDump::of::<Vec<u64>>(&[2, 1, 0, 2])),
+        "!! len=2, more items than the 3 args left"
+    );
+    assert_eq!(
+        format!("{:?}", ArgsDump::of::<BTreeMap<u8, u8>>(&[u32::MAX, 1])),
+        "!! len=4294967295, more items than the 1 args left"
+    );
+    assert_eq!(
+        format!("{:?}", ArgsDump::of::<(Vec<()>, u8)>(&[u32::MAX, 7])),
+        "0: len=4294967295, the same items without args | 1: u8 7"
+    );
+}
+
+#[test]
+fn schema_tree_refuses_broken_args() {
+    use wa_proto::{SchemaTree, SCHEMA_MAX_DEPTH};
+
+    // Option<Option<...<u8>>> nested deeper than the limit
+    let mut deep: Vec<u32> = vec![2; SCHEMA_MAX_DEPTH];
+    deep.extend([0, 2, 0x3875]);
+    assert!(SchemaTree::from_args(&deep).is_err());
+    assert!(SchemaTree::from_args(&deep[1..]).is_ok());
+
+    // a name or a count beyond the args isn't allocated
+    assert!(SchemaTree::from_args(&[0, u32::MAX]).is_err());
+    assert!(SchemaTree::from_args(&[0, 5, 0x6463_6261]).is_err());
+    assert!(SchemaTree::from_args(&[6, u32::MAX, 0]).is_err());
+    assert!(SchemaTree::from_args(&[7, 1, 0x41, 0, 0, u32::MAX]).is_err());
+
+    #[cfg(feature = "serde")]
+    {
+        use wa_proto::{schema_args, Compact, Schema, Serde};
+
+        // the codec and the type of the payload are in the schema
+        let tree = SchemaTree::from(<Serde<(u32, String)>>::SCHEMA);
+        assert_eq!(tree.to_string(), "Serde<args, (u32, String)>");
+        assert_eq!(tree.to_string().parse::<SchemaTree>().unwrap(), tree);
+        assert_ne!(<Serde<u32>>::SCHEMA_HASH, <Compact<u32>>::SCHEMA_HASH);
+        assert_ne!(<Serde<u32>>::SCHEMA_HASH, <Serde<u64>>::SCHEMA_HASH);
+        let mut args: Vec<u32> = Vec::new();
+        schema_args(<Compact<Vec<u8>>>::SCHEMA, &mut args).unwrap();
+        let error = wa_proto::check_schema(<Compact<Vec<u16>>>::SCHEMA, &args).unwrap_err();
+        assert_eq!(error.0, "schema mismatch: []: host u16, guest u8");
+    }
+}
+
+#[test]
+fn schema_text_parses_display() {
+    use wa_proto::{Schema, SchemaTree};
+
+    #[derive(wa_proto::Schema)]
+    #[coming(packed)]
+    #[allow(dead_code)]
+    struct Flags {
+        on: bool,
+        #[coming(pack)]
+        level: v1::Level,
+        count: u16,
+        items: [(u32, Option<String>); 2],
+    }
+
+    for tree in [
+        SchemaTree::from(Flags::SCHEMA),
+        SchemaTree::from(v2::Event::SCHEMA),
+        SchemaTree::from(v2::Order::SCHEMA),
+        SchemaTree::from(
+            <(
+                core::time::Duration,
+                (),
+                (i64,),
+                std::collections::BTreeMap<u8, Vec<f64>>,
+            )>::SCHEMA,
+        ),
+    ] {
+        let text = tree.to_string();
+        assert_eq!(text.parse::<SchemaTree>().unwrap(), tree, "{}", text);
+    }
+    assert_eq!(
+        SchemaTree::from(Flags::SCHEMA).to_string(),
+        "#[coming(packed)] Flags { on: bool, #[coming(pack)] level: Level { #[coming(other)] Unknown, \
+         Low, High }, count: u16, items: [(u32, Option<String>); 2] }"
+    );
+    let alias: SchemaTree = "Event { at: DateTime < Utc > as (i64, u32) }"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        alias.to_string(),
+        "Event { at: DateTime < Utc > as (i64, u32) }"
+    );
+    assert!("Event { at: }".parse::<SchemaTree>().is_err());
+    assert!("Vec<u32".parse::<SchemaTree>().is_err());
+}
+
+#[test]
+fn validate_and_skip_without_reading() {
+    use std::collections::BTreeMap;
+    use wa_proto::validate_args;
+
+    type Message = (
+        Vec<String>,
+        Option<[i32; 2]>,
+        BTreeMap<u8, String>,
+        v2::Event,
+        Packed,
+    );
+    let value: Message = (
+        vec!["é".repeat(40), String::new(), "abc".to_string()],
+        Some([1, 2]),
+        BTreeMap::from([(1, "one".to_string()), (2, "two".to_string())]),
+        v2::Event {
+            id: 7,
+            level: v2::Level::High,
+            retries: 3,
+        },
+        Packed {
+            a: true,
+            b: 200,
+            name: "packed".to_string(),
+            c: -3,
+            d: 65000,
+            e: false,
+        },
+    );
+    let mut args: Vec<u32> = Vec::new();
+    Outcoming::args(&value, &mut args).unwrap();
+    validate_args::<Message>(&[], &args).unwrap();
+    let mut iter = args.iter();
+    Message::skip(&mut iter).unwrap();
+    assert!(iter.next().is_none());
+
+    // skip the value that isn't needed and read the next one
+    let pair = (value.0.clone(), 42u32);
+    let mut args: Vec<u32> = Vec::new();
+    Outcoming::args(&pair, &mut args).unwrap();
+    let mut iter = args.iter();
+    <Vec<String>>::skip(&mut iter).unwrap();
+    assert_eq!(u32::read(&[], &mut iter).unwrap(), 42);
+
+    // the framed versions accept each other
+    let mut args: Vec<u32> = Vec::new();
+    Outcoming::args(&value.3, &mut args).unwrap();
+    validate_args::<v1::Event>(&[], &args).unwrap();
+    let mut iter = args.iter();
+    v1::Event::skip(&mut iter).unwrap();
+    assert!(iter.next().is_none());
+
+    // chars split between the words and the broken bytes in the middle of a long string
+    let text = "€".repeat(30);
+    let mut args: Vec<u32> = Vec::new();
+    Outcoming::args(&text, &mut args).unwrap();
+    validate_args::<String>(&[], &args).unwrap();
+    args[12] = 0xffff_ffff;
+    assert!(validate_args::<String>(&[], &args).is_err());
+    assert!(String::read(&[], &mut args.iter()).is_err());
+    <String>::skip(&mut args.iter()).unwrap();
+
+    // a len longer than the args fails before the loop over the items
+    assert_eq!(
+        validate_args::<Vec<u64>>(&[], &[u32::MAX]).unwrap_err().0,
+        "args next error"
+    );
+    assert!(validate_args::<Vec<String>>(&[], &[u32::MAX, 0]).is_err());
+    assert_eq!(
+        validate_args::<Option<u8>>(&[], &[1, 5, 6]).unwrap_err().0,
+        "trailing args"
+    );
+}
+
+#[derive(Outcoming, Debug, PartialEq)]
+struct Page<'a> {
+    id: u32,
+    title: &'a str,
+    body: &'a [u8],
+    tags: Vec<&'a str>,
+    note: Option<&'a str>,
+    owner: String,
+    level: v1::Level,
+}
+
+#[derive(Outcoming, Debug, PartialEq)]
+struct Pair<'a>(&'a str, Page<'a>);
+
+#[test]
+fn borrowed_fields_point_into_the_heap() {
+    use wa_proto::{read_ref, OutcomingRef};
+
+    // the memory of the guest, the args have the len and the pointer of the bytes
+    let heap = b"..title....body\xffbad".to_vec();
+    let mut args: Vec<u32> = vec![7, 5, 2, 4, 11, 2, 5, 2, 2, 11, 0];
+    Outcoming::args(&"owner".to_string(), &mut args).unwrap();
+    Outcoming::args(&v1::Level::High, &mut args).unwrap();
+    let page: Page = read_ref(&heap, &args).unwrap();
+    assert_eq!(
+        page,
+        Page {
+            id: 7,
+            title: "title",
+            body: b"body",
+            tags: vec!["title", "bo"],
+            note: None,
+            owner: "owner".to_string(),
+            level: v1::Level::High,
+        }
+    );
+    assert!(core::ptr::eq(page.title.as_ptr(), &heap[2]));
+    assert!(core::ptr::eq(page.body.as_ptr(), &heap[11]));
+    assert_eq!(OutcomingRef::size_hint(&page), args.len());
+
+    let mut pair_args = vec![3, 16];
+    pair_args.extend_from_slice(&args);
+    let pair: Pair = read_ref(&heap, &pair_args).unwrap();
+    assert_eq!(pair.0, "bad");
+    assert_eq!(pair.1, page);
+
+    // out of the heap and not utf-8
+    assert_eq!(
+        read_ref::<&str>(&heap, &[4, heap.len() as u32 - 2])
+            .unwrap_err()
+            .0,
+        "slice len error"
+    );
+    assert!(read_ref::<&str>(&heap, &[4, 15]).is_err());
+    assert_eq!(read_ref::<&[u8]>(&heap, &[4, 15]).unwrap(), b"\xffbad");
+
+    // the value borrows only the heap, the args may go first
+    let title: &str = {
+        let args = vec![5, 2];
+        read_ref(&heap, &args).unwrap()
+    };
+    assert_eq!(title, "title");
+}
+
+#[test]
+fn borrowed_values_in_containers() {
+    use core::time::Duration;
+    use std::collections::{BTreeMap, HashMap};
+    use wa_proto::{dump_args, read_ref, OutcomingRef, Schema, SchemaTree};
+
+    // read and count the args again, the pointers are offsets in the heap like in the guest
+    fn read_all<'a, T: OutcomingRef<'a, 'a>>(heap: &'a [u8], args: &'a [u32]) -> T {
+        let value: T = read_ref(heap, args).unwrap();
+        assert_eq!(OutcomingRef::size_hint(&value), args.len());
+        value
+    }
+
+    let heap = b"..title....body".to_vec();
+    let tuple_args = [5, 2, 7, 1, 4, 11];
+    let tuple: (&str, u32, Option<&[u8]>) = read_all(&heap, &tuple_args);
+    assert_eq!(tuple, ("title", 7, Some(&b"body"[..])));
+    assert!(core::ptr::eq(tuple.0.as_ptr(), &heap[2]));
+
+    let array: [&str; 2] = read_all(&heap, &[5, 2, 4, 11]);
+    assert_eq!(array, ["title", "body"]);
+    assert!(read_ref::<[&str; 2]>(&heap, &[5, 2, 4]).is_err());
+
+    let boxed: Box<&[u8]> = read_all(&heap, &[4, 11]);
+    assert_eq!(*boxed, b"body");
+
+    let map_args = [2, 5, 2, 1, 0, 4, 11, 2, 0];
+    let map: HashMap<&str, u64> = read_all(&heap, &map_args);
+    assert_eq!(map, HashMap::from([("title", 1), ("body", 2)]));
+    let tree: BTreeMap<u8, &[u8]> = read_all(&heap, &[2, 2, 4, 11, 1, 5, 2]);
+    assert_eq!(
+        tree,
+        BTreeMap::from([(1, &b"title"[..]), (2, &b"body"[..])])
+    );
+    // the same key twice and a len beyond the args
+    assert_eq!(
+        read_ref::<HashMap<&str, u64>>(&heap, &[2, 5, 2, 0, 0, 5, 2, 1, 0])
+            .unwrap_err()
+            .0,
+        "map already have item"
+    );
+    assert_eq!(
+        read_ref::<BTreeMap<u8, &[u8]>>(&heap, &[u32::MAX, 1])
+            .unwrap_err()
+            .0,
+        "args next error"
+    );
+    assert!(read_ref::<Vec<&str>>(&heap, &[u32::MAX]).is_err());
+
+    // a value without references is read like its Outcoming
+    let mut args: Vec<u32> = Vec::new();
+    Outcoming::args(&(Duration::from_millis(1500), [1_u64, 2]), &mut args).unwrap();
+    let read: (Duration, [u64; 2]) = read_all(&[], &args);
+    assert_eq!(read, (Duration::from_millis(1500), [1, 2]));
+
+    // the schema of a borrowed value is its len and pointer, the text parses back
+    let schema = SchemaTree::from(<(&str, Vec<&[u8]>)>::SCHEMA);
+    assert_eq!(schema.to_string(), "(&str, Vec<&Bytes>)");
+    assert_eq!(schema.to_string().parse::<SchemaTree>().unwrap(), schema);
+    assert_ne!(
+        SchemaTree::from(<&str>::SCHEMA).hash(),
+        SchemaTree::from(String::SCHEMA).hash()
+    );
+    assert_eq!(
+        dump_args(&schema, &[5, 0x10, 1, 4, 0x20]),
+        "0: &str len=5 ptr=0x00000010 | 1: len=1 | 1[0]: &Bytes len=4 ptr=0x00000020"
+    );
+}
+
+#[derive(Outcoming, Debug)]
+struct Reply<'a> {
+    id: u32,
+    totals: wa_proto::SeqView<'a, u64>,
+    names: wa_proto::MapView<'a, String, u32>,
+}
+
+#[test]
+fn views_decode_items_on_access() {
+    use std::collections::BTreeMap;
+    use wa_proto::{read_ref, MapView, OutcomingRef, SeqView};
+
+    let totals: Vec<u64> = (0..100_000).map(|i| i * 3).collect();
+    let mut args: Vec<u32> = Vec::new();
+    Outcoming::args(&totals, &mut args).unwrap();
+    let view: SeqView<u64> = read_ref(&[], &args).unwrap();
+    assert_eq!(view.len(), 100_000);
+    assert_eq!(view.get(77_777).unwrap().unwrap(), 233_331);
+    assert!(view.get(100_000).is_none());
+    assert_eq!(view.iter().nth(5).unwrap().unwrap(), 15);
+    let first: Vec<u64> = view.iter().take(3).map(Result::unwrap).collect();
+    assert_eq!(first, [0, 3, 6]);
+    // passed on as the same args without decoding
+    let mut again: Vec<u32> = Vec::new();
+    OutcomingRef::args(&view, &mut again).unwrap();
+    assert_eq!(again, args);
+    assert_eq!(
+        read_ref::<SeqView<u64>>(&[], &args[..args.len() - 1])
+            .unwrap_err()
+            .0,
+        "args next error"
+    );
+
+    // items of varying width are skipped, a broken one fails only when it is read
+    let words = vec!["one".to_string(), "two".to_string(), "three".to_string()];
+    let mut args: Vec<u32> = Vec::new();
+    Outcoming::args(&words, &mut args).unwrap();
+    args[4] = 0xff;
+    let view: SeqView<String> = read_ref(&[], &args).unwrap();
+    assert_eq!(view.get(2).unwrap().unwrap(), "three");
+    assert!(view.get(1).unwrap().is_err());
+    let items: Vec<_> = view.iter().collect();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].as_deref().unwrap(), "one");
+    assert!(items[1].is_err());
+
+    let names: BTreeMap<String, u32> = [("a", 1), ("bb", 2), ("ccc", 3)]
+        .into_iter()
+        .map(|(name, id)| (name.to_string(), id))
+        .collect();
+    let mut args: Vec<u32> = vec![9];
+    Outcoming::args(&totals[..4].to_vec(), &mut args).unwrap();
+    Outcoming::args(&names, &mut args).unwrap();
+    let reply: Reply = read_ref(&[], &args).unwrap();
+    assert_eq!(reply.id, 9);
+    assert_eq!(reply.totals.get(3).unwrap().unwrap(), 9);
+    let map: MapView<String, u32> = reply.names;
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get("bb").unwrap().unwrap(), 2);
+    assert!(map.get("d").is_none());
+    assert_eq!(map.get_index(2).unwrap().unwrap(), ("ccc".to_string(), 3));
+    let entries: BTreeMap<String, u32> = map.iter().map(Result::unwrap).collect();
+    assert_eq!(entries, names);
+    assert_eq!(OutcomingRef::size_hint(&reply), args.len());
+}
+
+#[test]
+fn stream_chunks_with_backpressure() {
+    use wa_proto::{Bytes, Chunk, Flow, Reassembler, StreamReader, StreamWriter};
+
+    let payload: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+    let mut writer = StreamWriter::new(7);
+    let mut receiver = Reassembler::new(7, payload.len());
+    let parts: Vec<&[u8]> = payload.chunks(1024).collect();
+    let mut index = 0;
+    let mut waited = false;
+    let mut calls = 0;
+    while index < parts.len() {
+        let last = index + 1 == parts.len();
+        let chunk = writer
+            .chunk(Bytes::from(parts[index].to_vec()), last)
+            .unwrap();
+        // a call of the receiver with the args of the chunk, the Flow is its result
+        let mut args: Vec<u32> = Vec::new();
+        Outcoming::args(&chunk, &mut args).unwrap();
+        assert_eq!(args[..3], [7, chunk.seq, last as u32]);
+        let chunk: Chunk<Bytes> = wa_proto::Outcoming::read(&[], &mut args.iter()).unwrap();
+        let flow = if chunk.seq == 3 && !waited {
+            waited = true;
+            Flow::Wait
+        } else {
+            receiver.push(chunk).unwrap()
+        };
+        let mut result: Vec<u32> = Vec::new();
+        Outcoming::args(&flow, &mut result).unwrap();
+        let flow: Flow = wa_proto::Outcoming::read(&[], &mut result.iter()).unwrap();
+        writer.flow(flow);
+        if flow == Flow::Continue {
+            index += 1;
+        }
+        calls += 1;
+    }
+    assert_eq!(calls, 11);
+    assert!(writer.is_finished() && !writer.is_stopped());
+    assert!(writer.chunk(Bytes::default(), true).is_err());
+    assert_eq!(receiver.finish().unwrap(), payload);
+
+    // the receiver stops a stream over its limit
+    let mut writer = StreamWriter::new(1);
+    let mut receiver = Reassembler::new(1, 100);
+    let flow = receiver
+        .push(writer.chunk(vec![0u8; 64], false).unwrap())
+        .unwrap();
+    assert_eq!(flow, Flow::Continue);
+    writer.flow(flow);
+    let flow = receiver
+        .push(writer.chunk(vec![0u8; 64], false).unwrap())
+        .unwrap();
+    assert_eq!(flow, Flow::Stop);
+    writer.flow(flow);
+    assert!(writer.is_finished() && writer.is_stopped());
+    assert!(receiver.finish().is_none());
+
+    // chunks sent again are ignored, missed chunks and other streams are errors
+    let mut reader = StreamReader::new(2);
+    let chunk = |stream, seq, last| Chunk {
+        stream,
+        seq,
+        last,
+        data: seq,
+    };
+    assert_eq!(reader.receive(chunk(2, 0, false)).unwrap(), Some(0));
+    assert_eq!(reader.receive(chunk(2, 0, false)).unwrap(), None);
+    assert_eq!(
+        reader.receive(chunk(2, 2, false)).unwrap_err().0,
+        "stream chunk out of order"
+    );
+    assert!(reader.receive(chunk(3, 1, false)).is_err());
+    assert_eq!(reader.receive(chunk(2, 1, true)).unwrap(), Some(1));
+    assert!(reader.is_finished());
+    assert!(reader.receive(chunk(2, 2, false)).is_err());
+
+    // the numbers don't wrap: both sides stop at u32::MAX
+    let mut writer = StreamWriter::resume(4, u32::MAX - 1);
+    let mut reader = StreamReader::resume(4, u32::MAX - 1);
+    let sent = writer.chunk(5, false).unwrap();
+    assert_eq!(sent.seq, u32::MAX - 1);
+    assert_eq!(reader.receive(sent).unwrap(), Some(5));
+    writer.flow(Flow::Continue);
+    assert_eq!(
+        writer.chunk(6, true).unwrap_err().0,
+        "stream chunk out of order"
+    );
+    assert_eq!(
+        reader.receive(chunk(4, u32::MAX, true)).unwrap_err().0,
+        "stream chunk out of order"
+    );
+    assert_eq!(reader.received(), u32::MAX);
+    // a chunk before the limit is taken again after a Wait
+    let mut writer = StreamWriter::resume(4, u32::MAX - 1);
+    assert_eq!(writer.chunk(7, true).unwrap().seq, u32::MAX - 1);
+    writer.flow(Flow::Wait);
+    assert_eq!(writer.chunk(7, true).unwrap().seq, u32::MAX - 1);
+}
@@ -0,0 +1,169 @@
+//! serde Serializer and Deserializer over the args: `cargo test --features serde`
+#![cfg(feature = "serde")]
+
+use wa_proto::{from_args, to_args};
+
+#[test]
+fn serde_args_roundtrip() {
+    let value: (u64, i64, u128, String, Option<Vec<u8>>, bool) = (
+        1 << 32,
+        -(1 << 40),
+        (1 << 64) | 7,
+        "abcde".to_string(),
+        Some(vec![1, 2, 3]),
+        true,
+    );
+    let mut args: Vec<u32> = Vec::new();
+    to_args(&value, &mut args).unwrap();
+    // the low word of a u64 first
+    assert_eq!(args[..2], [0, 1]);
+    assert_eq!(
+        from_args::<(u64, i64, u128, String, Option<Vec<u8>>, bool)>(&args).unwrap(),
+        value
+    );
+}
+
+#[test]
+fn serde_args_errors() {
+    // a string len beyond the args isn't allocated
+    assert!(from_args::<String>(&[u32::MAX, 0]).is_err());
+    assert!(from_args::<u64>(&[1]).is_err());
+    assert!(from_args::<u32>(&[1, 2]).is_err());
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn serde_len_beyond_u32() {
+    // zero-sized items: the len doesn't fit in an arg, nothing is allocated
+    let mut args = Vec::new();
+    let error = to_args(&vec![(); 1 << 32], &mut args).unwrap_err();
+    assert_eq!(error.0, "usize beyond u32");
+}
+
+/**
+A value the codecs can't encode.
+*/
+#[cfg(all(feature = "host", feature = "guest"))]
+#[derive(Debug, PartialEq)]
+struct Unencodable;
+
+#[cfg(all(feature = "host", feature = "guest"))]
+impl serde::Serialize for Unencodable {
+    fn serialize<S: serde::Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+        Err(serde::ser::Error::custom("unencodable"))
+    }
+}
+
+#[cfg(all(feature = "host", feature = "guest"))]
+impl<'de> serde::Deserialize<'de> for Unencodable {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <()>::deserialize(deserializer).map(|_| Unencodable)
+    }
+}
+
+/**
+The payload through both roles with the codec `C`, the payload args are returned.
+*/
+#[cfg(all(feature = "host", feature = "guest"))]
+fn payload_roundtrip<T, C>(value: T) -> Vec<u32>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned + PartialEq + core::fmt::Debug + Clone,
+    C: wa_proto::Codec,
+{
+    use wa_proto::{Incoming, Outcoming, Serde};
+
+    let wrapped: Serde<T, C> = Serde::new(value.clone());
+    let hint = Outcoming::size_hint(&wrapped);
+    let mut args: Vec<u32> = Vec::new();
+    Outcoming::args(&wrapped, &mut args).unwrap();
+    assert_eq!(hint, args.len());
+    let read: Serde<T, C> = Outcoming::read(&[], &mut args.iter()).unwrap();
+    assert_eq!(*read, value);
+
+    let mut host: Vec<u32> = Vec::new();
+    Incoming::args(&wrapped, &mut host).unwrap();
+    assert_eq!(host, args);
+    let (_, init): (u32, Serde<T, C>) = Incoming::init(&mut host.iter_mut()).unwrap();
+    assert_eq!(init.into_inner(), value);
+    args
+}
+
+#[cfg(all(feature = "host", feature = "guest"))]
+#[test]
+fn serde_payload_codecs() {
+    use wa_proto::{ArgsCodec, CompactCodec};
+
+    let value = (300_u32, String::from("abc"), vec![Some(-1_i64), None]);
+    let args = payload_roundtrip::<_, ArgsCodec>(value.clone());
+    // the len of the payload in bytes, then the args of the value as little endian bytes
+    assert_eq!(args[..3], [4 * (args.len() as u32 - 1), 300, 3]);
+    let compact = payload_roundtrip::<_, CompactCodec>(value.clone());
+    assert!(compact.len() < args.len());
+    #[cfg(feature = "bincode")]
+    {
+        let args = payload_roundtrip::<_, wa_proto::Bincode>(value.clone());
+        let bytes = bincode::serialize(&value).unwrap();
+        assert_eq!(args[0] as usize, bytes.len());
+        assert_eq!(args[1].to_le_bytes(), bytes[..4]);
+    }
+    #[cfg(feature = "postcard")]
+    {
+        let args = payload_roundtrip::<_, wa_proto::Postcard>(value.clone());
+        let bytes = postcard::to_allocvec(&value).unwrap();
+        assert_eq!(args[0] as usize, bytes.len());
+        assert_eq!(args[1].to_le_bytes(), bytes[..4]);
+    }
+}
+
+#[cfg(all(feature = "host", feature = "guest"))]
+#[test]
+fn serde_payload_errors() {
+    use wa_proto::{Outcoming, Serde};
+
+    let wrapped: Serde<Unencodable> = Serde::new(Unencodable);
+    assert_eq!(Outcoming::size_hint(&wrapped), 0);
+    // the encoding error comes out of args
+    let mut args: Vec<u32> = Vec::new();
+    assert!(Outcoming::args(&wrapped, &mut args).is_err());
+    assert!(args.is_empty());
+
+    // a payload of garbage bytes
+    assert!(<Serde<(u32, String)>>::read(&[], &mut [4, u32::MAX].iter()).is_err());
+    #[cfg(feature = "bincode")]
+    assert!(<Serde<String, wa_proto::Bincode>>::read(&[], &mut [4, u32::MAX].iter()).is_err());
+    #[cfg(feature = "postcard")]
+    assert!(<Serde<String, wa_proto::Postcard>>::read(&[], &mut [1, 5].iter()).is_err());
+
+    // a len beyond the args fails without reserving it
+    assert_eq!(
+        <Serde<String>>::read(&[], &mut [u32::MAX, 0].iter())
+            .unwrap_err()
+            .0,
+        "args next error"
+    );
+    let mut args = [u32::MAX, 0];
+    assert!(<Serde<String> as wa_proto::Incoming>::init(&mut args.iter_mut()).is_err());
+}
+
+#[cfg(all(feature = "host", feature = "guest"))]
+#[test]
+fn serde_payload_follows_changes() {
+    use wa_proto::{Outcoming, Serde};
+
+    let mut wrapped: Serde<String> = Serde::new(String::from("a"));
+    assert_eq!(Outcoming::size_hint(&wrapped), 3);
+    wrapped.push_str("bcdefgh");
+    assert_eq!(Outcoming::size_hint(&wrapped), 4);
+    // size_hint measures the value as it is now
+    let mut args: Vec<u32> = Vec::new();
+    Outcoming::args(&wrapped, &mut args).unwrap();
+    assert_eq!(args.len(), Outcoming::size_hint(&wrapped));
+    assert_eq!(
+        *<Serde<String>>::read(&[], &mut args.iter()).unwrap(),
+        "abcdefgh"
+    );
+
+    // nothing is kept inside, the messages with a payload can be shared between threads
+    fn shared<T: Sync + Send>(_: &T) {}
+    shared(&wrapped);
+}
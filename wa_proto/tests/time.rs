@@ -0,0 +1,365 @@
+//! Date and time types, both roles: `cargo test --features guest,time,chrono`,
+//! `legacy_time` makes the layout of wa_proto 0.1.2 the default one
+#![cfg(all(feature = "host", feature = "guest"))]
+
+use core::fmt::Debug;
+use core::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+use wa_proto::{with_wire_flags, Incoming, Outcoming, LEGACY_TIME_FLAG};
+
+/**
+Args of the value from both sides, checked to be the same.
+*/
+fn args_of<T: Incoming + Outcoming>(value: &T) -> Vec<u32> {
+    let mut host: Vec<u32> = Vec::new();
+    Incoming::args(value, &mut host).unwrap();
+    let mut guest: Vec<u32> = Vec::new();
+    Outcoming::args(value, &mut guest).unwrap();
+    assert_eq!(host, guest);
+    assert_eq!(Incoming::size_hint(value), host.len());
+    host
+}
+
+fn assert_roundtrip<T: Incoming + Outcoming + PartialEq + Debug>(values: &[T]) {
+    for value in values {
+        let mut args = args_of(value);
+        let decoded = T::read(&[], &mut args.iter()).unwrap();
+        assert_eq!(&decoded, value);
+        let (_, decoded) = T::init(&mut args.iter_mut()).unwrap();
+        assert_eq!(&decoded, value);
+    }
+}
+
+/**
+Both sides refuse the args with the same error.
+*/
+fn decode_error<T: Incoming + Outcoming + Debug>(args: &[u32]) -> String {
+    let read = T::read(&[], &mut args.iter()).unwrap_err();
+    let mut args = args.to_vec();
+    let init = T::init(&mut args.iter_mut()).unwrap_err();
+    assert_eq!(read.0, init.0);
+    read.0
+}
+
+#[test]
+fn duration() {
+    assert_roundtrip(&[
+        Duration::ZERO,
+        Duration::new(1, 999_999_999),
+        Duration::from_nanos(1),
+        Duration::new(1 << 32, 5),
+        Duration::MAX,
+    ]);
+    // the seconds as a u64 (low word first) and the nanos
+    assert_eq!(args_of(&Duration::new((1 << 32) | 7, 9)), [7, 1, 9]);
+    assert_eq!(<Duration as Outcoming>::FIXED_WORDS, Some(3));
+
+    assert_eq!(
+        decode_error::<Duration>(&[0, 0, 1_000_000_000]),
+        "duration nanos out of range"
+    );
+    assert_eq!(decode_error::<Duration>(&[0, 0]), "args next error");
+}
+
+#[test]
+fn system_time() {
+    let before = UNIX_EPOCH - Duration::from_millis(500);
+    assert_roundtrip(&[
+        UNIX_EPOCH,
+        UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789),
+        UNIX_EPOCH - Duration::from_secs(86_400),
+        before,
+        SystemTime::now(),
+    ]);
+    // nanos are counted forward from the negative seconds
+    assert_eq!(args_of(&before), [u32::MAX, u32::MAX, 500_000_000]);
+    assert_eq!(args_of(&(UNIX_EPOCH + Duration::new(5, 1))), [5, 0, 1]);
+
+    assert_eq!(
+        decode_error::<SystemTime>(&[0, 0, 1_000_000_000]),
+        "system time nanos out of range"
+    );
+    assert_eq!(decode_error::<SystemTime>(&[1, 0]), "args next error");
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn chrono_keeps_nanos() {
+    with_wire_flags(0, chrono_nanos).unwrap();
+}
+
+#[cfg(feature = "chrono")]
+fn chrono_nanos() {
+    use chrono::{DateTime, Duration as ChronoDuration, Utc};
+
+    let dt = DateTime::from_timestamp(1_700_000_000, 123_456_789).unwrap();
+    assert_roundtrip(&[dt, DateTime::<Utc>::UNIX_EPOCH]);
+    assert_eq!(args_of(&dt), [1_700_000_000, 0, 123_456_789]);
+
+    let duration = ChronoDuration::nanoseconds(-1_500_000_001);
+    assert_roundtrip(&[duration, ChronoDuration::zero(), ChronoDuration::MAX]);
+    // seconds and nanos with the same sign
+    assert_eq!(
+        args_of(&duration),
+        [u32::MAX, u32::MAX, -500_000_001_i32 as u32]
+    );
+    assert_eq!(
+        decode_error::<ChronoDuration>(&[0, 0, 1_000_000_000]),
+        "subsecond nanos out of range"
+    );
+}
+
+#[cfg(feature = "time")]
+fn offset_date_time(unix: i64, nanos: u32, offset_hours: i8) -> time::OffsetDateTime {
+    let offset = time::UtcOffset::from_hms(offset_hours, 0, 0).unwrap();
+    time::OffsetDateTime::from_unix_timestamp(unix)
+        .unwrap()
+        .replace_nanosecond(nanos)
+        .unwrap()
+        .to_offset(offset)
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn time_keeps_nanos() {
+    with_wire_flags(0, time_nanos).unwrap();
+}
+
+#[cfg(feature = "time")]
+fn time_nanos() {
+    let dt = offset_date_time(1_700_000_000, 123_456_789, 3);
+    assert_roundtrip(&[dt, time::OffsetDateTime::UNIX_EPOCH]);
+    // unix seconds, nanos and the offset in seconds
+    assert_eq!(args_of(&dt), [1_700_000_000, 0, 123_456_789, 10_800]);
+    let read = time::OffsetDateTime::read(&[], &mut args_of(&dt).iter()).unwrap();
+    assert_eq!(read.offset().whole_hours(), 3);
+
+    let time = time::Time::from_hms_nano(23, 59, 59, 999_999_999).unwrap();
+    assert_roundtrip(&[time, time::Time::MIDNIGHT]);
+    assert_eq!(args_of(&time), [0x003b_3b17, 999_999_999]);
+    assert_roundtrip(&[
+        time::Duration::new(-5, -999_999_999),
+        time::Duration::nanoseconds(1),
+        time::Duration::MAX,
+        time::Duration::MIN,
+    ]);
+    assert_eq!(
+        decode_error::<time::Duration>(&[0, 0, -1_000_000_000_i32 as u32]),
+        "subsecond nanos out of range"
+    );
+    assert_eq!(
+        decode_error::<time::OffsetDateTime>(&[0, 0, 1_000_000_000, 0]),
+        "cannot read datetime"
+    );
+    assert_eq!(
+        decode_error::<time::OffsetDateTime>(&[0, 0, 0, 93_600]),
+        "cannot read utc offset"
+    );
+    assert_eq!(
+        decode_error::<time::Time>(&[0, 1_000_000_000]),
+        "cannot read time"
+    );
+}
+
+/**
+The layout of wa_proto 0.1.2: whole seconds (milliseconds for chrono durations) only.
+*/
+#[test]
+fn legacy_time_layout() {
+    with_wire_flags(LEGACY_TIME_FLAG, legacy_layout).unwrap();
+    // the flags of the thread are back after the call
+    assert_eq!(wa_proto::wire_flags(), wa_proto::WIRE_FLAGS);
+    assert!(with_wire_flags(4, || ()).is_err());
+}
+
+fn legacy_layout() {
+    #[cfg(feature = "chrono")]
+    {
+        use chrono::{DateTime, Duration as ChronoDuration, Utc};
+
+        let dt = DateTime::from_timestamp(1_700_000_000, 123_456_789).unwrap();
+        assert_eq!(args_of(&dt), [1_700_000_000, 0]);
+        assert_eq!(
+            DateTime::<Utc>::read(&[], &mut [1_700_000_000, 0].iter()).unwrap(),
+            DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+        );
+        assert_eq!(
+            args_of(&ChronoDuration::microseconds(1_500_999)),
+            [1_500, 0]
+        );
+    }
+    #[cfg(feature = "time")]
+    {
+        let dt = offset_date_time(1_700_000_000, 500_000_000, 3);
+        assert_eq!(args_of(&dt), [1_700_000_000, 0]);
+        // the offset isn't sent, the time reads back in UTC
+        assert_eq!(
+            time::OffsetDateTime::read(&[], &mut [1_700_000_000, 0].iter()).unwrap(),
+            offset_date_time(1_700_000_000, 0, 0)
+        );
+        let time = time::Time::from_hms_milli(1, 2, 3, 500).unwrap();
+        assert_eq!(args_of(&time), [0x0003_0201]);
+        assert_eq!(args_of(&time::Duration::milliseconds(2_500)), [2, 0]);
+    }
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn chrono_types() {
+    use chrono::{DateTime, FixedOffset, Month, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+
+    let date = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+    assert_roundtrip(&[date, NaiveDate::MIN, NaiveDate::MAX]);
+    // days from the common era, 0001-01-01 is day 1
+    assert_eq!(args_of(&NaiveDate::from_ymd_opt(1, 1, 1).unwrap()), [1]);
+
+    let time = NaiveTime::from_hms_nano_opt(23, 59, 59, 999_999_999).unwrap();
+    // a leap second is the nanos beyond a second
+    let leap = NaiveTime::from_hms_nano_opt(23, 59, 59, 1_500_000_000).unwrap();
+    assert_roundtrip(&[time, leap, NaiveTime::MIN]);
+    assert_eq!(args_of(&leap), [86_399, 1_500_000_000]);
+
+    let dt: NaiveDateTime = date.and_time(time);
+    assert_roundtrip(&[dt, NaiveDateTime::MIN, NaiveDateTime::MAX]);
+
+    let offset = FixedOffset::west_opt(5 * 3600).unwrap();
+    let fixed: DateTime<FixedOffset> = dt.and_utc().with_timezone(&offset);
+    assert_roundtrip(&[fixed]);
+    assert_eq!(args_of(&fixed)[3], -18_000_i32 as u32);
+
+    assert_roundtrip(&[Weekday::Mon, Weekday::Sun]);
+    assert_eq!(args_of(&Weekday::Sun), [6]);
+    assert_roundtrip(&[Month::January, Month::December]);
+    assert_eq!(args_of(&Month::December), [12]);
+
+    assert_eq!(
+        decode_error::<NaiveDate>(&[i32::MAX as u32]),
+        "cannot read date"
+    );
+    assert_eq!(decode_error::<NaiveTime>(&[86_400, 0]), "cannot read time");
+    assert_eq!(
+        decode_error::<NaiveTime>(&[0, 2_000_000_000]),
+        "cannot read time"
+    );
+    assert_eq!(
+        decode_error::<NaiveDateTime>(&[0, 0, 2_000_000_000]),
+        "cannot read datetime"
+    );
+    assert_eq!(
+        decode_error::<DateTime<FixedOffset>>(&[0, 0, 0, 86_400]),
+        "cannot read offset"
+    );
+    assert_eq!(decode_error::<Weekday>(&[7]), "cannot read weekday");
+    assert_eq!(decode_error::<Weekday>(&[256]), "cannot read weekday");
+    assert_eq!(decode_error::<Month>(&[0]), "cannot read month");
+    assert_eq!(decode_error::<Month>(&[13]), "cannot read month");
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn time_types() {
+    use time::{Date, Month, PrimitiveDateTime, Time, UtcOffset, Weekday};
+
+    let date = Date::from_calendar_date(2024, Month::February, 29).unwrap();
+    assert_roundtrip(&[date, Date::MIN, Date::MAX]);
+    // julian day
+    assert_eq!(args_of(&date), [2_460_370]);
+
+    let time = Time::from_hms_nano(13, 14, 15, 16).unwrap();
+    let dt = PrimitiveDateTime::new(date, time);
+    assert_roundtrip(&[dt, PrimitiveDateTime::MIN, PrimitiveDateTime::MAX]);
+    // julian day, h/m/s in a word and nanos
+    assert_eq!(args_of(&dt), [2_460_370, 0x000f_0e0d, 16]);
+
+    let offset = UtcOffset::from_hms(-9, -30, 0).unwrap();
+    assert_roundtrip(&[offset, UtcOffset::UTC]);
+    assert_eq!(args_of(&offset), [-34_200_i32 as u32]);
+
+    assert_roundtrip(&[Weekday::Monday, Weekday::Sunday]);
+    assert_eq!(args_of(&Weekday::Sunday), [6]);
+    assert_roundtrip(&[Month::January, Month::December]);
+    assert_eq!(args_of(&Month::December), [12]);
+
+    assert_eq!(decode_error::<Date>(&[i32::MAX as u32]), "cannot read date");
+    assert_eq!(
+        decode_error::<PrimitiveDateTime>(&[2_460_370, 24, 0]),
+        "cannot read time"
+    );
+    assert_eq!(
+        decode_error::<PrimitiveDateTime>(&[2_460_370, 0, 1_000_000_000]),
+        "cannot read time"
+    );
+    assert_eq!(
+        decode_error::<UtcOffset>(&[93_600]),
+        "cannot read utc offset"
+    );
+    assert_eq!(decode_error::<Weekday>(&[7]), "cannot read weekday");
+    assert_eq!(decode_error::<Month>(&[0]), "cannot read month");
+    assert_eq!(decode_error::<Month>(&[269]), "cannot read month");
+}
+
+/**
+The host adapts to the time layout of the guest: the schemas are compared in that layout,
+and the values go with the flags `check` returns.
+*/
+#[cfg(feature = "chrono")]
+#[test]
+fn handshake_agrees_on_the_time_layout() {
+    use chrono::{DateTime, Utc};
+    use wa_proto::{Handshake, Message, Schema, TypeSchema, WIRE_FLAGS};
+
+    const NAME: &str = match <DateTime<Utc> as Schema>::SCHEMA {
+        TypeSchema::Alias(name, _) => name,
+        _ => "",
+    };
+    // a guest built with `legacy_time`, and one built without it
+    static OLD_AT: TypeSchema = TypeSchema::Alias(NAME, <i64 as Schema>::SCHEMA);
+    static NEW_AT: TypeSchema = TypeSchema::Alias(NAME, <(i64, u32) as Schema>::SCHEMA);
+    static HOST: Handshake = Handshake::new(&[Message::of::<Option<DateTime<Utc>>>("at")]);
+    let guest = |flags, schema: &'static TypeSchema| {
+        let messages = Box::leak(Box::new([Message {
+            name: "at",
+            schema: Box::leak(Box::new(TypeSchema::Option(schema))),
+        }]));
+        let mut args: Vec<u32> = Vec::new();
+        Handshake {
+            flags,
+            ..Handshake::new(messages)
+        }
+        .args(&mut args)
+        .unwrap();
+        args
+    };
+
+    let dt = DateTime::from_timestamp(1_700_000_000, 5).unwrap();
+    let flags = HOST.check(&guest(LEGACY_TIME_FLAG, &OLD_AT)).unwrap();
+    assert_eq!(flags, LEGACY_TIME_FLAG);
+    let mut args: Vec<u32> = Vec::new();
+    with_wire_flags(flags, || Incoming::args(&Some(dt), &mut args))
+        .unwrap()
+        .unwrap();
+    assert_eq!(args, [1, 1_700_000_000, 0]);
+    let read = with_wire_flags(flags, || {
+        Option::<DateTime<Utc>>::read(&[], &mut args.iter())
+    });
+    assert_eq!(
+        read.unwrap().unwrap(),
+        DateTime::from_timestamp(1_700_000_000, 0)
+    );
+
+    assert_eq!(HOST.check(&guest(0, &NEW_AT)).unwrap(), 0);
+    let mut args: Vec<u32> = Vec::new();
+    with_wire_flags(0, || Incoming::args(&Some(dt), &mut args))
+        .unwrap()
+        .unwrap();
+    assert_eq!(args, [1, 1_700_000_000, 0, 5]);
+
+    // the flags and the schemas of the guest disagree
+    let error = HOST.check(&guest(0, &OLD_AT)).unwrap_err();
+    assert!(error.0.starts_with("handshake: at: "), "{}", error.0);
+    assert!(HOST.check(&guest(LEGACY_TIME_FLAG, &NEW_AT)).is_err());
+    // a guest of the same build
+    let mut same: Vec<u32> = Vec::new();
+    HOST.args(&mut same).unwrap();
+    assert_eq!(HOST.check(&same).unwrap(), WIRE_FLAGS);
+}
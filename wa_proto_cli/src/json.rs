@@ -0,0 +1,331 @@
+//! JSON of a `Value`: integers out of the range of f64 are strings, bytes are arrays of numbers,
+//! `None` is null, a unit variant is its name and a variant with a value is `{"Name": value}`,
+//! a map is an array of `[key, value]` pairs, and the fields of a struct keep the schema order.
+//! An option inside an option is `{"Some": value}`, so `Some(None)` isn't read back as `None`.
+
+use serde_json::Value as Json;
+use wa_proto::{SchemaTree, Value};
+
+/**
+Largest integer that JSON readers keep exact in a f64.
+*/
+const MAX_SAFE_INTEGER: u128 = (1 << 53) - 1;
+
+pub fn to_json(value: &Value) -> String {
+    let mut out = String::new();
+    write_json(value, &mut out);
+    out
+}
+
+fn write_str(s: &str, out: &mut String) {
+    out.push_str(&Json::from(s).to_string());
+}
+
+fn write_list<'a, I: Iterator<Item = &'a Value>>(items: I, out: &mut String) {
+    out.push('[');
+    for (index, item) in items.enumerate() {
+        if index > 0 {
+            out.push_str(", ");
+        }
+        write_json(item, out);
+    }
+    out.push(']');
+}
+
+fn write_json(value: &Value, out: &mut String) {
+    match value {
+        Value::Bool(value) => out.push_str(&value.to_string()),
+        Value::Int(value) if value.unsigned_abs() <= MAX_SAFE_INTEGER => {
+            out.push_str(&value.to_string())
+        }
+        Value::UInt(value) if *value <= MAX_SAFE_INTEGER => out.push_str(&value.to_string()),
+        Value::Int(value) => write_str(&value.to_string(), out),
+        Value::UInt(value) => write_str(&value.to_string(), out),
+        Value::Float(value) => match serde_json::Number::from_f64(*value) {
+            Some(number) => out.push_str(&number.to_string()),
+            // NaN and the infinities aren't JSON numbers
+            None => write_str(&value.to_string(), out),
+        },
+        Value::String(value) => write_str(value, out),
+        Value::Bytes(bytes) => {
+            let bytes: Vec<String> = bytes.iter().map(|byte| byte.to_string()).collect();
+            out.push('[');
+            out.push_str(&bytes.join(", "));
+            out.push(']');
+        }
+        Value::Option(None) => out.push_str("null"),
+        Value::Option(Some(value)) => match value.as_ref() {
+            Value::Option(_) => {
+                out.push_str("{\"Some\": ");
+                write_json(value, out);
+                out.push('}');
+            }
+            _ => write_json(value, out),
+        },
+        Value::List(items) => write_list(items.iter(), out),
+        Value::Map(entries) => {
+            out.push('[');
+            for (index, (key, value)) in entries.iter().enumerate() {
+                if index > 0 {
+                    out.push_str(", ");
+                }
+                write_list([key, value].into_iter(), out);
+            }
+            out.push(']');
+        }
+        Value::Variant(_, name, None) => write_str(name, out),
+        Value::Variant(_, name, Some(value)) => {
+            out.push('{');
+            write_str(name, out);
+            out.push_str(": ");
+            write_json(value, out);
+            out.push('}');
+        }
+        Value::Record(fields) => {
+            out.push('{');
+            for (index, (name, value)) in fields.iter().enumerate() {
+                if index > 0 {
+                    out.push_str(", ");
+                }
+                write_str(name, out);
+                out.push_str(": ");
+                write_json(value, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn expected(path: &str, what: &str, json: &Json) -> String {
+    let path = if path.is_empty() { "value" } else { path };
+    format!("{}: expected {}, found {}", path, what, json)
+}
+
+fn join(path: &str, item: &str) -> String {
+    if path.is_empty() {
+        item.to_string()
+    } else {
+        format!("{}.{}", path, item)
+    }
+}
+
+fn is_option(schema: &SchemaTree) -> bool {
+    match schema {
+        SchemaTree::Option(_) => true,
+        SchemaTree::Alias(_, inner) => is_option(inner),
+        _ => false,
+    }
+}
+
+fn list<'a>(path: &str, json: &'a Json, len: Option<usize>) -> Result<&'a Vec<Json>, String> {
+    match (json, len) {
+        (Json::Array(items), None) => Ok(items),
+        (Json::Array(items), Some(len)) if items.len() == len => Ok(items),
+        (_, None) => Err(expected(path, "an array", json)),
+        (_, Some(len)) => Err(expected(path, &format!("an array of {}", len), json)),
+    }
+}
+
+/**
+Value of the schema from its JSON, the path of the value is in the errors.
+*/
+pub fn from_json(schema: &SchemaTree, json: &Json, path: &str) -> Result<Value, String> {
+    let value = match schema {
+        SchemaTree::Primitive(name) => primitive_from_json(name, json, path)?,
+        SchemaTree::Alias(_, inner) => from_json(inner, json, path)?,
+        SchemaTree::Payload(_, _) => primitive_from_json("Bytes", json, path)?,
+        SchemaTree::Option(_) if json.is_null() => Value::Option(None),
+        SchemaTree::Option(inner) if is_option(inner) => match json.get("Some") {
+            Some(value) if json.as_object().is_some_and(|object| object.len() == 1) => {
+                Value::Option(Some(Box::new(from_json(inner, value, path)?)))
+            }
+            _ => return Err(expected(path, "null or {\"Some\": value}", json)),
+        },
+        SchemaTree::Option(inner) => Value::Option(Some(Box::new(from_json(inner, json, path)?))),
+        SchemaTree::Seq(inner) => Value::List(
+            list(path, json, None)?
+                .iter()
+                .enumerate()
+                .map(|(index, item)| from_json(inner, item, &format!("{}[{}]", path, index)))
+                .collect::<Result<_, _>>()?,
+        ),
+        SchemaTree::Array(inner, len) => Value::List(
+            list(path, json, Some(*len))?
+                .iter()
+                .enumerate()
+                .map(|(index, item)| from_json(inner, item, &format!("{}[{}]", path, index)))
+                .collect::<Result<_, _>>()?,
+        ),
+        SchemaTree::Tuple(schemas) => Value::List(
+            list(path, json, Some(schemas.len()))?
+                .iter()
+                .zip(schemas)
+                .enumerate()
+                .map(|(index, (item, schema))| {
+                    from_json(schema, item, &join(path, &index.to_string()))
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        SchemaTree::Map(key, value) => Value::Map(
+            list(path, json, None)?
+                .iter()
+                .enumerate()
+                .map(|(index, entry)| {
+                    let path = format!("{}[{}]", path, index);
+                    let entry = list(&path, entry, Some(2))?;
+                    Ok((
+                        from_json(key, &entry[0], &format!("{}.key", path))?,
+                        from_json(value, &entry[1], &format!("{}.value", path))?,
+                    ))
+                })
+                .collect::<Result<_, String>>()?,
+        ),
+        SchemaTree::Struct {
+            name,
+            framed,
+            fields,
+            ..
+        } => {
+            let path = if path.is_empty() { name.as_str() } else { path };
+            let object = json
+                .as_object()
+                .ok_or_else(|| expected(path, "an object", json))?;
+            if let Some(key) = object
+                .keys()
+                .find(|key| !fields.iter().any(|field| &field.name == *key))
+            {
+                return Err(format!("{}: unknown field {:?}", path, key));
+            }
+            let mut record = Vec::with_capacity(fields.len());
+            for field in fields {
+                let field_path = join(path, &field.name);
+                match object.get(&field.name) {
+                    Some(json) => record.push((
+                        field.name.clone(),
+                        from_json(&field.schema, json, &field_path)?,
+                    )),
+                    // an older reader doesn't have the since fields
+                    None if *framed && field.since > 0 => {}
+                    None => return Err(format!("{}: missing", field_path)),
+                }
+            }
+            Value::Record(record)
+        }
+        SchemaTree::Enum { name, variants, .. } => {
+            let path = if path.is_empty() { name.as_str() } else { path };
+            let (variant, json) = match json {
+                Json::String(variant) => (variant, None),
+                Json::Object(object) if object.len() == 1 => {
+                    let (variant, json) = object.iter().next().unwrap();
+                    (variant, Some(json))
+                }
+                _ => return Err(expected(path, "a variant name or {\"Name\": value}", json)),
+            };
+            let tag = variants
+                .iter()
+                .position(|(name, _, _)| name == variant)
+                .ok_or_else(|| format!("{}: unknown variant {:?}", path, variant))?;
+            let variant_path = format!("{}::{}", path, variant);
+            let value = match (&variants[tag].2, json) {
+                (Some(schema), Some(json)) => {
+                    Some(Box::new(from_json(schema, json, &variant_path)?))
+                }
+                (None, None) => None,
+                (Some(_), None) => return Err(format!("{}: missing the value", variant_path)),
+                (None, Some(_)) => return Err(format!("{}: has no value", variant_path)),
+            };
+            Value::Variant(tag as u32, variant.clone(), value)
+        }
+    };
+    Ok(value)
+}
+
+/**
+Integer from a JSON number or from a string for the integers out of the range of f64.
+*/
+fn integer(json: &Json, path: &str, signed: bool, bits: u32) -> Result<Value, String> {
+    let what = format!("{}{}", if signed { "i" } else { "u" }, bits);
+    let text = match json {
+        Json::Number(number) => number.to_string(),
+        Json::String(text) => text.clone(),
+        _ => return Err(expected(path, &what, json)),
+    };
+    let out_of_range = || expected(path, &what, json);
+    if signed {
+        let value: i128 = text.parse().map_err(|_| out_of_range())?;
+        let min = -1_i128 << (bits - 1);
+        if bits < 128 && (value < min || value > -(min + 1)) {
+            return Err(out_of_range());
+        }
+        Ok(Value::Int(value))
+    } else {
+        let value: u128 = text.parse().map_err(|_| out_of_range())?;
+        if bits < 128 && value >> bits != 0 {
+            return Err(out_of_range());
+        }
+        Ok(Value::UInt(value))
+    }
+}
+
+fn float(json: &Json, path: &str) -> Result<Value, String> {
+    match json {
+        Json::Number(number) => number.as_f64(),
+        Json::String(text) => text.parse().ok(),
+        _ => None,
+    }
+    .map(Value::Float)
+    .ok_or_else(|| expected(path, "a float", json))
+}
+
+fn primitive_from_json(name: &str, json: &Json, path: &str) -> Result<Value, String> {
+    let value = match name {
+        "bool" => Value::Bool(
+            json.as_bool()
+                .ok_or_else(|| expected(path, "a bool", json))?,
+        ),
+        "u8" => integer(json, path, false, 8)?,
+        "u16" => integer(json, path, false, 16)?,
+        "u32" | "usize" => integer(json, path, false, 32)?,
+        "u64" => integer(json, path, false, 64)?,
+        "u128" => integer(json, path, false, 128)?,
+        "i8" => integer(json, path, true, 8)?,
+        "i32" | "isize" => integer(json, path, true, 32)?,
+        "i64" => integer(json, path, true, 64)?,
+        "i128" => integer(json, path, true, 128)?,
+        "f32" | "f64" => float(json, path)?,
+        "String" => Value::String(
+            json.as_str()
+                .ok_or_else(|| expected(path, "a string", json))?
+                .to_string(),
+        ),
+        "Bytes" => Value::Bytes(
+            list(path, json, None)?
+                .iter()
+                .map(|byte| match byte.as_u64() {
+                    Some(byte) if byte <= u8::MAX as u64 => Ok(byte as u8),
+                    _ => Err(expected(path, "an array of bytes", json)),
+                })
+                .collect::<Result<_, _>>()?,
+        ),
+        "Duration" | "SystemTime" => {
+            let secs = json
+                .get("secs")
+                .ok_or_else(|| expected(path, "{\"secs\": _, \"nanos\": _}", json))?;
+            let nanos = json
+                .get("nanos")
+                .ok_or_else(|| expected(path, "{\"secs\": _, \"nanos\": _}", json))?;
+            Value::Record(vec![
+                (
+                    "secs".to_string(),
+                    integer(secs, &join(path, "secs"), name == "SystemTime", 64)?,
+                ),
+                (
+                    "nanos".to_string(),
+                    integer(nanos, &join(path, "nanos"), false, 32)?,
+                ),
+            ])
+        }
+        _ => return Err(format!("{}: unknown type {}", path, name)),
+    };
+    Ok(value)
+}
@@ -0,0 +1,197 @@
+//! `wa-proto`: decode the args of a message to JSON and encode JSON to args,
+//! with the schema text printed by the Display of `SchemaTree`.
+
+mod json;
+
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    process,
+    str::FromStr,
+};
+use wa_proto::{dump_args, SchemaTree, Value};
+
+const USAGE: &str = "\
+usage: wa-proto <decode|encode|dump> --schema <TEXT|@FILE> [--format hex|bin|json] [FILE]
+
+  decode  read the args and print the value as JSON
+  encode  read the value as JSON and print the args
+  dump    read the args and print every value with its args
+
+  --schema  schema text like `Event { id: u64, name: String }`, or @FILE to read it from a file
+  --format  args as hex words (default), little-endian binary or a JSON array of words
+  FILE      input, the standard input if absent";
+
+/**
+Format of the args on the input of decode and dump and on the output of encode.
+*/
+#[derive(Clone, Copy)]
+enum Format {
+    /// Words like `0x00000003 0x00636261`, separated by spaces, commas or new lines
+    Hex,
+    /// Little-endian bytes of the words
+    Bin,
+    /// JSON array of the words
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hex" => Ok(Format::Hex),
+            "bin" => Ok(Format::Bin),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown format {:?}, expected hex, bin or json", s)),
+        }
+    }
+}
+
+struct Options {
+    command: String,
+    schema: SchemaTree,
+    format: Format,
+    input: Option<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut command = None;
+        let mut schema = None;
+        let mut format = Format::Hex;
+        let mut input = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--schema" => {
+                    let text = args.next().ok_or("--schema needs a value")?;
+                    let text = match text.strip_prefix('@') {
+                        Some(path) => fs::read_to_string(path)
+                            .map_err(|error| format!("{}: {}", path, error))?,
+                        None => text.clone(),
+                    };
+                    schema = Some(SchemaTree::from_str(text.trim()).map_err(|error| error.0)?);
+                }
+                "--format" => format = args.next().ok_or("--format needs a value")?.parse()?,
+                flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+                _ if command.is_none() => command = Some(arg.clone()),
+                _ if input.is_none() => input = Some(arg.clone()),
+                _ => return Err(format!("unexpected argument {:?}", arg)),
+            }
+        }
+        Ok(Options {
+            command: command.ok_or("no command")?,
+            schema: schema.ok_or("no --schema")?,
+            format,
+            input,
+        })
+    }
+
+    fn read_input(&self) -> Result<Vec<u8>, String> {
+        match &self.input {
+            Some(path) => fs::read(path).map_err(|error| format!("{}: {}", path, error)),
+            None => {
+                let mut input = Vec::new();
+                io::stdin()
+                    .read_to_end(&mut input)
+                    .map_err(|error| format!("stdin: {}", error))?;
+                Ok(input)
+            }
+        }
+    }
+}
+
+fn read_words(format: Format, input: &[u8]) -> Result<Vec<u32>, String> {
+    match format {
+        Format::Hex => String::from_utf8_lossy(input)
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|word| !word.is_empty())
+            .map(|word| {
+                let digits = word.strip_prefix("0x").unwrap_or(word);
+                u32::from_str_radix(digits, 16).map_err(|_| format!("not a hex word: {:?}", word))
+            })
+            .collect(),
+        Format::Bin => {
+            if !input.len().is_multiple_of(4) {
+                return Err(format!(
+                    "{} bytes is not a whole number of words",
+                    input.len()
+                ));
+            }
+            Ok(input
+                .chunks(4)
+                .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect())
+        }
+        Format::Json => serde_json::from_slice(input).map_err(|error| format!("json: {}", error)),
+    }
+}
+
+fn write_words(format: Format, words: &[u32]) -> Result<(), String> {
+    let output = match format {
+        Format::Hex => {
+            let words: Vec<String> = words.iter().map(|word| format!("{:#010x}", word)).collect();
+            format!("{}\n", words.join(" ")).into_bytes()
+        }
+        Format::Bin => words.iter().flat_map(|word| word.to_le_bytes()).collect(),
+        Format::Json => format!(
+            "{}\n",
+            serde_json::to_string(words).map_err(|error| error.to_string())?
+        )
+        .into_bytes(),
+    };
+    io::stdout()
+        .write_all(&output)
+        .map_err(|error| format!("stdout: {}", error))
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    match options.command.as_str() {
+        "decode" => {
+            let words = read_words(options.format, &options.read_input()?)?;
+            let mut args = words.iter();
+            let value = Value::from_args(&options.schema, &mut args).map_err(|error| error.0)?;
+            if args.len() > 0 {
+                return Err(format!("{} trailing args after the value", args.len()));
+            }
+            println!("{}", json::to_json(&value));
+            Ok(())
+        }
+        "encode" => {
+            let input: serde_json::Value = serde_json::from_slice(&options.read_input()?)
+                .map_err(|error| format!("json: {}", error))?;
+            let value = json::from_json(&options.schema, &input, "")?;
+            let mut words: Vec<u32> = Vec::new();
+            value
+                .args(&options.schema, &mut words)
+                .map_err(|error| error.0)?;
+            write_words(options.format, &words)
+        }
+        "dump" => {
+            let words = read_words(options.format, &options.read_input()?)?;
+            println!("{}", dump_args(&options.schema, &words));
+            Ok(())
+        }
+        command => Err(format!("unknown command {:?}", command)),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(error) = run(&options) {
+        eprintln!("wa-proto: {}", error);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,207 @@
+//! The `wa-proto` binary on its input and output: the formats of the args,
+//! encode and decode round trips, and the messages of the errors.
+
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+const EVENT: &str = "Event { id: u64, name: String }";
+
+struct Output {
+    ok: bool,
+    stdout: Vec<u8>,
+    stderr: String,
+}
+
+impl Output {
+    fn text(&self) -> String {
+        String::from_utf8(self.stdout.clone()).unwrap()
+    }
+}
+
+fn run(args: &[&str], input: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_wa-proto"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    let output = child.wait_with_output().unwrap();
+    Output {
+        ok: output.status.success(),
+        stdout: output.stdout,
+        stderr: String::from_utf8(output.stderr).unwrap(),
+    }
+}
+
+fn encode(schema: &str, format: &str, json: &str) -> Vec<u8> {
+    let output = run(
+        &["encode", "--schema", schema, "--format", format],
+        json.as_bytes(),
+    );
+    assert!(output.ok, "{}", output.stderr);
+    output.stdout
+}
+
+fn decode(schema: &str, format: &str, args: &[u8]) -> String {
+    let output = run(&["decode", "--schema", schema, "--format", format], args);
+    assert!(output.ok, "{}", output.stderr);
+    output.text()
+}
+
+fn error(args: &[&str], input: &[u8]) -> String {
+    let output = run(args, input);
+    assert!(!output.ok, "no error, the output is {:?}", output.text());
+    output.stderr
+}
+
+#[test]
+fn formats_of_the_args() {
+    let json = r#"{"id": 7, "name": "abc"}"#;
+    let hex = encode(EVENT, "hex", json);
+    assert_eq!(
+        String::from_utf8(hex.clone()).unwrap(),
+        "0x00000007 0x00000000 0x00000003 0x00636261\n"
+    );
+    let bin = encode(EVENT, "bin", json);
+    assert_eq!(
+        bin,
+        [7, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0x61, 0x62, 0x63, 0]
+    );
+    let words = encode(EVENT, "json", json);
+    assert_eq!(
+        String::from_utf8(words.clone()).unwrap(),
+        "[7,0,3,6513249]\n"
+    );
+
+    for (format, args) in [("hex", hex), ("bin", bin), ("json", words)] {
+        assert_eq!(decode(EVENT, format, &args), format!("{}\n", json));
+    }
+    // commas, new lines and words without 0x
+    assert_eq!(
+        decode(EVENT, "hex", b"7,0\n0x3\t636261\n"),
+        format!("{}\n", json)
+    );
+}
+
+#[test]
+fn values_round_trip() {
+    for (schema, json) in [
+        (
+            "(u8, i8, i64, u128, f64, bool)",
+            r#"[255, -128, -9007199254740991, "340282366920938463463374607431768211455", 1.5, true]"#,
+        ),
+        ("Vec<String>", r#"["", "abcde"]"#),
+        ("Map<u32, Bytes>", "[[1, [0, 255]], [2, []]]"),
+        (
+            "[Shape { Dot, Circle(f32), Rect((u32, u32)) }; 3]",
+            r#"["Dot", {"Circle": 0.5}, {"Rect": [2, 3]}]"#,
+        ),
+        ("Duration", r#"{"secs": 5, "nanos": 7}"#),
+    ] {
+        let args = encode(schema, "hex", json);
+        assert_eq!(
+            decode(schema, "hex", &args),
+            format!("{}\n", json),
+            "{}",
+            schema
+        );
+    }
+}
+
+#[test]
+fn nested_options_keep_their_level() {
+    let schema = "Option<Option<u32>>";
+    for (json, args) in [
+        ("null", "0x00000000\n"),
+        (r#"{"Some": null}"#, "0x00000001 0x00000000\n"),
+        (r#"{"Some": 5}"#, "0x00000001 0x00000001 0x00000005\n"),
+    ] {
+        let encoded = encode(schema, "hex", json);
+        assert_eq!(String::from_utf8(encoded.clone()).unwrap(), args);
+        assert_eq!(decode(schema, "hex", &encoded), format!("{}\n", json));
+    }
+    // the outermost option stays the value itself
+    assert_eq!(decode("Option<u32>", "hex", b"1 5"), "5\n");
+
+    let message = error(&["encode", "--schema", schema], b"5");
+    assert!(
+        message.contains(r#"value: expected null or {"Some": value}, found 5"#),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn dump_marks_the_mismatch() {
+    let output = run(&["dump", "--schema", EVENT], b"7 0 3");
+    assert!(output.ok, "{}", output.stderr);
+    assert_eq!(
+        output.text(),
+        "Event.id: u64 7 (lo 0x00000007, hi 0x00000000) | Event.name: len=3 \
+         | Event.name: !! expected 3 bytes of String, the args ended\n"
+    );
+}
+
+#[test]
+fn errors_are_readable() {
+    let message = error(&["decode", "--schema", EVENT], b"7 0 xyz");
+    assert!(message.contains("not a hex word: \"xyz\""), "{}", message);
+
+    let message = error(
+        &["decode", "--schema", EVENT, "--format", "bin"],
+        &[1, 2, 3],
+    );
+    assert!(
+        message.contains("3 bytes is not a whole number of words"),
+        "{}",
+        message
+    );
+
+    let message = error(&["decode", "--schema", EVENT, "--format", "json"], b"[1,");
+    assert!(message.starts_with("wa-proto: json: "), "{}", message);
+
+    let message = error(&["decode", "--schema", EVENT], b"7 0 0 9");
+    assert!(
+        message.contains("1 trailing args after the value"),
+        "{}",
+        message
+    );
+
+    let message = error(&["decode", "--schema", EVENT], b"7 0");
+    assert!(message.contains("args next error"), "{}", message);
+
+    let message = error(
+        &["encode", "--schema", EVENT],
+        br#"{"id": "x", "name": ""}"#,
+    );
+    assert!(
+        message.contains(r#"Event.id: expected u64, found "x""#),
+        "{}",
+        message
+    );
+    let message = error(&["encode", "--schema", EVENT], br#"{"id": 1}"#);
+    assert!(message.contains("Event.name: missing"), "{}", message);
+    let message = error(&["encode", "--schema", "u8"], b"256");
+    assert!(
+        message.contains("value: expected u8, found 256"),
+        "{}",
+        message
+    );
+
+    // usage errors exit with the usage
+    let message = error(&["decode", "--format", "yaml", "--schema", EVENT], b"");
+    assert!(message.contains("unknown format \"yaml\""), "{}", message);
+    assert!(message.contains("usage: wa-proto"), "{}", message);
+    let message = error(&["decode"], b"");
+    assert!(message.contains("no --schema"), "{}", message);
+    let message = error(&["convert", "--schema", EVENT], b"");
+    assert!(
+        message.contains("unknown command \"convert\""),
+        "{}",
+        message
+    );
+}
@@ -11,25 +11,25 @@ use syn::{parse_macro_input, Data, DeriveInput, Field, Fields, Ident, Lit, Meta,
 
 fn args_in(name: Ident) -> TokenStream {
     quote! {
-        Incoming::args(&self.#name, args)?;
+        wa_proto::Incoming::args(&self.#name, args)?;
     }
 }
 
 fn args_out(name: Ident) -> TokenStream {
     quote! {
-        Outcoming::args(&self.#name, args)?;
+        wa_proto::Outcoming::args(&self.#name, args)?;
     }
 }
 
 fn fill_gen(name: Ident) -> TokenStream {
     quote! {
-        Incoming::fill(&self.#name, heap, args)?;
+        wa_proto::Incoming::fill(&self.#name, heap, args)?;
     }
 }
 
 fn init_gen(name: Ident, typ: &Type) -> TokenStream {
     let stream = quote! {
-        let (_, #name) = <#typ as Incoming>::init(args)?;
+        let (_, #name) = <#typ as wa_proto::Incoming>::init(args)?;
     };
 
     stream
@@ -37,7 +37,7 @@ fn init_gen(name: Ident, typ: &Type) -> TokenStream {
 
 fn read_gen(name: Ident, typ: &Type) -> TokenStream {
     let stream = quote! {
-        let #name = <#typ as Outcoming>::read(heap, args)?;
+        let #name = <#typ as wa_proto::Outcoming>::read(heap, args)?;
     };
 
     stream
@@ -45,16 +45,28 @@ fn read_gen(name: Ident, typ: &Type) -> TokenStream {
 
 fn is_need_init_fill_gen(typ: &Type) -> TokenStream {
     quote! {
-        <#typ as Incoming>::IS_NEED_INIT_FILL
+        <#typ as wa_proto::Incoming>::IS_NEED_INIT_FILL
     }
 }
 
 fn is_need_read_gen(typ: &Type) -> TokenStream {
     quote! {
-        <#typ as Outcoming>::IS_NEED_READ
+        <#typ as wa_proto::Outcoming>::IS_NEED_READ
     }
 }
 
+/**
+FIXED_WORDS of a struct: sum of the fields, None if any field isn't fixed.
+*/
+fn fixed_words_gen<'a>(
+    trait_path: &TokenStream,
+    types: impl Iterator<Item = &'a Type>,
+) -> TokenStream {
+    types.fold(quote! { Some(0) }, |acc, typ| {
+        quote! { wa_proto::add_fixed_words(#acc, <#typ as #trait_path>::FIXED_WORDS) }
+    })
+}
+
 fn get_primitive_name(ast: &DeriveInput) -> TokenStream {
     ast.attrs
         .iter()
@@ -84,6 +96,699 @@ fn get_primitive_name(ast: &DeriveInput) -> TokenStream {
         .expect("complex enums must include primitive type name!")
 }
 
+/**
+`#[coming(flag)]` on a struct or a field.
+*/
+fn has_coming_flag(attrs: &[syn::Attribute], flag: &str) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path.is_ident("coming")
+            && matches!(attr.parse_args::<Meta>(), Ok(Meta::Path(path)) if path.is_ident(flag))
+    })
+}
+
+/**
+Fields packed by `#[coming(packed)]`: bool, u8, i8, u16 and fields with `#[coming(pack)]`.
+*/
+fn is_packable(field: &Field) -> bool {
+    if has_coming_flag(&field.attrs, "pack") {
+        return true;
+    }
+    match &field.ty {
+        Type::Path(path) => ["bool", "u8", "i8", "u16"]
+            .iter()
+            .any(|name| path.qself.is_none() && path.path.is_ident(name)),
+        _ => false,
+    }
+}
+
+/**
+Access to the field and the name of the local variable for it.
+*/
+fn field_access(index: usize, field: &Field) -> (TokenStream, Ident) {
+    match &field.ident {
+        Some(ident) => (quote! { self.#ident }, ident.clone()),
+        None => {
+            let index_literal = Literal::usize_unsuffixed(index);
+            let arg_ident = Ident::new(&format!("arg{}", index), Span::call_site());
+            (quote! { self.#index_literal }, arg_ident)
+        }
+    }
+}
+
+/**
+Constructor of the struct from the local variables.
+*/
+fn construct_gen(name: &Ident, fields: &Fields, locals: &[Ident]) -> TokenStream {
+    match fields {
+        Fields::Named(_) => quote! { #name { #(#locals),* } },
+        Fields::Unnamed(_) => quote! { #name ( #(#locals),* ) },
+        Fields::Unit => quote! { #name },
+    }
+}
+
+/**
+Parts shared by the packed impls of both traits.
+*/
+struct PackedFields {
+    packed: Vec<(TokenStream, Ident, Type)>,
+    others: Vec<(TokenStream, Ident, Type)>,
+    locals: Vec<Ident>,
+    words: TokenStream,
+}
+
+fn packed_fields(fields: &Fields) -> PackedFields {
+    let mut packed = Vec::new();
+    let mut others = Vec::new();
+    let mut locals = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        let (access, local) = field_access(index, field);
+        locals.push(local.clone());
+        if is_packable(field) {
+            packed.push((access, local, field.ty.clone()));
+        } else {
+            others.push((access, local, field.ty.clone()));
+        }
+    }
+    let bits = packed
+        .iter()
+        .map(|(_, _, typ)| quote! { <#typ as wa_proto::PackBits>::BITS });
+    let words = quote! { wa_proto::packed_words(&[#(#bits),*]) };
+    PackedFields {
+        packed,
+        others,
+        locals,
+        words,
+    }
+}
+
+/**
+Incoming for `#[coming(packed)]` structs: the packed fields in shared args, then the other fields.
+*/
+fn packed_incoming(name: &Ident, fields: &Fields) -> TokenStream {
+    let PackedFields {
+        packed,
+        others,
+        locals,
+        words,
+    } = packed_fields(fields);
+    let construct = construct_gen(name, fields, &locals);
+    let fixed_words = fixed_words_gen(
+        &quote! { wa_proto::Incoming },
+        others.iter().map(|(_, _, typ)| typ),
+    );
+    let other_access: Vec<&TokenStream> = others.iter().map(|(access, _, _)| access).collect();
+    let other_types = others.iter().map(|(_, _, typ)| typ);
+    let other_locals = others.iter().map(|(_, local, _)| local);
+    let other_need = others.iter().map(|(_, _, typ)| is_need_init_fill_gen(typ));
+    let packed_access = packed.iter().map(|(access, _, _)| access);
+    let packed_locals = packed.iter().map(|(_, local, _)| local);
+    let packed_types = packed.iter().map(|(_, _, typ)| typ);
+
+    quote! {
+        impl wa_proto::Incoming for #name {
+            const IS_NEED_INIT_FILL: bool = false #(|| #other_need)*;
+
+            const FIXED_WORDS: Option<usize> = wa_proto::add_fixed_words(Some(#words), #fixed_words);
+
+            fn size_hint(&self) -> usize {
+                #words #(+ wa_proto::Incoming::size_hint(&#other_access))*
+            }
+
+            wa_proto::__guest! {
+                fn init(args: &mut core::slice::IterMut<u32>) -> Result<(u32, Self), wa_proto::ProtocolError> {
+                    let mut bits = wa_proto::BitReader::new();
+                    #(let #packed_locals = bits.read::<#packed_types>(|| args.next().map(|u| *u))?;)*
+                    #(let (_, #other_locals) = <#other_types as wa_proto::Incoming>::init(args)?;)*
+                    Ok((0, #construct))
+                }
+            }
+
+            wa_proto::__host! {
+                fn args(&self, args: &mut wa_proto::__private::Vec<u32>) -> Result<(), wa_proto::ProtocolError> {
+                    let mut bits = wa_proto::BitWriter::new();
+                    #(bits.write(&#packed_access, args)?;)*
+                    bits.finish(args)?;
+                    #(wa_proto::Incoming::args(&#other_access, args)?;)*
+                    Ok(())
+                }
+
+                fn fill(&self, heap: &mut core::cell::RefMut<[u8]>, args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
+                    for _ in 0..#words {
+                        args.next().ok_or_else(|| wa_proto::ProtocolError::from(wa_proto::ARGS_NEXT_ERROR))?;
+                    }
+                    #(wa_proto::Incoming::fill(&#other_access, heap, args)?;)*
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+/**
+Outcoming for `#[coming(packed)]` structs, the same layout as `packed_incoming`.
+*/
+fn packed_outcoming(name: &Ident, fields: &Fields) -> TokenStream {
+    let PackedFields {
+        packed,
+        others,
+        locals,
+        words,
+    } = packed_fields(fields);
+    let construct = construct_gen(name, fields, &locals);
+    let fixed_words = fixed_words_gen(
+        &quote! { wa_proto::Outcoming },
+        others.iter().map(|(_, _, typ)| typ),
+    );
+    let other_access: Vec<&TokenStream> = others.iter().map(|(access, _, _)| access).collect();
+    let other_types: Vec<&Type> = others.iter().map(|(_, _, typ)| typ).collect();
+    let other_locals = others.iter().map(|(_, local, _)| local);
+    let other_need = others.iter().map(|(_, _, typ)| is_need_read_gen(typ));
+    let packed_access = packed.iter().map(|(access, _, _)| access);
+    let packed_locals = packed.iter().map(|(_, local, _)| local);
+    let packed_types: Vec<&Type> = packed.iter().map(|(_, _, typ)| typ).collect();
+
+    quote! {
+        impl wa_proto::Outcoming for #name {
+            const IS_NEED_READ: bool = false #(|| #other_need)*;
+
+            const FIXED_WORDS: Option<usize> = wa_proto::add_fixed_words(Some(#words), #fixed_words);
+
+            fn size_hint(&self) -> usize {
+                #words #(+ wa_proto::Outcoming::size_hint(&#other_access))*
+            }
+
+            wa_proto::__guest! {
+                fn args<W: wa_proto::ArgsWriter>(&self, args: &mut W) -> Result<(), wa_proto::ProtocolError> {
+                    let mut bits = wa_proto::BitWriter::new();
+                    #(bits.write(&#packed_access, args)?;)*
+                    bits.finish(args)?;
+                    #(wa_proto::Outcoming::args(&#other_access, args)?;)*
+                    Ok(())
+                }
+            }
+
+            wa_proto::__host! {
+                fn read(heap: &[u8], args: &mut core::slice::Iter<u32>) -> Result<Self, wa_proto::ProtocolError> {
+                    let mut bits = wa_proto::BitReader::new();
+                    #(let #packed_locals = bits.read::<#packed_types>(|| args.next().copied())?;)*
+                    #(let #other_locals = <#other_types as wa_proto::Outcoming>::read(heap, args)?;)*
+                    Ok(#construct)
+                }
+
+                fn validate(heap: &[u8], args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
+                    let mut bits = wa_proto::BitReader::new();
+                    #(bits.read::<#packed_types>(|| args.next().copied())?;)*
+                    #(<#other_types as wa_proto::Outcoming>::validate(heap, args)?;)*
+                    Ok(())
+                }
+
+                fn skip(args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
+                    wa_proto::skip_args(args, #words)?;
+                    #(<#other_types as wa_proto::Outcoming>::skip(args)?;)*
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+/**
+`#[coming(since = N)]` on a field: the version of the message that added the field.
+*/
+fn coming_since(field: &Field) -> Option<u32> {
+    field.attrs.iter().find_map(|attr| {
+        if !attr.path.is_ident("coming") {
+            return None;
+        }
+        match attr.parse_args::<Meta>() {
+            Ok(Meta::NameValue(name_value)) if name_value.path.is_ident("since") => {
+                match name_value.lit {
+                    Lit::Int(int) => match int.base10_parse::<u32>().unwrap() {
+                        0 => panic!("#[coming(since = N)] versions start from 1"),
+                        since => Some(since),
+                    },
+                    _ => panic!("#[coming(since = N)] needs an integer version"),
+                }
+            }
+            _ => None,
+        }
+    })
+}
+
+/**
+Field of a `#[coming(framed)]` struct.
+*/
+struct FramedField {
+    access: TokenStream,
+    local: Ident,
+    typ: Type,
+    since: Option<u32>,
+}
+
+/**
+Fields of a `#[coming(framed)]` struct: the required fields, then the `since` fields.
+*/
+fn framed_fields(ast: &DeriveInput, fields: &Fields) -> Vec<FramedField> {
+    if has_coming_flag(&ast.attrs, "packed") {
+        panic!("#[coming(framed)] can't be combined with #[coming(packed)]");
+    }
+    let mut last_since: Option<u32> = None;
+    fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let (access, local) = field_access(index, field);
+            let since = coming_since(field);
+            match (last_since, since) {
+                (Some(_), None) => panic!("fields with #[coming(since)] must be the last fields"),
+                (Some(last), Some(since)) if since < last => {
+                    panic!("fields with #[coming(since)] must be in the order of the versions")
+                }
+                _ => {}
+            }
+            last_since = since.or(last_since);
+            FramedField {
+                access,
+                local,
+                typ: field.ty.clone(),
+                since,
+            }
+        })
+        .collect()
+}
+
+/**
+Reading of a field inside the frame: `since` fields are default when the frame is over.
+*/
+fn framed_read_gen(field: &FramedField, read: TokenStream) -> TokenStream {
+    let local = &field.local;
+    if field.since.is_some() {
+        quote! {
+            let #local = if args.len() > end { #read } else { Default::default() };
+        }
+    } else {
+        quote! {
+            let #local = #read;
+        }
+    }
+}
+
+/**
+Reads the len of the frame and computes the count of the args left after it.
+*/
+fn frame_start_gen() -> TokenStream {
+    quote! {
+        let end = wa_proto::frame_end(args)?;
+    }
+}
+
+/**
+Skips a whole frame: the len of the frame and the args in it.
+*/
+fn skip_frame_gen() -> TokenStream {
+    quote! {
+        let frame = *args.next().ok_or_else(|| wa_proto::ProtocolError::from(wa_proto::ARGS_NEXT_ERROR))? as usize;
+        wa_proto::skip_args(args, frame)
+    }
+}
+
+/**
+Writes a placeholder for the count of the args of the frame, patched by `frame_close_gen`.
+*/
+fn frame_open_gen() -> TokenStream {
+    quote! {
+        let frame = wa_proto::ArgsWriter::len(args);
+        wa_proto::ArgsWriter::push(args, 0)?;
+    }
+}
+
+/**
+Patches the count of the args written since `frame_open_gen`.
+*/
+fn frame_close_gen() -> TokenStream {
+    quote! {
+        let count = wa_proto::ArgsWriter::len(args) - frame - 1;
+        wa_proto::ArgsWriter::set(args, frame, wa_proto::usize_arg(count)?)?;
+    }
+}
+
+/**
+Skips the args of the frame unknown to this version of the type.
+*/
+fn frame_end_gen() -> TokenStream {
+    quote! {
+        wa_proto::skip_to_frame_end(args, end)?;
+    }
+}
+
+/**
+Incoming for `#[coming(framed)]` structs: the count of the args of the fields, then the fields.
+*/
+fn framed_incoming(ast: &DeriveInput, fields: &Fields) -> TokenStream {
+    let name = &ast.ident;
+    let framed = framed_fields(ast, fields);
+    let locals: Vec<Ident> = framed.iter().map(|field| field.local.clone()).collect();
+    let construct = construct_gen(name, fields, &locals);
+    let access: Vec<&TokenStream> = framed.iter().map(|field| &field.access).collect();
+    let need = framed.iter().map(|field| is_need_init_fill_gen(&field.typ));
+    let reads = framed.iter().map(|field| {
+        let typ = &field.typ;
+        framed_read_gen(
+            field,
+            quote! { <#typ as wa_proto::Incoming>::init(args)?.1 },
+        )
+    });
+    let frame_start = frame_start_gen();
+    let frame_end = frame_end_gen();
+    let frame_open = frame_open_gen();
+    let frame_close = frame_close_gen();
+
+    quote! {
+        impl wa_proto::Incoming for #name {
+            const IS_NEED_INIT_FILL: bool = false #(|| #need)*;
+
+            // another version of the struct sends another count of args
+            const FIXED_WORDS: Option<usize> = None;
+
+            fn size_hint(&self) -> usize {
+                1 #(+ wa_proto::Incoming::size_hint(&#access))*
+            }
+
+            wa_proto::__guest! {
+                fn init(args: &mut core::slice::IterMut<u32>) -> Result<(u32, Self), wa_proto::ProtocolError> {
+                    #frame_start
+                    #(#reads)*
+                    #frame_end
+                    Ok((0, #construct))
+                }
+            }
+
+            wa_proto::__host! {
+                fn args(&self, args: &mut wa_proto::__private::Vec<u32>) -> Result<(), wa_proto::ProtocolError> {
+                    #frame_open
+                    #(wa_proto::Incoming::args(&#access, args)?;)*
+                    #frame_close
+                    Ok(())
+                }
+
+                fn fill(&self, heap: &mut core::cell::RefMut<[u8]>, args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
+                    args.next().ok_or_else(|| wa_proto::ProtocolError::from(wa_proto::ARGS_NEXT_ERROR))?;
+                    #(wa_proto::Incoming::fill(&#access, heap, args)?;)*
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+/**
+Outcoming for `#[coming(framed)]` structs, the same layout as `framed_incoming`.
+*/
+fn framed_outcoming(ast: &DeriveInput, fields: &Fields) -> TokenStream {
+    let name = &ast.ident;
+    let framed = framed_fields(ast, fields);
+    let locals: Vec<Ident> = framed.iter().map(|field| field.local.clone()).collect();
+    let construct = construct_gen(name, fields, &locals);
+    let access: Vec<&TokenStream> = framed.iter().map(|field| &field.access).collect();
+    let need = framed.iter().map(|field| is_need_read_gen(&field.typ));
+    let reads = framed.iter().map(|field| {
+        let typ = &field.typ;
+        framed_read_gen(
+            field,
+            quote! { <#typ as wa_proto::Outcoming>::read(heap, args)? },
+        )
+    });
+    let validates = framed.iter().map(|field| {
+        let typ = &field.typ;
+        let validate = quote! { <#typ as wa_proto::Outcoming>::validate(heap, args)?; };
+        if field.since.is_some() {
+            quote! { if args.len() > end { #validate } }
+        } else {
+            validate
+        }
+    });
+    let frame_start = frame_start_gen();
+    let frame_end = frame_end_gen();
+    let skip_frame = skip_frame_gen();
+    let frame_open = frame_open_gen();
+    let frame_close = frame_close_gen();
+
+    quote! {
+        impl wa_proto::Outcoming for #name {
+            const IS_NEED_READ: bool = false #(|| #need)*;
+
+            // another version of the struct sends another count of args
+            const FIXED_WORDS: Option<usize> = None;
+
+            fn size_hint(&self) -> usize {
+                1 #(+ wa_proto::Outcoming::size_hint(&#access))*
+            }
+
+            wa_proto::__guest! {
+                fn args<W: wa_proto::ArgsWriter>(&self, args: &mut W) -> Result<(), wa_proto::ProtocolError> {
+                    #frame_open
+                    #(wa_proto::Outcoming::args(&#access, args)?;)*
+                    #frame_close
+                    Ok(())
+                }
+            }
+
+            wa_proto::__host! {
+                fn read(heap: &[u8], args: &mut core::slice::Iter<u32>) -> Result<Self, wa_proto::ProtocolError> {
+                    #frame_start
+                    #(#reads)*
+                    #frame_end
+                    Ok(#construct)
+                }
+
+                fn validate(heap: &[u8], args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
+                    #frame_start
+                    #(#validates)*
+                    #frame_end
+                    Ok(())
+                }
+
+                fn skip(args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
+                    #skip_frame
+                }
+            }
+        }
+    }
+}
+
+/**
+`#[coming(other)]` variant of an enum, for the tags unknown to this version.
+*/
+fn other_variant(data_enum: &syn::DataEnum) -> Option<Ident> {
+    let mut others = data_enum
+        .variants
+        .iter()
+        .filter(|variant| has_coming_flag(&variant.attrs, "other"));
+    let other = others.next()?;
+    if others.next().is_some() {
+        panic!("only one variant can be #[coming(other)]");
+    }
+    if !other.fields.is_empty() {
+        panic!("#[coming(other)] variant must be a unit variant");
+    }
+    Some(other.ident.clone())
+}
+
+/**
+Value of a simple enum from the tag: the `other` variant for the unknown tags.
+*/
+fn simple_from_u32_gen(name: &Ident, data_enum: &syn::DataEnum) -> TokenStream {
+    match other_variant(data_enum) {
+        Some(other) => quote! {
+            let pt = #name::from_u32(val).unwrap_or(#name::#other);
+        },
+        None => quote! {
+            let pt = #name::from_u32(val).ok_or_else(|| wa_proto::ProtocolError::from(wa_proto::ENUM_FROM_U32_ERROR))?;
+        },
+    }
+}
+
+/**
+Panics on `since` fields outside of `#[coming(framed)]` structs.
+*/
+fn check_since_is_framed(data: &Data) {
+    if let Data::Struct(s) = data {
+        if s.fields.iter().any(|field| coming_since(field).is_some()) {
+            panic!("#[coming(since = N)] fields need #[coming(framed)] on the struct");
+        }
+    }
+}
+
+/**
+Parts of the complex enums for `#[coming(framed)]` and `#[coming(other)]`:
+the tag, the count of the args of the value and the value.
+*/
+struct EnumFrame {
+    header_words: usize,
+    start: TokenStream,
+    end: TokenStream,
+    unknown: TokenStream,
+    skip: TokenStream,
+}
+
+fn enum_frame(ast: &DeriveInput, data_enum: &syn::DataEnum) -> EnumFrame {
+    let name = &ast.ident;
+    let framed = has_coming_flag(&ast.attrs, "framed");
+    let other = other_variant(data_enum);
+    if other.is_some() && !framed {
+        panic!(
+            "#[coming(other)] on enums with values needs #[coming(framed)] to skip unknown values"
+        );
+    }
+    let unknown = match other {
+        Some(other) => quote! { None => #name::#other, },
+        None => quote! {
+            None => return Err(wa_proto::ProtocolError::from(wa_proto::ENUM_FROM_U32_ERROR)),
+        },
+    };
+    if framed {
+        EnumFrame {
+            header_words: 2,
+            start: frame_start_gen(),
+            end: frame_end_gen(),
+            unknown,
+            skip: quote! {
+                args.next().ok_or_else(|| wa_proto::ProtocolError::from(wa_proto::ARGS_NEXT_ERROR))?;
+            },
+        }
+    } else {
+        EnumFrame {
+            header_words: 1,
+            start: TokenStream::new(),
+            end: TokenStream::new(),
+            unknown,
+            skip: TokenStream::new(),
+        }
+    }
+}
+
+/**
+PackBits for simple enums, the tag takes only the bits for the count of the variants.
+*/
+#[proc_macro_derive(PackBits)]
+pub fn derive_pack_bits(stream: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let ast = parse_macro_input!(stream as DeriveInput);
+    let name = &ast.ident;
+
+    let variants = match &ast.data {
+        Data::Enum(data_enum)
+            if data_enum
+                .variants
+                .iter()
+                .all(|variant| variant.fields.is_empty()) =>
+        {
+            data_enum.variants.len() as u32
+        }
+        _ => panic!("PackBits is supported only for enums without fields"),
+    };
+
+    let gen = quote! {
+        impl wa_proto::PackBits for #name {
+            const BITS: u32 = wa_proto::enum_bits(#variants);
+
+            fn pack(&self) -> u32 {
+                *self as u32
+            }
+
+            fn unpack(bits: u32) -> Result<Self, wa_proto::ProtocolError> {
+                #name::from_u32(bits).ok_or_else(|| wa_proto::ProtocolError::from(wa_proto::ENUM_FROM_U32_ERROR))
+            }
+        }
+    };
+
+    proc_macro::TokenStream::from(gen)
+}
+
+/**
+Schema of the type for the compatibility check between the host and the guest.
+Field names of tuple structs are their indexes.
+*/
+#[proc_macro_derive(Schema, attributes(coming))]
+pub fn derive_schema(stream: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let ast = parse_macro_input!(stream as DeriveInput);
+    let name = &ast.ident;
+    let name_str = name.to_string();
+
+    let schema = match &ast.data {
+        Data::Struct(s) => {
+            let packed = has_coming_flag(&ast.attrs, "packed");
+            let framed = has_coming_flag(&ast.attrs, "framed");
+            let fields = s.fields.iter().enumerate().map(|(index, field)| {
+                let field_name = match &field.ident {
+                    Some(ident) => ident.to_string(),
+                    None => index.to_string(),
+                };
+                let typ = &field.ty;
+                let since = coming_since(field).unwrap_or(0);
+                let bits = if packed && is_packable(field) {
+                    quote! { <#typ as wa_proto::PackBits>::BITS }
+                } else {
+                    quote! { 0 }
+                };
+                quote! {
+                    wa_proto::FieldSchema {
+                        name: #field_name,
+                        since: #since,
+                        bits: #bits,
+                        schema: <#typ as wa_proto::Schema>::SCHEMA,
+                    }
+                }
+            });
+            quote! {
+                wa_proto::TypeSchema::Struct {
+                    name: #name_str,
+                    packed: #packed,
+                    framed: #framed,
+                    fields: &[#(#fields),*],
+                }
+            }
+        }
+        Data::Enum(data_enum) => {
+            let variants = data_enum.variants.iter().map(|variant| {
+                let variant_name = variant.ident.to_string();
+                let schema = match &variant.fields {
+                    Fields::Unit => quote! { None },
+                    Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                        let typ = &fields.unnamed.first().unwrap().ty;
+                        quote! { Some(<#typ as wa_proto::Schema>::SCHEMA) }
+                    }
+                    _ => panic!("enums variants is currently support only with 1 unnamed fields"),
+                };
+                let other = has_coming_flag(&variant.attrs, "other");
+                quote! {
+                    wa_proto::VariantSchema {
+                        name: #variant_name,
+                        other: #other,
+                        schema: #schema,
+                    }
+                }
+            });
+            let framed = has_coming_flag(&ast.attrs, "framed");
+            quote! {
+                wa_proto::TypeSchema::Enum {
+                    name: #name_str,
+                    framed: #framed,
+                    variants: &[#(#variants),*],
+                }
+            }
+        }
+        Data::Union(_) => panic!("unions not supported"),
+    };
+
+    let gen = quote! {
+        impl wa_proto::Schema for #name {
+            const SCHEMA: &'static wa_proto::TypeSchema = &#schema;
+        }
+    };
+
+    proc_macro::TokenStream::from(gen)
+}
+
 #[proc_macro_derive(Incoming, attributes(coming))]
 pub fn derive_set_incoming(stream: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let ast = parse_macro_input!(stream as DeriveInput);
@@ -91,6 +796,22 @@ pub fn derive_set_incoming(stream: proc_macro::TokenStream) -> proc_macro::Token
     let name = &ast.ident;
     let data = &ast.data;
 
+    if has_coming_flag(&ast.attrs, "framed") {
+        if let Data::Struct(s) = data {
+            return proc_macro::TokenStream::from(framed_incoming(&ast, &s.fields));
+        }
+    }
+    check_since_is_framed(data);
+
+    if has_coming_flag(&ast.attrs, "packed") {
+        match data {
+            Data::Struct(s) => {
+                return proc_macro::TokenStream::from(packed_incoming(name, &s.fields))
+            }
+            _ => panic!("#[coming(packed)] is supported only for structs"),
+        }
+    }
+
     match data {
         Data::Struct(s) => {
             let fields = &s.fields;
@@ -136,28 +857,44 @@ pub fn derive_set_incoming(stream: proc_macro::TokenStream) -> proc_macro::Token
                         },
                     );
 
-                    let names = init_names.iter().map(|s| Ident::new(&s, Span::call_site()));
+                    let names = init_names.iter().map(|s| Ident::new(s, Span::call_site()));
+
+                    let fixed_words = fixed_words_gen(
+                        &quote! { wa_proto::Incoming },
+                        named.iter().map(|field| &field.ty),
+                    );
+                    let size_hint_fields = named.iter().map(|field| {
+                        let ident = &field.ident;
+                        quote! { self.#ident }
+                    });
 
                     let gen = quote! {
-                        impl Incoming for #name {
+                        impl wa_proto::Incoming for #name {
                             const IS_NEED_INIT_FILL: bool = #need_init_fill;
 
-                            #[cfg(not(feature = "std"))]
-                            fn init(args: &mut core::slice::IterMut<u32>) -> Result<(u32, Self), wa_proto::ProtocolError> {
-                                #(#init_streams)*
-                                Ok((0, #name { #(#names),* }))
+                            const FIXED_WORDS: Option<usize> = #fixed_words;
+
+                            fn size_hint(&self) -> usize {
+                                0 #(+ wa_proto::Incoming::size_hint(&#size_hint_fields))*
                             }
 
-                            #[cfg(feature = "std")]
-                            fn args(&self, args: &mut Vec<u32>) -> Result<(), wa_proto::ProtocolError> {
-                                #(#args_streams)*
-                                Ok(())
+                            wa_proto::__guest! {
+                                fn init(args: &mut core::slice::IterMut<u32>) -> Result<(u32, Self), wa_proto::ProtocolError> {
+                                    #(#init_streams)*
+                                    Ok((0, #name { #(#names),* }))
+                                }
                             }
 
-                            #[cfg(feature = "std")]
-                            fn fill(&self, heap: &mut core::cell::RefMut<[u8]>, args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
-                                #(#fill_streams)*
-                                Ok(())
+                            wa_proto::__host! {
+                                fn args(&self, args: &mut wa_proto::__private::Vec<u32>) -> Result<(), wa_proto::ProtocolError> {
+                                    #(#args_streams)*
+                                    Ok(())
+                                }
+
+                                fn fill(&self, heap: &mut core::cell::RefMut<[u8]>, args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
+                                    #(#fill_streams)*
+                                    Ok(())
+                                }
                             }
                         }
                     };
@@ -182,12 +919,12 @@ pub fn derive_set_incoming(stream: proc_macro::TokenStream) -> proc_macro::Token
                         let arg_idx_ident = Ident::new(&arg_idx, Span::call_site());
 
                         let args_ge = quote! {
-                            Incoming::args(&self.#idx_literal, args)?;
+                            wa_proto::Incoming::args(&self.#idx_literal, args)?;
                         };
                         args_streams.push(args_ge.clone());
 
                         let fill_ge = quote! {
-                            Incoming::fill(&self.#idx_literal, heap, args)?;
+                            wa_proto::Incoming::fill(&self.#idx_literal, heap, args)?;
                         };
                         fill_streams.push(fill_ge.clone());
 
@@ -211,28 +948,44 @@ pub fn derive_set_incoming(stream: proc_macro::TokenStream) -> proc_macro::Token
                         },
                     );
 
-                    let names = init_names.iter().map(|s| Ident::new(&s, Span::call_site()));
+                    let names = init_names.iter().map(|s| Ident::new(s, Span::call_site()));
+
+                    let fixed_words = fixed_words_gen(
+                        &quote! { wa_proto::Incoming },
+                        unnamed.iter().map(|field| &field.ty),
+                    );
+                    let size_hint_fields = (0..unnamed.len()).map(|index| {
+                        let index = Literal::usize_unsuffixed(index);
+                        quote! { self.#index }
+                    });
 
                     let gen = quote! {
-                        impl Incoming for #name {
+                        impl wa_proto::Incoming for #name {
                             const IS_NEED_INIT_FILL: bool = #need_init_fill;
 
-                            #[cfg(not(feature = "std"))]
-                            fn init(args: &mut core::slice::IterMut<u32>) -> Result<(u32, Self), wa_proto::ProtocolError> {
-                                #(#init_streams)*
-                                Ok((0, #name ( #(#names),* )))
+                            const FIXED_WORDS: Option<usize> = #fixed_words;
+
+                            fn size_hint(&self) -> usize {
+                                0 #(+ wa_proto::Incoming::size_hint(&#size_hint_fields))*
                             }
 
-                            #[cfg(feature = "std")]
-                            fn args(&self, args: &mut Vec<u32>) -> Result<(), wa_proto::ProtocolError> {
-                                #(#args_streams)*
-                                Ok(())
+                            wa_proto::__guest! {
+                                fn init(args: &mut core::slice::IterMut<u32>) -> Result<(u32, Self), wa_proto::ProtocolError> {
+                                    #(#init_streams)*
+                                    Ok((0, #name ( #(#names),* )))
+                                }
                             }
 
-                            #[cfg(feature = "std")]
-                            fn fill(&self, heap: &mut core::cell::RefMut<[u8]>, args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
-                                #(#fill_streams)*
-                                Ok(())
+                            wa_proto::__host! {
+                                fn args(&self, args: &mut wa_proto::__private::Vec<u32>) -> Result<(), wa_proto::ProtocolError> {
+                                    #(#args_streams)*
+                                    Ok(())
+                                }
+
+                                fn fill(&self, heap: &mut core::cell::RefMut<[u8]>, args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
+                                    #(#fill_streams)*
+                                    Ok(())
+                                }
                             }
                         }
                     };
@@ -241,20 +994,23 @@ pub fn derive_set_incoming(stream: proc_macro::TokenStream) -> proc_macro::Token
                 }
                 Fields::Unit => {
                     let gen = quote! {
-                        impl Incoming for #name {
-                            #[cfg(not(feature = "std"))]
-                            fn init(args: &mut core::slice::IterMut<u32>) -> Result<(u32, Self), wa_proto::ProtocolError> {
-                                Ok((0, #name))
-                            }
+                        impl wa_proto::Incoming for #name {
+                            const FIXED_WORDS: Option<usize> = Some(0);
 
-                            #[cfg(feature = "std")]
-                            fn args(&self, args: &mut Vec<u32>) -> Result<(), wa_proto::ProtocolError> {
-                                Ok(())
+                            wa_proto::__guest! {
+                                fn init(_args: &mut core::slice::IterMut<u32>) -> Result<(u32, Self), wa_proto::ProtocolError> {
+                                    Ok((0, #name))
+                                }
                             }
 
-                            #[cfg(feature = "std")]
-                            fn fill(&self, heap: &mut core::cell::RefMut<[u8]>, args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
-                                Ok(())
+                            wa_proto::__host! {
+                                fn args(&self, _args: &mut wa_proto::__private::Vec<u32>) -> Result<(), wa_proto::ProtocolError> {
+                                    Ok(())
+                                }
+
+                                fn fill(&self, _heap: &mut core::cell::RefMut<[u8]>, _args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
+                                    Ok(())
+                                }
                             }
                         }
                     };
@@ -266,25 +1022,29 @@ pub fn derive_set_incoming(stream: proc_macro::TokenStream) -> proc_macro::Token
             let is_simple_enum = data_enum.variants.iter().all(|item| item.fields.is_empty());
 
             if is_simple_enum {
+                let from_u32 = simple_from_u32_gen(name, data_enum);
                 let gen = quote! {
-                    impl Incoming for #name {
-                        #[cfg(not(feature = "std"))]
-                        fn init(args: &mut core::slice::IterMut<u32>) -> Result<(u32, Self), wa_proto::ProtocolError> {
-                            let val = *args.next().ok_or(wa_proto::ProtocolError(ARGS_NEXT_ERROR))?;
-                            let pt = #name::from_u32(val).ok_or(wa_proto::ProtocolError(ENUM_FROM_U32_ERROR))?;
-                            Ok((val, pt))
-                        }
+                    impl wa_proto::Incoming for #name {
+                        const FIXED_WORDS: Option<usize> = Some(1);
 
-                        #[cfg(feature = "std")]
-                        fn args(&self, args: &mut Vec<u32>) -> Result<(), wa_proto::ProtocolError> {
-                            args.push(*self as u32);
-                            Ok(())
+                        wa_proto::__guest! {
+                            fn init(args: &mut core::slice::IterMut<u32>) -> Result<(u32, Self), wa_proto::ProtocolError> {
+                                let val = *args.next().ok_or_else(|| wa_proto::ProtocolError::from(wa_proto::ARGS_NEXT_ERROR))?;
+                                #from_u32
+                                Ok((val, pt))
+                            }
                         }
 
-                        #[cfg(feature = "std")]
-                        fn fill(&self, heap: &mut core::cell::RefMut<[u8]>, args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
-                            args.next().ok_or(wa_proto::ProtocolError("args is end".to_string()))?;
-                            Ok(())
+                        wa_proto::__host! {
+                            fn args(&self, args: &mut wa_proto::__private::Vec<u32>) -> Result<(), wa_proto::ProtocolError> {
+                                args.push(*self as u32);
+                                Ok(())
+                            }
+
+                            fn fill(&self, _heap: &mut core::cell::RefMut<[u8]>, args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
+                                args.next().ok_or_else(|| wa_proto::ProtocolError::from(wa_proto::ARGS_NEXT_ERROR))?;
+                                Ok(())
+                            }
                         }
                     }
                 };
@@ -325,7 +1085,7 @@ pub fn derive_set_incoming(stream: proc_macro::TokenStream) -> proc_macro::Token
                         if let Some(inner) = inner {
                             quote! {
                                 #primitive_name::#variant_name => {
-                                    let v = <#inner>::init(args)?.1;
+                                    let v = <#inner as wa_proto::Incoming>::init(args)?.1;
                                     #name::#variant_name(v)
                                 }
                             }
@@ -345,7 +1105,7 @@ pub fn derive_set_incoming(stream: proc_macro::TokenStream) -> proc_macro::Token
                         if inner.is_some() {
                             quote! {
                                 #name::#variant_name(value) => {
-                                    value.args(args)?;
+                                    wa_proto::Incoming::args(value, args)?;
                                 }
                             }
                         } else {
@@ -362,7 +1122,7 @@ pub fn derive_set_incoming(stream: proc_macro::TokenStream) -> proc_macro::Token
                         if inner.is_some() {
                             quote! {
                                 #name::#variant_name(value) => {
-                                    value.fill(heap, args)?;
+                                    wa_proto::Incoming::fill(value, heap, args)?;
                                 }
                             }
                         } else {
@@ -392,36 +1152,76 @@ pub fn derive_set_incoming(stream: proc_macro::TokenStream) -> proc_macro::Token
                             acc
                         });
 
+                let size_hint_items = variants_names.iter().map(|(variant_name, inner)| {
+                    if inner.is_some() {
+                        quote! {
+                            #name::#variant_name(value) => wa_proto::Incoming::size_hint(value),
+                        }
+                    } else {
+                        quote! {
+                            #name::#variant_name => 0,
+                        }
+                    }
+                });
+
+                let EnumFrame {
+                    header_words,
+                    start,
+                    end,
+                    unknown,
+                    skip,
+                } = enum_frame(&ast, data_enum);
+                let (frame_open, frame_close) = if header_words == 2 {
+                    (frame_open_gen(), frame_close_gen())
+                } else {
+                    (TokenStream::new(), TokenStream::new())
+                };
+
                 let gen = quote! {
-                    impl Incoming for #name {
+                    impl wa_proto::Incoming for #name {
                         const IS_NEED_INIT_FILL: bool = #need_init_fill;
 
-                        #[cfg(not(feature = "std"))]
-                        fn init(args: &mut core::slice::IterMut<u32>) -> Result<(u32, Self), wa_proto::ProtocolError> {
-                            let val = *args.next().ok_or(wa_proto::ProtocolError(ARGS_NEXT_ERROR))?;
-                            let pt: #primitive_name = FromPrimitive::from_u32(val).ok_or(wa_proto::ProtocolError(ENUM_FROM_U32_ERROR))?;
-                            let item = match pt {
-                                #(#init_items)*
-                            };
-                            Ok((val, item))
+                        fn size_hint(&self) -> usize {
+                            #header_words + match self {
+                                #(#size_hint_items)*
+                            }
                         }
 
-                        #[cfg(feature = "std")]
-                        fn args(&self, args: &mut Vec<u32>) -> Result<(), wa_proto::ProtocolError> {
-                            args.push(self.get_primitive_enum() as u32);
-                            match self {
-                                #(#args_in_items)*
+                        wa_proto::__guest! {
+                            fn init(args: &mut core::slice::IterMut<u32>) -> Result<(u32, Self), wa_proto::ProtocolError> {
+                                let val = *args.next().ok_or_else(|| wa_proto::ProtocolError::from(wa_proto::ARGS_NEXT_ERROR))?;
+                                #start
+                                let pt: Option<#primitive_name> = FromPrimitive::from_u32(val);
+                                let item = match pt {
+                                    Some(pt) => match pt {
+                                        #(#init_items)*
+                                    },
+                                    #unknown
+                                };
+                                #end
+                                Ok((val, item))
                             }
-                            Ok(())
                         }
 
-                        #[cfg(feature = "std")]
-                        fn fill(&self, heap: &mut core::cell::RefMut<[u8]>, args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
-                            args.next().ok_or(wa_proto::ProtocolError("args is end".to_string()))?;
-                            match self {
-                                #(#fill_items)*
+                        wa_proto::__host! {
+                            fn args(&self, args: &mut wa_proto::__private::Vec<u32>) -> Result<(), wa_proto::ProtocolError> {
+                                args.push(self.get_primitive_enum() as u32);
+                                #frame_open
+                                match self {
+                                    #(#args_in_items)*
+                                }
+                                #frame_close
+                                Ok(())
+                            }
+
+                            fn fill(&self, heap: &mut core::cell::RefMut<[u8]>, args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
+                                args.next().ok_or_else(|| wa_proto::ProtocolError::from(wa_proto::ARGS_NEXT_ERROR))?;
+                                #skip
+                                match self {
+                                    #(#fill_items)*
+                                }
+                                Ok(())
                             }
-                            Ok(())
                         }
                     }
                 };
@@ -436,13 +1236,121 @@ pub fn derive_set_incoming(stream: proc_macro::TokenStream) -> proc_macro::Token
     }
 }
 
+/**
+OutcomingRef of a derived type without references, through its Outcoming,
+so the type can be a field of a struct with references.
+*/
+fn outcoming_ref_by_value(name: &Ident) -> TokenStream {
+    quote! {
+        impl<'a, 'b> wa_proto::OutcomingRef<'a, 'b> for #name {
+            fn size_hint(&self) -> usize {
+                wa_proto::Outcoming::size_hint(self)
+            }
+
+            wa_proto::__guest! {
+                fn args<W: wa_proto::ArgsWriter>(&self, args: &mut W) -> Result<(), wa_proto::ProtocolError> {
+                    wa_proto::Outcoming::args(self, args)
+                }
+            }
+
+            wa_proto::__host! {
+                fn read_ref(heap: &'a [u8], args: &mut core::slice::Iter<'b, u32>) -> Result<Self, wa_proto::ProtocolError> {
+                    <Self as wa_proto::Outcoming>::read(heap, args)
+                }
+            }
+        }
+    }
+}
+
+/**
+OutcomingRef for a struct with references like `&'a str` and `&'a [u8]`,
+the host reads them from the memory of the guest without copying.
+*/
+fn borrowed_outcoming(ast: &DeriveInput) -> TokenStream {
+    let name = &ast.ident;
+    let fields = match &ast.data {
+        Data::Struct(s) => &s.fields,
+        _ => panic!("only structs can have references"),
+    };
+    let mut lifetimes = ast.generics.lifetimes();
+    let lifetime = &lifetimes.next().unwrap().lifetime;
+    if lifetimes.next().is_some() || ast.generics.type_params().next().is_some() {
+        panic!("structs with references can have only one lifetime parameter");
+    }
+    if has_coming_flag(&ast.attrs, "packed") || has_coming_flag(&ast.attrs, "framed") {
+        panic!("structs with references can't be #[coming(packed)] or #[coming(framed)]");
+    }
+    check_since_is_framed(&ast.data);
+    let (access, locals): (Vec<TokenStream>, Vec<Ident>) = fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| field_access(index, field))
+        .unzip();
+    let types: Vec<&Type> = fields.iter().map(|field| &field.ty).collect();
+    let construct = construct_gen(name, fields, &locals);
+
+    // the args lifetime is free unless a field borrows the args too
+    quote! {
+        impl<#lifetime, 'wa_args> wa_proto::OutcomingRef<#lifetime, 'wa_args> for #name<#lifetime>
+        where
+            #(#types: wa_proto::OutcomingRef<#lifetime, 'wa_args>,)*
+        {
+            fn size_hint(&self) -> usize {
+                0 #(+ wa_proto::OutcomingRef::size_hint(&#access))*
+            }
+
+            wa_proto::__guest! {
+                fn args<W: wa_proto::ArgsWriter>(&self, args: &mut W) -> Result<(), wa_proto::ProtocolError> {
+                    #(wa_proto::OutcomingRef::args(&#access, args)?;)*
+                    Ok(())
+                }
+            }
+
+            wa_proto::__host! {
+                fn read_ref(heap: &#lifetime [u8], args: &mut core::slice::Iter<'wa_args, u32>) -> Result<Self, wa_proto::ProtocolError> {
+                    #(let #locals = <#types as wa_proto::OutcomingRef<#lifetime, 'wa_args>>::read_ref(heap, args)?;)*
+                    Ok(#construct)
+                }
+            }
+        }
+    }
+}
+
+/**
+Outcoming of a type without references, and OutcomingRef through it.
+A struct with a lifetime parameter gets only OutcomingRef.
+*/
 #[proc_macro_derive(Outcoming, attributes(coming))]
 pub fn derive_set_outcoming(stream: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let ast = parse_macro_input!(stream as DeriveInput);
+    if ast.generics.lifetimes().next().is_some() {
+        return proc_macro::TokenStream::from(borrowed_outcoming(&ast));
+    }
+    let mut gen = TokenStream::from(outcoming(&ast));
+    gen.extend(outcoming_ref_by_value(&ast.ident));
+    proc_macro::TokenStream::from(gen)
+}
 
+fn outcoming(ast: &DeriveInput) -> proc_macro::TokenStream {
     let name = &ast.ident;
     let data = &ast.data;
 
+    if has_coming_flag(&ast.attrs, "framed") {
+        if let Data::Struct(s) = data {
+            return proc_macro::TokenStream::from(framed_outcoming(ast, &s.fields));
+        }
+    }
+    check_since_is_framed(data);
+
+    if has_coming_flag(&ast.attrs, "packed") {
+        match data {
+            Data::Struct(s) => {
+                return proc_macro::TokenStream::from(packed_outcoming(name, &s.fields))
+            }
+            _ => panic!("#[coming(packed)] is supported only for structs"),
+        }
+    }
+
     match data {
         Data::Struct(data_struct) => {
             let fields = &data_struct.fields;
@@ -484,22 +1392,50 @@ pub fn derive_set_outcoming(stream: proc_macro::TokenStream) -> proc_macro::Toke
                                 acc
                             });
 
-                    let names = init_names.iter().map(|s| Ident::new(&s, Span::call_site()));
+                    let names = init_names.iter().map(|s| Ident::new(s, Span::call_site()));
+                    let fixed_words = fixed_words_gen(
+                        &quote! { wa_proto::Outcoming },
+                        named.iter().map(|field| &field.ty),
+                    );
+                    let field_types: Vec<&Type> = named.iter().map(|field| &field.ty).collect();
+                    let size_hint_fields = named.iter().map(|field| {
+                        let ident = &field.ident;
+                        quote! { self.#ident }
+                    });
+
                     let gen = quote! {
-                        impl Outcoming for #name {
+                        impl wa_proto::Outcoming for #name {
                             const IS_NEED_READ: bool = #is_need_read_stream;
 
-                            #[cfg(not(feature = "std"))]
-                            fn args(&self, args: &mut Vec<u32>) -> Result<(), wa_proto::ProtocolError> {
-                                #(#args_streams)*
-                                Ok(())
+                            const FIXED_WORDS: Option<usize> = #fixed_words;
+
+                            fn size_hint(&self) -> usize {
+                                0 #(+ wa_proto::Outcoming::size_hint(&#size_hint_fields))*
                             }
 
-                            #[cfg(feature = "std")]
-                            fn read(heap: &[u8], args: &mut core::slice::Iter<u32>) -> Result<Self, wa_proto::ProtocolError> where Self: Sized {
-                                #(#read_streams)*
+                            wa_proto::__guest! {
+                                fn args<W: wa_proto::ArgsWriter>(&self, args: &mut W) -> Result<(), wa_proto::ProtocolError> {
+                                    #(#args_streams)*
+                                    Ok(())
+                                }
+                            }
 
-                                Ok(#name { #(#names),* })
+                            wa_proto::__host! {
+                                fn read(heap: &[u8], args: &mut core::slice::Iter<u32>) -> Result<Self, wa_proto::ProtocolError> where Self: Sized {
+                                    #(#read_streams)*
+
+                                    Ok(#name { #(#names),* })
+                                }
+
+                                fn validate(heap: &[u8], args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
+                                    #(<#field_types as wa_proto::Outcoming>::validate(heap, args)?;)*
+                                    Ok(())
+                                }
+
+                                fn skip(args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
+                                    #(<#field_types as wa_proto::Outcoming>::skip(args)?;)*
+                                    Ok(())
+                                }
                             }
                         }
                     };
@@ -524,7 +1460,7 @@ pub fn derive_set_outcoming(stream: proc_macro::TokenStream) -> proc_macro::Toke
                         let arg_idx_ident = Ident::new(&arg_idx, Span::call_site());
 
                         let args_ge = quote! {
-                            Outcoming::args(&self.#idx_literal, args)?;
+                            wa_proto::Outcoming::args(&self.#idx_literal, args)?;
                         };
                         args_streams.push(args_ge.clone());
 
@@ -549,22 +1485,50 @@ pub fn derive_set_outcoming(stream: proc_macro::TokenStream) -> proc_macro::Toke
                                 acc
                             });
 
-                    let names = init_names.iter().map(|s| Ident::new(&s, Span::call_site()));
+                    let names = init_names.iter().map(|s| Ident::new(s, Span::call_site()));
+                    let fixed_words = fixed_words_gen(
+                        &quote! { wa_proto::Outcoming },
+                        unnamed.iter().map(|field| &field.ty),
+                    );
+                    let field_types: Vec<&Type> = unnamed.iter().map(|field| &field.ty).collect();
+                    let size_hint_fields = (0..unnamed.len()).map(|index| {
+                        let index = Literal::usize_unsuffixed(index);
+                        quote! { self.#index }
+                    });
+
                     let gen = quote! {
-                        impl Outcoming for #name {
+                        impl wa_proto::Outcoming for #name {
                             const IS_NEED_READ: bool = #is_need_read_stream;
 
-                            #[cfg(not(feature = "std"))]
-                            fn args(&self, args: &mut alloc::vec::Vec<u32>) -> Result<(), wa_proto::ProtocolError> {
-                                #(#args_streams)*
-                                Ok(())
+                            const FIXED_WORDS: Option<usize> = #fixed_words;
+
+                            fn size_hint(&self) -> usize {
+                                0 #(+ wa_proto::Outcoming::size_hint(&#size_hint_fields))*
                             }
 
-                            #[cfg(feature = "std")]
-                            fn read(heap: &[u8], args: &mut core::slice::Iter<u32>) -> Result<Self, wa_proto::ProtocolError> {
-                                #(#read_streams)*
+                            wa_proto::__guest! {
+                                fn args<W: wa_proto::ArgsWriter>(&self, args: &mut W) -> Result<(), wa_proto::ProtocolError> {
+                                    #(#args_streams)*
+                                    Ok(())
+                                }
+                            }
+
+                            wa_proto::__host! {
+                                fn read(heap: &[u8], args: &mut core::slice::Iter<u32>) -> Result<Self, wa_proto::ProtocolError> {
+                                    #(#read_streams)*
+
+                                    Ok(#name ( #(#names),* ))
+                                }
 
-                                Ok(#name ( #(#names),* ))
+                                fn validate(heap: &[u8], args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
+                                    #(<#field_types as wa_proto::Outcoming>::validate(heap, args)?;)*
+                                    Ok(())
+                                }
+
+                                fn skip(args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
+                                    #(<#field_types as wa_proto::Outcoming>::skip(args)?;)*
+                                    Ok(())
+                                }
                             }
                         }
                     };
@@ -573,15 +1537,19 @@ pub fn derive_set_outcoming(stream: proc_macro::TokenStream) -> proc_macro::Toke
                 }
                 Fields::Unit => {
                     let gen = quote! {
-                        impl Outcoming for #name {
-                            #[cfg(not(feature = "std"))]
-                            fn args(&self, args: &mut alloc::vec::Vec<u32>) -> Result<(), wa_proto::ProtocolError> {
-                                Ok(())
+                        impl wa_proto::Outcoming for #name {
+                            const FIXED_WORDS: Option<usize> = Some(0);
+
+                            wa_proto::__guest! {
+                                fn args<W: wa_proto::ArgsWriter>(&self, _args: &mut W) -> Result<(), wa_proto::ProtocolError> {
+                                    Ok(())
+                                }
                             }
 
-                            #[cfg(feature = "std")]
-                            fn read(heap: &[u8], args: &mut core::slice::Iter<u32>) -> Result<Self, wa_proto::ProtocolError> {
-                                Ok(#name)
+                            wa_proto::__host! {
+                                fn read(_heap: &[u8], _args: &mut core::slice::Iter<u32>) -> Result<Self, wa_proto::ProtocolError> {
+                                    Ok(#name)
+                                }
                             }
                         }
                     };
@@ -594,26 +1562,31 @@ pub fn derive_set_outcoming(stream: proc_macro::TokenStream) -> proc_macro::Toke
             let is_simple_enum = data_enum.variants.iter().all(|item| item.fields.is_empty());
 
             if is_simple_enum {
+                let from_u32 = simple_from_u32_gen(name, data_enum);
                 let gen = quote! {
-                    impl Outcoming for #name {
-                        #[cfg(not(feature = "std"))]
-                        fn args(&self, args: &mut alloc::vec::Vec<u32>) -> Result<(), wa_proto::ProtocolError> {
-                            args.push(*self as u32);
-                            Ok(())
+                    impl wa_proto::Outcoming for #name {
+                        const FIXED_WORDS: Option<usize> = Some(1);
+
+                        wa_proto::__guest! {
+                            fn args<W: wa_proto::ArgsWriter>(&self, args: &mut W) -> Result<(), wa_proto::ProtocolError> {
+                                args.push(*self as u32)?;
+                                Ok(())
+                            }
                         }
 
-                        #[cfg(feature = "std")]
-                        fn read(heap: &[u8], args: &mut core::slice::Iter<u32>) -> Result<Self, wa_proto::ProtocolError> {
-                            let val = *args.next().ok_or(wa_proto::ProtocolError("args is end".to_string()))?;
-                            let pt = #name::from_u32(val).ok_or(wa_proto::ProtocolError("#name enum type error".to_string()))?;
-                            Ok(pt)
+                        wa_proto::__host! {
+                            fn read(_heap: &[u8], args: &mut core::slice::Iter<u32>) -> Result<Self, wa_proto::ProtocolError> {
+                                let val = *args.next().ok_or_else(|| wa_proto::ProtocolError::from(wa_proto::ARGS_NEXT_ERROR))?;
+                                #from_u32
+                                Ok(pt)
+                            }
                         }
                     }
                 };
 
                 proc_macro::TokenStream::from(gen)
             } else {
-                let primitive_name = get_primitive_name(&ast);
+                let primitive_name = get_primitive_name(ast);
                 let mut variants_names: Vec<(TokenStream, Option<Field>)> =
                     Vec::with_capacity(data_enum.variants.len());
 
@@ -647,7 +1620,7 @@ pub fn derive_set_outcoming(stream: proc_macro::TokenStream) -> proc_macro::Toke
                         if inner.is_some() {
                             quote! {
                                 #name::#variant_name(value) => {
-                                    value.args(args)?;
+                                    wa_proto::Outcoming::args(value, args)?;
                                 }
                             }
                         } else {
@@ -664,7 +1637,7 @@ pub fn derive_set_outcoming(stream: proc_macro::TokenStream) -> proc_macro::Toke
                         if let Some(inner) = inner {
                             quote! {
                                 #primitive_name::#variant_name => {
-                                    let v = <#inner>::read(heap, args)?;
+                                    let v = <#inner as wa_proto::Outcoming>::read(heap, args)?;
                                     #name::#variant_name(v)
                                 }
                             }
@@ -678,6 +1651,31 @@ pub fn derive_set_outcoming(stream: proc_macro::TokenStream) -> proc_macro::Toke
                     })
                     .collect();
 
+                let check_items = |method: TokenStream| -> Vec<TokenStream> {
+                    variants_names
+                        .iter()
+                        .map(|(variant_name, inner)| match inner {
+                            Some(inner) => {
+                                let inner = &inner.ty;
+                                quote! {
+                                    #primitive_name::#variant_name => <#inner as wa_proto::Outcoming>::#method?,
+                                }
+                            }
+                            None => quote! {
+                                #primitive_name::#variant_name => {}
+                            },
+                        })
+                        .collect()
+                };
+                let validate_items = check_items(quote! { validate(heap, args) });
+                let skip_items = check_items(quote! { skip(args) });
+                let unknown_tag = match other_variant(data_enum) {
+                    Some(_) => quote! { None => {} },
+                    None => quote! {
+                        None => return Err(wa_proto::ProtocolError::from(wa_proto::ENUM_FROM_U32_ERROR)),
+                    },
+                };
+
                 let need_read: Vec<TokenStream> = variants_names
                     .iter()
                     .filter_map(|(_, inner)| {
@@ -696,27 +1694,105 @@ pub fn derive_set_outcoming(stream: proc_macro::TokenStream) -> proc_macro::Toke
                         acc
                     });
 
+                let size_hint_items = variants_names.iter().map(|(variant_name, inner)| {
+                    if inner.is_some() {
+                        quote! {
+                            #name::#variant_name(value) => wa_proto::Outcoming::size_hint(value),
+                        }
+                    } else {
+                        quote! {
+                            #name::#variant_name => 0,
+                        }
+                    }
+                });
+
+                let EnumFrame {
+                    header_words,
+                    start,
+                    end,
+                    unknown,
+                    ..
+                } = enum_frame(ast, data_enum);
+                let (frame_open, frame_close) = if header_words == 2 {
+                    (frame_open_gen(), frame_close_gen())
+                } else {
+                    (TokenStream::new(), TokenStream::new())
+                };
+                // the value of a framed enum is skipped by its frame, without looking at the tag
+                let skip = if header_words == 2 {
+                    let skip_frame = skip_frame_gen();
+                    quote! {
+                        args.next().ok_or_else(|| wa_proto::ProtocolError::from(wa_proto::ARGS_NEXT_ERROR))?;
+                        #skip_frame
+                    }
+                } else {
+                    quote! {
+                        let val = *args.next().ok_or_else(|| wa_proto::ProtocolError::from(wa_proto::ARGS_NEXT_ERROR))?;
+                        let pt: Option<#primitive_name> = FromPrimitive::from_u32(val);
+                        match pt {
+                            Some(pt) => match pt {
+                                #(#skip_items)*
+                            },
+                            #unknown_tag
+                        }
+                        Ok(())
+                    }
+                };
+
                 let gen = quote! {
-                    impl Outcoming for #name {
+                    impl wa_proto::Outcoming for #name {
                         const IS_NEED_READ: bool = #need_read;
 
-                        #[cfg(not(feature = "std"))]
-                        fn args(&self, args: &mut alloc::vec::Vec<u32>) -> Result<(), wa_proto::ProtocolError> {
-                            args.push(self.get_primitive_enum() as u32);
-                            match self {
-                                #(#args_in_items)*
+                        fn size_hint(&self) -> usize {
+                            #header_words + match self {
+                                #(#size_hint_items)*
                             }
-                            Ok(())
                         }
 
-                        #[cfg(feature = "std")]
-                        fn read(heap: &[u8], args: &mut core::slice::Iter<u32>) -> Result<Self, wa_proto::ProtocolError> {
-                            let val = *args.next().ok_or(wa_proto::ProtocolError("args is end".to_string()))?;
-                            let pt: #primitive_name = FromPrimitive::from_u32(val).ok_or(wa_proto::ProtocolError("#name enum type error".to_string()))?;
-                            let t = match pt {
-                                #(#read_items)*
-                            };
-                            Ok(t)
+                        wa_proto::__guest! {
+                            fn args<W: wa_proto::ArgsWriter>(&self, args: &mut W) -> Result<(), wa_proto::ProtocolError> {
+                                args.push(self.get_primitive_enum() as u32)?;
+                                #frame_open
+                                match self {
+                                    #(#args_in_items)*
+                                }
+                                #frame_close
+                                Ok(())
+                            }
+                        }
+
+                        wa_proto::__host! {
+                            fn read(heap: &[u8], args: &mut core::slice::Iter<u32>) -> Result<Self, wa_proto::ProtocolError> {
+                                let val = *args.next().ok_or_else(|| wa_proto::ProtocolError::from(wa_proto::ARGS_NEXT_ERROR))?;
+                                #start
+                                let pt: Option<#primitive_name> = FromPrimitive::from_u32(val);
+                                let t = match pt {
+                                    Some(pt) => match pt {
+                                        #(#read_items)*
+                                    },
+                                    #unknown
+                                };
+                                #end
+                                Ok(t)
+                            }
+
+                            fn validate(heap: &[u8], args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
+                                let val = *args.next().ok_or_else(|| wa_proto::ProtocolError::from(wa_proto::ARGS_NEXT_ERROR))?;
+                                #start
+                                let pt: Option<#primitive_name> = FromPrimitive::from_u32(val);
+                                match pt {
+                                    Some(pt) => match pt {
+                                        #(#validate_items)*
+                                    },
+                                    #unknown_tag
+                                }
+                                #end
+                                Ok(())
+                            }
+
+                            fn skip(args: &mut core::slice::Iter<u32>) -> Result<(), wa_proto::ProtocolError> {
+                                #skip
+                            }
                         }
                     }
                 };